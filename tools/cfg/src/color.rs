@@ -1,11 +1,15 @@
 use serde::{Deserialize, Serialize};
 
-/// Represents an RGB color
+/// Represents an RGB color, optionally with an alpha channel
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
     pub b: u8,
+    /// Opacity 0-1. `None` for plain opaque colors, which format (and
+    /// serialize) exactly as they did before colors had alpha.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub a: Option<f32>,
 }
 
 impl std::fmt::Display for Color {
@@ -15,84 +19,220 @@ impl std::fmt::Display for Color {
 }
 
 impl Color {
-    /// Parse a hex color string (with or without #)
+    /// Parse a hex color string (with or without #): rgb, rgba, rrggbb or
+    /// rrggbbaa
     pub fn from_hex(hex: &str) -> Result<Self, String> {
-        let hex = hex.trim_start_matches('#');
-        if hex.len() != 6 {
-            return Err(format!("Invalid hex color: {}", hex));
+        let digits = hex.trim_start_matches('#');
+        if !digits.is_ascii() {
+            return Err(format!("Invalid hex color: {}", digits));
         }
+        let channels: Vec<u8> = match digits.len() {
+            // #rgb(a): each digit is doubled, f -> ff
+            3 | 4 => digits
+                .chars()
+                .map(|c| c.to_digit(16).map(|v| (v * 17) as u8))
+                .collect::<Option<_>>(),
+            6 | 8 => (0..digits.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+                .collect::<Option<_>>(),
+            _ => None,
+        }
+        .ok_or_else(|| format!("Invalid hex color: {}", digits))?;
         Ok(Color {
-            r: u8::from_str_radix(&hex[0..2], 16).map_err(|e| e.to_string())?,
-            g: u8::from_str_radix(&hex[2..4], 16).map_err(|e| e.to_string())?,
-            b: u8::from_str_radix(&hex[4..6], 16).map_err(|e| e.to_string())?,
+            r: channels[0],
+            g: channels[1],
+            b: channels[2],
+            a: channels.get(3).map(|&a| a as f32 / 255.0),
         })
     }
 
-    /// Output as hex without # (e.g., "89b4fa")
+    /// Parse any color notation cfg accepts: hex (see `from_hex`), CSS
+    /// `rgb()`/`rgba()` and `hsl()`/`hsla()`, with commas or the
+    /// space-and-slash syntax (`rgb(137 180 250 / 50%)`).
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let invalid = || format!("Invalid color: {}", value);
+        let Some((name, args)) = value
+            .strip_suffix(')')
+            .and_then(|rest| rest.split_once('('))
+        else {
+            return Color::from_hex(value);
+        };
+        let args: Vec<&str> = args
+            .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+            .filter(|arg| !arg.is_empty())
+            .collect();
+        if args.len() != 3 && args.len() != 4 {
+            return Err(invalid());
+        }
+        let alpha = match args.get(3) {
+            Some(arg) => Some(parse_unit(arg, 1.0).ok_or_else(invalid)? as f32),
+            None => None,
+        };
+        let color = match name.trim().to_ascii_lowercase().as_str() {
+            "rgb" | "rgba" => {
+                let mut rgb = [0; 3];
+                for (channel, arg) in rgb.iter_mut().zip(&args) {
+                    *channel = (parse_unit(arg, 255.0).ok_or_else(invalid)? * 255.0).round() as u8;
+                }
+                Color {
+                    r: rgb[0],
+                    g: rgb[1],
+                    b: rgb[2],
+                    a: None,
+                }
+            }
+            "hsl" | "hsla" => {
+                let h: f64 = args[0]
+                    .strip_suffix("deg")
+                    .unwrap_or(args[0])
+                    .parse()
+                    .map_err(|_| invalid())?;
+                // Saturation and lightness are percentages, with or without %
+                let percent = |arg: &str| {
+                    let v: f64 = arg.strip_suffix('%').unwrap_or(arg).parse().ok()?;
+                    (0.0..=100.0).contains(&v).then_some(v / 100.0)
+                };
+                Color::from_hsl(Hsl {
+                    h: h.rem_euclid(360.0),
+                    s: percent(args[1]).ok_or_else(invalid)?,
+                    l: percent(args[2]).ok_or_else(invalid)?,
+                })
+            }
+            _ => return Err(invalid()),
+        };
+        Ok(color.with_alpha(alpha))
+    }
+
+    /// Whether `value` is written as a color (hex with #, or a CSS color
+    /// function) rather than a plain string
+    pub fn is_literal(value: &str) -> bool {
+        let value = value.trim_start().to_ascii_lowercase();
+        value.starts_with('#')
+            || ["rgb(", "rgba(", "hsl(", "hsla("]
+                .iter()
+                .any(|prefix| value.starts_with(prefix))
+    }
+
+    /// The same color with `alpha` (0-1, `None` for opaque)
+    pub fn with_alpha(self, alpha: Option<f32>) -> Color {
+        Color {
+            a: alpha.map(|a| a.clamp(0.0, 1.0)),
+            ..self
+        }
+    }
+
+    /// Opacity 0-1 (1 when no alpha is set)
+    pub fn alpha(&self) -> f32 {
+        self.a.unwrap_or(1.0)
+    }
+
+    /// Alpha as a hex byte, if set (e.g., "e6" for 0.9)
+    fn alpha_hex(&self) -> String {
+        self.a
+            .map(|a| format!("{:02x}", alpha_byte(a)))
+            .unwrap_or_default()
+    }
+
+    /// Output as hex without # (e.g., "89b4fa", or "89b4fae6" with alpha)
     pub fn to_hex(&self) -> String {
-        format!("{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+        format!(
+            "{:02x}{:02x}{:02x}{}",
+            self.r,
+            self.g,
+            self.b,
+            self.alpha_hex()
+        )
     }
 
     /// Output as hex without # in uppercase (e.g., "89B4FA")
     pub fn to_hex_upper(&self) -> String {
-        format!("{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+        self.to_hex().to_uppercase()
     }
 
     /// Output as hex without # in lowercase (e.g., "89b4fa")
     pub fn to_hex_lower(&self) -> String {
-        format!("{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+        self.to_hex()
     }
 
     /// Output as hex with # (e.g., "#89b4fa")
     pub fn to_hex_hash(&self) -> String {
-        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+        format!("#{}", self.to_hex())
     }
 
-    /// Output as space-separated RGB (e.g., "137 180 250")
+    /// Output as space-separated RGB (e.g., "137 180 250"), with alpha
+    /// 0-1 appended when set
     pub fn to_rgb(&self) -> String {
-        format!("{} {} {}", self.r, self.g, self.b)
+        match self.a {
+            Some(a) => format!("{} {} {} {}", self.r, self.g, self.b, css_alpha(a)),
+            None => format!("{} {} {}", self.r, self.g, self.b),
+        }
     }
 
-    /// Output as CSS rgb() (e.g., "rgb(137, 180, 250)")
+    /// Output as CSS rgb() (e.g., "rgb(137, 180, 250)"), or rgba() when
+    /// alpha is set
     pub fn to_rgb_css(&self) -> String {
-        format!("rgb({}, {}, {})", self.r, self.g, self.b)
+        match self.a {
+            Some(a) => self.to_rgba(a),
+            None => format!("rgb({}, {}, {})", self.r, self.g, self.b),
+        }
     }
 
-    /// Output as Hyprlang format (e.g., "rgb(89b4fa)")
+    /// Output as Hyprlang format (e.g., "rgb(89b4fa)"), or rgba() when
+    /// alpha is set
     pub fn to_hyprlang(&self) -> String {
-        format!("rgb({:02x}{:02x}{:02x})", self.r, self.g, self.b)
+        match self.a {
+            Some(a) => self.to_hyprlang_rgba(a),
+            None => format!("rgb({:02x}{:02x}{:02x})", self.r, self.g, self.b),
+        }
     }
 
     /// Output as RGBA with alpha (e.g., "rgba(137, 180, 250, 0.9)")
     pub fn to_rgba(&self, alpha: f32) -> String {
-        format!("rgba({}, {}, {}, {})", self.r, self.g, self.b, alpha)
+        format!(
+            "rgba({}, {}, {}, {})",
+            self.r,
+            self.g,
+            self.b,
+            css_alpha(alpha)
+        )
     }
 
     /// Output as Hyprlang RGBA (e.g., "rgba(89b4fae6)" where e6 = 0.9 * 255)
     pub fn to_hyprlang_rgba(&self, alpha: f32) -> String {
-        let alpha_byte = (alpha * 255.0).round() as u8;
         format!(
             "rgba({:02x}{:02x}{:02x}{:02x})",
-            self.r, self.g, self.b, alpha_byte
+            self.r,
+            self.g,
+            self.b,
+            alpha_byte(alpha)
         )
     }
 
     /// Output as Qt ARGB format (e.g., "#ff89b4fa")
     pub fn to_hex_argb(&self, alpha: f32) -> String {
-        let alpha_byte = (alpha * 255.0).round() as u8;
         format!(
             "#{:02x}{:02x}{:02x}{:02x}",
-            alpha_byte, self.r, self.g, self.b
+            alpha_byte(alpha),
+            self.r,
+            self.g,
+            self.b
         )
     }
 
-    /// Blend this color with another (amount = percentage of this color, 0-100)
+    /// Blend this color with another (amount = percentage of this color, 0-100).
+    /// Alpha is blended too; the result is opaque if both colors are.
     pub fn blend(&self, other: &Color, amount: u8) -> Color {
         let ratio = amount as f32 / 100.0;
         Color {
             r: ((self.r as f32 * ratio) + (other.r as f32 * (1.0 - ratio))).round() as u8,
             g: ((self.g as f32 * ratio) + (other.g as f32 * (1.0 - ratio))).round() as u8,
             b: ((self.b as f32 * ratio) + (other.b as f32 * (1.0 - ratio))).round() as u8,
+            a: match (self.a, other.a) {
+                (None, None) => None,
+                _ => Some(self.alpha() * ratio + other.alpha() * (1.0 - ratio)),
+            },
         }
     }
 
@@ -103,6 +243,7 @@ impl Color {
             r: 255,
             g: 255,
             b: 255,
+            a: self.a,
         };
         self.blend(&white, 100 - amount)
     }
@@ -110,9 +251,353 @@ impl Color {
     /// Darken this color by a percentage (0-100)
     /// amount = 0: no change, amount = 100: pure black
     pub fn darken(&self, amount: u8) -> Color {
-        let black = Color { r: 0, g: 0, b: 0 };
+        let black = Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: self.a,
+        };
         self.blend(&black, 100 - amount)
     }
+
+    /// Lighten (or, with a negative amount, darken) by `amount` points of
+    /// OKLCH lightness (0-100), keeping hue and chroma. Unlike `lighten`,
+    /// accents don't wash out toward white.
+    pub fn lighten_l(&self, amount: f64) -> Color {
+        let lch = self.to_oklch();
+        Color::from_oklch(Oklch {
+            l: (lch.l + amount / 100.0).clamp(0.0, 1.0),
+            ..lch
+        })
+        .with_alpha(self.a)
+    }
+
+    /// Add `amount` points (0-100) of HSL saturation; negative desaturates.
+    pub fn saturate(&self, amount: f64) -> Color {
+        let hsl = self.to_hsl();
+        Color::from_hsl(Hsl {
+            s: (hsl.s + amount / 100.0).clamp(0.0, 1.0),
+            ..hsl
+        })
+        .with_alpha(self.a)
+    }
+
+    /// Remove `amount` points (0-100) of HSL saturation.
+    pub fn desaturate(&self, amount: f64) -> Color {
+        self.saturate(-amount)
+    }
+
+    /// Rotate the OKLCH hue by `degrees`, keeping lightness and chroma.
+    pub fn rotate_hue(&self, degrees: f64) -> Color {
+        let lch = self.to_oklch();
+        Color::from_oklch(Oklch {
+            h: (lch.h + degrees).rem_euclid(360.0),
+            ..lch
+        })
+        .with_alpha(self.a)
+    }
+
+    /// Mix with another color in OKLab (amount = percentage of this color,
+    /// 0-100, like `blend`). Mixing two accents doesn't dip through grey.
+    pub fn mix_oklab(&self, other: &Color, amount: f64) -> Color {
+        let t = (amount / 100.0).clamp(0.0, 1.0);
+        let (a, b) = (self.to_oklab(), other.to_oklab());
+        let alpha = match (self.a, other.a) {
+            (None, None) => None,
+            _ => Some(self.alpha() * t as f32 + other.alpha() * (1.0 - t as f32)),
+        };
+        Color::from_oklab(Oklab {
+            l: a.l * t + b.l * (1.0 - t),
+            a: a.a * t + b.a * (1.0 - t),
+            b: a.b * t + b.b * (1.0 - t),
+        })
+        .with_alpha(alpha)
+    }
+
+    /// Set OKLCH chroma (0 is grey, Catppuccin accents are around 0.1-0.15),
+    /// keeping lightness and hue.
+    pub fn with_chroma(&self, chroma: f64) -> Color {
+        Color::from_oklch(Oklch {
+            c: chroma.max(0.0),
+            ..self.to_oklch()
+        })
+        .with_alpha(self.a)
+    }
+
+    /// This color drawn over an opaque `background`: alpha is composited
+    /// away. Opaque colors are returned as they are.
+    pub fn over(self, background: Color) -> Color {
+        match self.a {
+            Some(a) => {
+                let mix = |fg: u8, bg: u8| (fg as f32 * a + bg as f32 * (1.0 - a)).round() as u8;
+                Color {
+                    r: mix(self.r, background.r),
+                    g: mix(self.g, background.g),
+                    b: mix(self.b, background.b),
+                    a: None,
+                }
+            }
+            None => self,
+        }
+    }
+
+    /// WCAG 2.1 relative luminance, 0 (black) to 1 (white). Alpha is ignored.
+    pub fn relative_luminance(self) -> f64 {
+        let [r, g, b] = self.unit_rgb().map(to_linear);
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    /// WCAG 2.1 contrast ratio between this color and `other`, 1 to 21.
+    /// Symmetric; a translucent color is composited over the other first.
+    /// AA needs 4.5 for text (`WCAG_AA`), 3 for large text and UI parts.
+    pub fn contrast_ratio(self, other: Color) -> f64 {
+        let l1 = self.over(other).relative_luminance();
+        let l2 = other.over(self).relative_luminance();
+        (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+    }
+
+    /// APCA (0.0.98G-4g) lightness contrast Lc of this color as text on
+    /// `background`, roughly -108 to 106. Positive for dark text on a light
+    /// background, negative the other way round; |Lc| 60 is about body text,
+    /// 75 is comfortable, 45 is large text.
+    pub fn apca_contrast(self, background: Color) -> f64 {
+        fn screen_luminance(color: Color) -> f64 {
+            let [r, g, b] = color.unit_rgb().map(|v| v.powf(2.4));
+            let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+            // Soft clamp near black
+            if y < 0.022 {
+                y + (0.022 - y).powf(1.414)
+            } else {
+                y
+            }
+        }
+
+        let text = screen_luminance(self.over(background));
+        let bg = screen_luminance(background);
+        if (bg - text).abs() < 0.0005 {
+            return 0.0;
+        }
+        let lc = if bg > text {
+            let sapc = (bg.powf(0.56) - text.powf(0.57)) * 1.14;
+            if sapc < 0.1 {
+                0.0
+            } else {
+                sapc - 0.027
+            }
+        } else {
+            let sapc = (bg.powf(0.65) - text.powf(0.62)) * 1.14;
+            if sapc > -0.1 {
+                0.0
+            } else {
+                sapc + 0.027
+            }
+        };
+        lc * 100.0
+    }
+
+    /// The candidate to use as text on `background`: the first one reaching
+    /// `min_ratio` (so candidates go in order of preference), or the one with
+    /// the most contrast if none does. `None` only for no candidates.
+    pub fn readable_on(background: Color, candidates: &[Color], min_ratio: f64) -> Option<Color> {
+        candidates
+            .iter()
+            .find(|c| c.contrast_ratio(background) >= min_ratio)
+            .or_else(|| {
+                candidates.iter().max_by(|a, b| {
+                    a.contrast_ratio(background)
+                        .total_cmp(&b.contrast_ratio(background))
+                })
+            })
+            .copied()
+    }
+
+    /// HSL: hue in degrees, saturation and lightness 0-1
+    pub fn to_hsl(self) -> Hsl {
+        let [r, g, b] = self.unit_rgb();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let l = (max + min) / 2.0;
+        let d = max - min;
+        if d == 0.0 {
+            return Hsl { h: 0.0, s: 0.0, l };
+        }
+        let s = d / (1.0 - (2.0 * l - 1.0).abs());
+        let h = if max == r {
+            60.0 * ((g - b) / d).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / d + 2.0)
+        } else {
+            60.0 * ((r - g) / d + 4.0)
+        };
+        Hsl { h, s, l }
+    }
+
+    pub fn from_hsl(hsl: Hsl) -> Color {
+        let c = (1.0 - (2.0 * hsl.l - 1.0).abs()) * hsl.s;
+        let h = hsl.h.rem_euclid(360.0) / 60.0;
+        let x = c * (1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let m = hsl.l - c / 2.0;
+        Color::from_unit_rgb([r + m, g + m, b + m])
+    }
+
+    /// OKLab (Björn Ottosson's perceptual space): L 0-1, a/b roughly ±0.4
+    pub fn to_oklab(self) -> Oklab {
+        let [r, g, b] = self.unit_rgb().map(to_linear);
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+        Oklab {
+            l: 0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            a: 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            b: 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+        }
+    }
+
+    /// Nearest sRGB color to `lab`, clipping out-of-gamut channels.
+    pub fn from_oklab(lab: Oklab) -> Color {
+        Color::from_unit_rgb(oklab_to_linear(lab).map(to_srgb))
+    }
+
+    /// OKLCH: OKLab as lightness, chroma and hue in degrees
+    pub fn to_oklch(self) -> Oklch {
+        let lab = self.to_oklab();
+        let c = lab.a.hypot(lab.b);
+        // Greys have no meaningful hue; keep them at 0 so rounding noise
+        // doesn't pick a random one.
+        let h = if c < 1e-4 {
+            0.0
+        } else {
+            lab.b.atan2(lab.a).to_degrees().rem_euclid(360.0)
+        };
+        Oklch { l: lab.l, c, h }
+    }
+
+    /// Nearest sRGB color to `lch`. Out-of-gamut colors lose chroma (not
+    /// lightness or hue) until they fit, so results stay recognizably the
+    /// same color rather than clipping toward a different hue.
+    pub fn from_oklch(lch: Oklch) -> Color {
+        let lab = |c: f64| {
+            let h = lch.h.to_radians();
+            Oklab {
+                l: lch.l,
+                a: c * h.cos(),
+                b: c * h.sin(),
+            }
+        };
+        let in_gamut = |c: f64| {
+            oklab_to_linear(lab(c))
+                .iter()
+                .all(|v| (-1e-4..=1.0 + 1e-4).contains(v))
+        };
+        if in_gamut(lch.c) {
+            return Color::from_oklab(lab(lch.c));
+        }
+        let (mut lo, mut hi) = (0.0, lch.c);
+        for _ in 0..24 {
+            let mid = (lo + hi) / 2.0;
+            if in_gamut(mid) {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        Color::from_oklab(lab(lo))
+    }
+
+    fn unit_rgb(&self) -> [f64; 3] {
+        [self.r, self.g, self.b].map(|v| v as f64 / 255.0)
+    }
+
+    fn from_unit_rgb(rgb: [f64; 3]) -> Color {
+        let [r, g, b] = rgb.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+        Color { r, g, b, a: None }
+    }
+}
+
+/// WCAG 2.1 AA minimum contrast ratio for normal text
+pub const WCAG_AA: f64 = 4.5;
+
+/// Alpha 0-1 as a byte, 0.9 -> 230
+fn alpha_byte(alpha: f32) -> u8 {
+    (alpha.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Alpha 0-1 for CSS, to at most 3 decimals (230/255 -> "0.902")
+fn css_alpha(alpha: f32) -> String {
+    format!("{}", (alpha.clamp(0.0, 1.0) * 1000.0).round() / 1000.0)
+}
+
+/// A CSS color component: a percentage, or a number up to `max`. Returns
+/// it scaled to 0-1.
+fn parse_unit(arg: &str, max: f64) -> Option<f64> {
+    let (value, max) = match arg.strip_suffix('%') {
+        Some(percent) => (percent, 100.0),
+        None => (arg, max),
+    };
+    let value: f64 = value.parse().ok()?;
+    (0.0..=max).contains(&value).then_some(value / max)
+}
+
+/// Hue in degrees, saturation and lightness 0-1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsl {
+    pub h: f64,
+    pub s: f64,
+    pub l: f64,
+}
+
+/// Perceptual lightness (0-1) and opponent axes a (green-red), b (blue-yellow)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Oklab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// OKLab in polar form: lightness 0-1, chroma, hue in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Oklch {
+    pub l: f64,
+    pub c: f64,
+    pub h: f64,
+}
+
+/// sRGB channel (0-1) → linear light
+fn to_linear(v: f64) -> f64 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Linear light → sRGB channel (0-1)
+fn to_srgb(v: f64) -> f64 {
+    if v <= 0.0031308 {
+        12.92 * v
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// OKLab → linear sRGB, unclamped (out of 0-1 means out of gamut)
+fn oklab_to_linear(lab: Oklab) -> [f64; 3] {
+    let l = (lab.l + 0.3963377774 * lab.a + 0.2158037573 * lab.b).powi(3);
+    let m = (lab.l - 0.1055613458 * lab.a - 0.0638541728 * lab.b).powi(3);
+    let s = (lab.l - 0.0894841775 * lab.a - 1.2914855480 * lab.b).powi(3);
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
 }
 
 /// Format a color in the specified format
@@ -190,4 +675,190 @@ mod tests {
         let d50 = c.darken(50);
         assert_eq!(d50.to_hex(), "455a7d");
     }
+
+    #[test]
+    fn oklab_and_hsl_roundtrip_palette_colors() {
+        for hex in [
+            "89b4fa", "f38ba8", "a6e3a1", "1e1e2e", "eff1f5", "ffffff", "000000",
+        ] {
+            let c = Color::from_hex(hex).unwrap();
+            assert_eq!(Color::from_oklch(c.to_oklch()).to_hex(), hex);
+            assert_eq!(Color::from_hsl(c.to_hsl()).to_hex(), hex);
+        }
+
+        let white = Color::from_hex("ffffff").unwrap().to_oklab();
+        assert!((white.l - 1.0).abs() < 1e-4 && white.a.abs() < 1e-4 && white.b.abs() < 1e-4);
+
+        let red = Color::from_hex("ff0000").unwrap();
+        assert_eq!(
+            red.to_hsl(),
+            Hsl {
+                h: 0.0,
+                s: 1.0,
+                l: 0.5
+            }
+        );
+        let lch = red.to_oklch();
+        // Reference values for sRGB red: L 0.628, C 0.2577, h 29.23
+        assert!((lch.l - 0.628).abs() < 1e-3, "{:?}", lch);
+        assert!((lch.c - 0.2577).abs() < 1e-3, "{:?}", lch);
+        assert!((lch.h - 29.23).abs() < 0.1, "{:?}", lch);
+    }
+
+    #[test]
+    fn perceptual_adjustments() {
+        let blue = Color::from_hex("89b4fa").unwrap();
+        let base = Color::from_hex("1e1e2e").unwrap();
+
+        assert_eq!(blue.lighten_l(0.0).to_hex(), "89b4fa");
+        assert_eq!(blue.rotate_hue(360.0).to_hex(), "89b4fa");
+        assert_eq!(blue.mix_oklab(&base, 100.0).to_hex(), "89b4fa");
+        assert_eq!(blue.mix_oklab(&base, 0.0).to_hex(), "1e1e2e");
+
+        // Lightening keeps the hue and stays saturated
+        let lighter = blue.lighten_l(10.0).to_oklch();
+        assert!((lighter.l - blue.to_oklch().l - 0.1).abs() < 0.01);
+        assert!((lighter.h - blue.to_oklch().h).abs() < 2.0);
+        assert!(blue.lighten_l(-10.0).to_oklch().l < blue.to_oklch().l);
+        assert_eq!(blue.lighten_l(100.0).to_hex(), "ffffff");
+
+        let grey = blue.with_chroma(0.0);
+        assert!(grey.r.abs_diff(grey.g) <= 1 && grey.g.abs_diff(grey.b) <= 1);
+        assert_eq!(blue.desaturate(100.0).to_hsl().s, 0.0);
+        assert!(blue.saturate(10.0).to_hsl().s > blue.to_hsl().s);
+
+        // Far out of gamut: chroma is reduced, lightness and hue are kept
+        let vivid = blue.with_chroma(0.5).to_oklch();
+        assert!((vivid.l - blue.to_oklch().l).abs() < 0.01);
+        assert!((vivid.h - blue.to_oklch().h).abs() < 2.0);
+
+        // Halfway in OKLab stays between the two in lightness
+        let mid = blue.mix_oklab(&base, 50.0).to_oklab();
+        assert!(mid.l < blue.to_oklab().l && mid.l > base.to_oklab().l);
+    }
+
+    #[test]
+    fn parses_short_hex_alpha_and_css_functions() {
+        for input in [
+            "#89b4fa",
+            "89B4FA",
+            "rgb(137, 180, 250)",
+            "rgb(137 180 250)",
+            "RGB( 137,180,250 )",
+            "hsl(217.2deg, 91.9%, 75.9%)",
+        ] {
+            let c = Color::parse(input).unwrap();
+            assert_eq!(c.to_hex(), "89b4fa", "{}", input);
+            assert!(c.a.is_none(), "{}", input);
+        }
+
+        assert_eq!(Color::parse("#fa0").unwrap().to_hex(), "ffaa00");
+        assert_eq!(Color::parse("#fa08").unwrap().to_hex(), "ffaa0088");
+        assert_eq!(Color::parse("#89b4fae6").unwrap().a, Some(230.0 / 255.0));
+        assert_eq!(
+            Color::parse("rgba(137, 180, 250, 0.5)").unwrap().a,
+            Some(0.5)
+        );
+        assert_eq!(
+            Color::parse("rgb(137 180 250 / 25%)").unwrap().a,
+            Some(0.25)
+        );
+        assert_eq!(
+            Color::parse("rgb(100%, 0%, 0%)").unwrap().to_hex(),
+            "ff0000"
+        );
+        assert_eq!(
+            Color::parse("hsla(120, 100%, 25%, 1)")
+                .unwrap()
+                .to_hex_hash(),
+            "#008000ff"
+        );
+
+        for bad in [
+            "#12345",
+            "#gggggg",
+            "rgb(1, 2)",
+            "rgb(256, 0, 0)",
+            "rgba(0, 0, 0, 2)",
+            "hsl(0, 150%, 50%)",
+            "lab(50 0 0)",
+            "blue",
+        ] {
+            assert!(Color::parse(bad).is_err(), "{}", bad);
+        }
+        assert!(Color::is_literal("#fff") && Color::is_literal("hsl(0 0% 0%)"));
+        assert!(!Color::is_literal("blue") && !Color::is_literal("rgbish"));
+    }
+
+    #[test]
+    fn alpha_reaches_every_format_and_opaque_colors_are_unchanged() {
+        let opaque = Color::from_hex("89b4fa").unwrap();
+        assert_eq!(
+            serde_json::to_string(&opaque).unwrap(),
+            r#"{"r":137,"g":180,"b":250}"#
+        );
+        let from_old: Color = serde_json::from_str(r#"{"r":137,"g":180,"b":250}"#).unwrap();
+        assert!(from_old.a.is_none());
+
+        let c = opaque.with_alpha(Some(0.9));
+        assert_eq!(c.to_hex(), "89b4fae6");
+        assert_eq!(c.to_hex_upper(), "89B4FAE6");
+        assert_eq!(c.to_hex_hash(), "#89b4fae6");
+        assert_eq!(c.to_rgb(), "137 180 250 0.9");
+        assert_eq!(c.to_rgb_css(), "rgba(137, 180, 250, 0.9)");
+        assert_eq!(c.to_hyprlang(), "rgba(89b4fae6)");
+        assert_eq!(c.to_hex_argb(c.alpha()), "#e689b4fa");
+        assert_eq!(
+            serde_json::to_string(&c).unwrap(),
+            r#"{"r":137,"g":180,"b":250,"a":0.9}"#
+        );
+
+        // Adjustments keep alpha; blending mixes it
+        assert_eq!(c.lighten(50).a, Some(0.9));
+        assert_eq!(c.darken(50).a, Some(0.9));
+        assert_eq!(c.rotate_hue(30.0).a, Some(0.9));
+        assert_eq!(c.blend(&opaque, 50).a, Some(0.95));
+        assert!(opaque.blend(&opaque, 50).a.is_none());
+    }
+
+    #[test]
+    fn wcag_and_apca_contrast() {
+        let black = Color::from_hex("000000").unwrap();
+        let white = Color::from_hex("ffffff").unwrap();
+        assert!((black.contrast_ratio(white) - 21.0).abs() < 1e-9);
+        assert!((white.contrast_ratio(white) - 1.0).abs() < 1e-9);
+
+        // Reference values from the WebAIM checker and the APCA calculator
+        let grey = Color::from_hex("777777").unwrap();
+        assert!((grey.contrast_ratio(white) - 4.48).abs() < 0.01);
+        assert!((white.contrast_ratio(grey) - 4.48).abs() < 0.01);
+        assert!((black.apca_contrast(white) - 106.04).abs() < 0.01);
+        assert!((white.apca_contrast(black) + 107.88).abs() < 0.01);
+        assert!((Color::from_hex("888888").unwrap().apca_contrast(white) - 63.06).abs() < 0.01);
+        assert_eq!(grey.apca_contrast(grey), 0.0);
+
+        // Translucent text is composited over the background first
+        let faint = black.with_alpha(Some(0.0));
+        assert!((faint.contrast_ratio(white) - 1.0).abs() < 1e-9);
+
+        // Latte: yellow fails as text on base, text on yellow is the better pick
+        let base = Color::from_hex("eff1f5").unwrap();
+        let text = Color::from_hex("4c4f69").unwrap();
+        let yellow = Color::from_hex("df8e1d").unwrap();
+        assert!(yellow.contrast_ratio(base) < WCAG_AA);
+        assert_eq!(
+            Color::readable_on(yellow, &[base, text], WCAG_AA)
+                .unwrap()
+                .to_hex(),
+            "4c4f69"
+        );
+        // Nothing reaches 21:1, so the highest contrast wins
+        assert_eq!(
+            Color::readable_on(yellow, &[base, text, black], 21.0)
+                .unwrap()
+                .to_hex(),
+            "000000"
+        );
+        assert!(Color::readable_on(yellow, &[], WCAG_AA).is_none());
+    }
 }
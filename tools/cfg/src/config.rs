@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -20,6 +21,43 @@ pub struct Config {
     pub wallpaper: WallpaperConfig,
     #[serde(default)]
     pub leds: LedConfig,
+    /// Free-form values for templates (`{{ vars.gaps }}`), set with
+    /// `cfg theme --set vars.gaps=8`
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub vars: toml::Table,
+    /// Rules for `cfg schedule` (not part of profiles)
+    #[serde(default, skip_serializing_if = "ScheduleConfig::is_empty")]
+    pub schedule: ScheduleConfig,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ScheduleConfig {
+    /// Location for sunrise/sunset rules, in degrees (north positive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    /// Degrees, east positive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<ScheduleRule>,
+}
+
+impl ScheduleConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == ScheduleConfig::default()
+    }
+}
+
+/// At `at`, switch to `profile` or apply the `set` values.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ScheduleRule {
+    /// "HH:MM", "sunrise" or "sunset", optionally offset ("sunset-30m")
+    pub at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Config keys to set, as with `cfg theme --set` (e.g. flavor = "mocha")
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub set: BTreeMap<String, String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
@@ -206,6 +244,17 @@ impl Config {
             .map_err(|e| format!("Failed to parse config file '{}': {}", path, e))
     }
 
+    /// Load config, or the defaults if the file doesn't exist yet. A file
+    /// that exists but doesn't parse is an error, so a typo can't quietly
+    /// reset everything (and be overwritten by the next save).
+    pub fn load_or_default(path: &str) -> Result<Self, String> {
+        if Path::new(path).exists() {
+            Config::load(path)
+        } else {
+            Ok(Config::default())
+        }
+    }
+
     /// Save config to a TOML file
     pub fn save(&self, path: &str) -> Result<(), String> {
         let content = toml::to_string_pretty(self)
@@ -220,148 +269,275 @@ impl Config {
             .map_err(|e| format!("Failed to write config file '{}': {}", path, e))
     }
 
-    /// Get a config value by key
+    /// Get a config value by dotted path ("fonts.mono", "vars.bar.height",
+    /// "wallpaper.sources[1].path"). Strings come back bare; numbers,
+    /// booleans, arrays and tables as TOML.
     pub fn get(&self, key: &str) -> Option<String> {
-        match key {
-            "flavor" => Some(self.flavor.clone()),
-            "primary" => Some(self.primary.clone()),
-            "secondary" => Some(self.secondary.clone()),
-            "icon_theme" => Some(self.icon_theme.clone()),
-            "gtk_theme" => Some(self.gtk_theme.clone()),
-            "qt_style" => Some(self.qt_style.clone()),
-            "fonts.mono" => Some(self.fonts.mono.clone()),
-            "fonts.mono_size" => Some(self.fonts.mono_size.to_string()),
-            "fonts.sans" => Some(self.fonts.sans.clone()),
-            "fonts.sans_size" => Some(self.fonts.sans_size.to_string()),
-            "wallpaper.mode" => Some(self.wallpaper.mode.clone()),
-            "wallpaper.path" => Some(self.wallpaper.path.clone()),
-            "wallpaper.gravity" => Some(self.wallpaper.gravity.clone()),
-            "wallpaper.cache_dir" => Some(self.wallpaper.cache_dir.clone()),
-            "wallpaper.source_dir" => Some(self.wallpaper.source_dir.clone()),
-            "leds.effect" => Some(self.leds.effect.clone()),
-            "leds.brightness" => Some(self.leds.brightness.to_string()),
-            "leds.speed" => Some(self.leds.speed.to_string()),
-            _ => None,
+        let path = parse_path(key).ok()?;
+        let root = toml::Value::try_from(self).ok()?;
+        match lookup(&root, &path)? {
+            toml::Value::String(s) => Some(s.clone()),
+            other => Some(other.to_string()),
         }
     }
 
-    /// Set a config value by key
+    /// Set a config value by dotted path. The raw value is parsed to the
+    /// type already there (so "10" stays a string for `primary` but is a
+    /// number for `fonts.mono_size`); new keys (like `vars.*`) are inferred
+    /// as integer, float, boolean, inline array/table or string. Missing
+    /// tables are created, and `[n]` one past the end appends to an array.
     pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
-        match key {
-            "flavor" => {
-                let valid = ["mocha", "macchiato", "frappe", "latte"];
-                if valid.contains(&value) {
-                    self.flavor = value.to_string();
-                    Ok(())
-                } else {
-                    Err(format!("Invalid flavor '{}'. Valid: {:?}", value, valid))
-                }
-            }
-            "primary" => {
-                self.primary = value.to_string();
-                Ok(())
-            }
-            "secondary" => {
-                self.secondary = value.to_string();
-                Ok(())
-            }
-            "icon_theme" => {
-                self.icon_theme = value.to_string();
-                Ok(())
-            }
-            "gtk_theme" => {
-                self.gtk_theme = value.to_string();
-                Ok(())
-            }
-            "qt_style" => {
-                self.qt_style = value.to_string();
-                Ok(())
-            }
-            "fonts.mono" => {
-                self.fonts.mono = value.to_string();
-                Ok(())
-            }
-            "fonts.mono_size" => {
-                self.fonts.mono_size = value
-                    .parse()
-                    .map_err(|_| format!("Invalid number: {}", value))?;
-                Ok(())
-            }
-            "fonts.sans" => {
-                self.fonts.sans = value.to_string();
-                Ok(())
-            }
-            "fonts.sans_size" => {
-                self.fonts.sans_size = value
-                    .parse()
-                    .map_err(|_| format!("Invalid number: {}", value))?;
-                Ok(())
-            }
-            "wallpaper.mode" => {
-                const VALID_MODES: &[&str] = &["pinned", "picker"];
-                if VALID_MODES.contains(&value) {
-                    self.wallpaper.mode = value.to_string();
-                    Ok(())
-                } else {
-                    Err(format!(
-                        "Invalid wallpaper.mode '{}'. Valid: 'pinned' or 'picker'",
-                        value
-                    ))
-                }
-            }
-            "wallpaper.path" => {
-                self.wallpaper.path = value.to_string();
-                Ok(())
+        let path = parse_path(key)?;
+        if let Some(part) = path.iter().find_map(|segment| match segment {
+            Segment::Key(part) if !valid_key(part) => Some(part),
+            _ => None,
+        }) {
+            return Err(format!(
+                "Invalid key '{}' in '{}' (use letters, digits and _)",
+                part, key
+            ));
+        }
+        let mut root = self.to_value()?;
+        let (last, parents) = path.split_last().unwrap();
+
+        let mut slot = &mut root;
+        for (i, segment) in parents.iter().enumerate() {
+            slot = match segment {
+                Segment::Key(part) => slot
+                    .as_table_mut()
+                    .ok_or_else(|| not_a_table(key, &parents[..i]))?
+                    .entry(part)
+                    .or_insert_with(|| match path[i + 1] {
+                        Segment::Key(_) => toml::Value::Table(toml::Table::new()),
+                        Segment::Index(_) => toml::Value::Array(Vec::new()),
+                    }),
+                Segment::Index(index) => slot
+                    .as_array_mut()
+                    .ok_or_else(|| format!("Can't set '{}': not an array", key))?
+                    .get_mut(*index)
+                    .ok_or_else(|| {
+                        format!("Can't set '{}': index {} is out of range", key, index)
+                    })?,
+            };
+        }
+        match last {
+            Segment::Key(part) => {
+                let table = slot
+                    .as_table_mut()
+                    .ok_or_else(|| not_a_table(key, parents))?;
+                let parsed = parse_value(table.get(part), value)?;
+                validate(key, &parsed)?;
+                table.insert(part.clone(), parsed);
             }
-            "wallpaper.gravity" => {
-                const VALID_GRAVITY: &[&str] = &[
-                    "NorthWest",
-                    "North",
-                    "NorthEast",
-                    "West",
-                    "Center",
-                    "East",
-                    "SouthWest",
-                    "South",
-                    "SouthEast",
-                ];
-                if VALID_GRAVITY.contains(&value) {
-                    self.wallpaper.gravity = value.to_string();
-                    Ok(())
-                } else {
-                    Err(format!(
-                        "Invalid gravity '{}'. Valid: {:?}",
-                        value, VALID_GRAVITY
-                    ))
+            Segment::Index(index) => {
+                let array = slot
+                    .as_array_mut()
+                    .ok_or_else(|| format!("Can't set '{}': not an array", key))?;
+                let parsed = parse_value(array.get(*index), value)?;
+                validate(key, &parsed)?;
+                match (*index).cmp(&array.len()) {
+                    std::cmp::Ordering::Less => array[*index] = parsed,
+                    std::cmp::Ordering::Equal => array.push(parsed),
+                    std::cmp::Ordering::Greater => {
+                        return Err(format!(
+                            "Can't set '{}': index {} is out of range",
+                            key, index
+                        ))
+                    }
                 }
             }
-            "wallpaper.cache_dir" => {
-                self.wallpaper.cache_dir = value.to_string();
-                Ok(())
+        }
+
+        let config: Config = root
+            .try_into()
+            .map_err(|e| format!("Invalid value for {}: {}", key, e.to_string().trim_end()))?;
+        // Keys serde doesn't know are dropped rather than rejected
+        if lookup(&config.to_value()?, &path).is_none() {
+            return Err(format!("Unknown config key: {}", key));
+        }
+        *self = config;
+        Ok(())
+    }
+
+    /// Remove a value by dotted path: settings go back to their defaults,
+    /// `vars` entries and array elements are deleted. Required settings
+    /// (flavor, primary) can't be unset.
+    pub fn unset(&mut self, key: &str) -> Result<(), String> {
+        let path = parse_path(key)?;
+        let mut root = self.to_value()?;
+        if lookup(&root, &path).is_none() {
+            return Err(format!("'{}' is not set", key));
+        }
+        let (last, parents) = path.split_last().unwrap();
+        let parent = lookup_mut(&mut root, parents).unwrap();
+        match (last, parent) {
+            (Segment::Key(part), toml::Value::Table(table)) => {
+                table.remove(part);
             }
-            "wallpaper.source_dir" => {
-                self.wallpaper.source_dir = value.to_string();
-                Ok(())
+            (Segment::Index(index), toml::Value::Array(array)) => {
+                array.remove(*index);
             }
-            "leds.effect" => {
-                self.leds.effect = value.to_string();
-                Ok(())
+            _ => unreachable!("lookup succeeded"),
+        }
+        *self = root
+            .try_into()
+            .map_err(|e| format!("Can't unset '{}': {}", key, e.to_string().trim_end()))?;
+        Ok(())
+    }
+
+    fn to_value(&self) -> Result<toml::Value, String> {
+        toml::Value::try_from(self).map_err(|e| format!("Failed to serialize config: {}", e))
+    }
+}
+
+/// One step of a dotted config path
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// "wallpaper.sources[1].path" → [wallpaper, sources, 1, path]
+fn parse_path(path: &str) -> Result<Vec<Segment>, String> {
+    let invalid = || format!("Invalid config path '{}'", path);
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (key, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if key.is_empty() {
+            return Err(invalid());
+        }
+        segments.push(Segment::Key(key.to_string()));
+        while !rest.is_empty() {
+            let (index, after) = rest
+                .strip_prefix('[')
+                .and_then(|r| r.split_once(']'))
+                .ok_or_else(invalid)?;
+            segments.push(Segment::Index(index.parse().map_err(|_| invalid())?));
+            rest = after;
+        }
+    }
+    Ok(segments)
+}
+
+fn valid_key(part: &str) -> bool {
+    part.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn lookup<'a>(value: &'a toml::Value, path: &[Segment]) -> Option<&'a toml::Value> {
+    path.iter().try_fold(value, |value, segment| match segment {
+        Segment::Key(key) => value.as_table()?.get(key),
+        Segment::Index(index) => value.as_array()?.get(*index),
+    })
+}
+
+fn lookup_mut<'a>(value: &'a mut toml::Value, path: &[Segment]) -> Option<&'a mut toml::Value> {
+    path.iter().try_fold(value, |value, segment| match segment {
+        Segment::Key(key) => value.as_table_mut()?.get_mut(key),
+        Segment::Index(index) => value.as_array_mut()?.get_mut(*index),
+    })
+}
+
+fn not_a_table(key: &str, parent: &[Segment]) -> String {
+    let name = match parent.last() {
+        Some(Segment::Key(part)) => part.clone(),
+        Some(Segment::Index(index)) => format!("[{}]", index),
+        None => String::new(),
+    };
+    format!("Can't set '{}': '{}' is not a table", key, name)
+}
+
+/// Parse `raw` as the same type as `current`, or infer a type for a new key.
+fn parse_value(current: Option<&toml::Value>, raw: &str) -> Result<toml::Value, String> {
+    let inline = || {
+        toml::from_str::<toml::Table>(&format!("value = {}", raw))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .ok_or_else(|| format!("Invalid TOML value: {}", raw))
+    };
+    match current {
+        Some(toml::Value::String(_)) => Ok(toml::Value::String(raw.to_string())),
+        Some(toml::Value::Integer(_)) => raw
+            .parse()
+            .map(toml::Value::Integer)
+            .map_err(|_| format!("Invalid number: {}", raw)),
+        Some(toml::Value::Float(_)) => raw
+            .parse()
+            .map(toml::Value::Float)
+            .map_err(|_| format!("Invalid number: {}", raw)),
+        Some(toml::Value::Boolean(_)) => raw
+            .parse()
+            .map(toml::Value::Boolean)
+            .map_err(|_| format!("Invalid boolean: {}", raw)),
+        Some(_) => inline(),
+        None => Ok(if let Ok(n) = raw.parse::<i64>() {
+            toml::Value::Integer(n)
+        } else if let Ok(n) = raw.parse::<f64>() {
+            toml::Value::Float(n)
+        } else if let Ok(b) = raw.parse::<bool>() {
+            toml::Value::Boolean(b)
+        } else if raw.starts_with('[') || raw.starts_with('{') {
+            inline()?
+        } else {
+            toml::Value::String(raw.to_string())
+        }),
+    }
+}
+
+/// Checks beyond the type for settings with a fixed set of valid values.
+fn validate(key: &str, value: &toml::Value) -> Result<(), String> {
+    let text = value.as_str().unwrap_or_default();
+    match key {
+        // Whether the palette exists depends on the cfg dir, which callers
+        // check with `palette::check_flavor`
+        "flavor" => crate::palette::check_name(text)?,
+        "wallpaper.mode" => {
+            const VALID_MODES: &[&str] = &["pinned", "picker"];
+            if !VALID_MODES.contains(&text) {
+                return Err(format!(
+                    "Invalid wallpaper.mode '{}'. Valid: 'pinned' or 'picker'",
+                    text
+                ));
             }
-            "leds.brightness" => {
-                self.leds.brightness = value
-                    .parse()
-                    .map_err(|_| format!("Invalid number: {}", value))?;
-                Ok(())
+        }
+        "wallpaper.gravity" => {
+            const VALID_GRAVITY: &[&str] = &[
+                "NorthWest",
+                "North",
+                "NorthEast",
+                "West",
+                "Center",
+                "East",
+                "SouthWest",
+                "South",
+                "SouthEast",
+            ];
+            if !VALID_GRAVITY.contains(&text) {
+                return Err(format!(
+                    "Invalid gravity '{}'. Valid: {:?}",
+                    text, VALID_GRAVITY
+                ));
             }
-            "leds.speed" => {
-                self.leds.speed = value
-                    .parse()
-                    .map_err(|_| format!("Invalid number: {}", value))?;
-                Ok(())
+        }
+        "schedule.latitude" | "schedule.longitude" => {
+            let limit = if key == "schedule.latitude" {
+                90.0
+            } else {
+                180.0
+            };
+            let degrees = value
+                .as_float()
+                .or(value.as_integer().map(|n| n as f64))
+                .ok_or_else(|| format!("Invalid number: {}", value))?;
+            if !(-limit..=limit).contains(&degrees) {
+                return Err(format!("{} must be between -{} and {}", key, limit, limit));
             }
-            _ => Err(format!("Unknown config key: {}", key)),
         }
+        _ => {}
     }
+    Ok(())
 }
 
 impl Default for Config {
@@ -376,6 +552,8 @@ impl Default for Config {
             fonts: FontConfig::default(),
             wallpaper: WallpaperConfig::default(),
             leds: LedConfig::default(),
+            vars: toml::Table::new(),
+            schedule: ScheduleConfig::default(),
         }
     }
 }
@@ -400,6 +578,100 @@ mod tests {
         assert_eq!(config.leds.devices[1].product_id, 0x01e0);
     }
 
+    #[test]
+    fn vars_set_get_and_save_roundtrip() {
+        let mut config = Config::default();
+        config.set("vars.gaps", "8").unwrap();
+        config.set("vars.opacity", "0.95").unwrap();
+        config.set("vars.cursor_theme", "Bibata-Modern").unwrap();
+        config.set("vars.bar.height", "30").unwrap();
+
+        assert_eq!(config.get("vars.gaps").unwrap(), "8");
+        assert_eq!(config.get("vars.cursor_theme").unwrap(), "Bibata-Modern");
+        assert_eq!(config.get("vars.bar.height").unwrap(), "30");
+        assert_eq!(config.get("vars.missing"), None);
+        assert_eq!(config.vars["opacity"].as_float(), Some(0.95));
+
+        let path = std::env::temp_dir().join(format!("cfg-vars-{}.toml", std::process::id()));
+        let path = path.to_str().unwrap();
+        config.save(path).unwrap();
+        let loaded = Config::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.vars, config.vars);
+    }
+
+    #[test]
+    fn vars_reject_bad_names_and_non_table_parents() {
+        let mut config = Config::default();
+        assert!(config.set("vars.bad-name", "1").is_err());
+        assert!(config.set("vars.", "1").is_err());
+        config.set("vars.gaps", "8").unwrap();
+        assert!(config.set("vars.gaps.inner", "4").is_err());
+    }
+
+    #[test]
+    fn dotted_paths_reach_arrays_and_keep_types() {
+        let mut config = Config::default();
+        config.set("leds.devices[1].name", "q11").unwrap();
+        config.set("leds.devices[0].vendor_id", "4660").unwrap();
+        assert_eq!(config.leds.devices[1].name, "q11");
+        assert_eq!(config.get("leds.devices[0].vendor_id").unwrap(), "4660");
+        assert_eq!(config.get("leds.devices[0].name").unwrap(), "keychron-v1");
+
+        // Appending one past the end, including to an array that's absent
+        config
+            .set(
+                "wallpaper.sources[0]",
+                r#"{ name = "APOD", path = "~/APOD" }"#,
+            )
+            .unwrap();
+        config
+            .set("wallpaper.sources[0].path", "~/Pictures/APOD")
+            .unwrap();
+        assert_eq!(config.wallpaper.sources[0].path, "~/Pictures/APOD");
+        assert!(config.set("wallpaper.sources[5].path", "x").is_err());
+
+        // A string setting stays a string; a number setting must be a number
+        config.set("primary", "10").unwrap();
+        assert_eq!(config.primary, "10");
+        assert_eq!(
+            config.set("fonts.mono_size", "big").unwrap_err(),
+            "Invalid number: big"
+        );
+        assert!(config.set("leds.brightness", "300").is_err());
+
+        assert_eq!(
+            config.set("fonts.bogus", "1").unwrap_err(),
+            "Unknown config key: fonts.bogus"
+        );
+        assert!(config.set("wallpaper.sources[x].path", "1").is_err());
+        assert_eq!(config.get("fonts.bogus"), None);
+    }
+
+    #[test]
+    fn unset_restores_defaults_and_removes_entries() {
+        let mut config = Config::default();
+        config.set("leds.speed", "10").unwrap();
+        config.set("vars.gaps", "8").unwrap();
+        config.unset("leds.speed").unwrap();
+        config.unset("vars.gaps").unwrap();
+        assert_eq!(config.leds.speed, 175);
+        assert!(config.vars.is_empty());
+
+        config.unset("leds.devices[0]").unwrap();
+        assert_eq!(config.leds.devices.len(), 1);
+        assert_eq!(config.leds.devices[0].name, "keychron-q11");
+
+        assert!(config
+            .unset("flavor")
+            .unwrap_err()
+            .contains("missing field"));
+        assert_eq!(
+            config.unset("vars.gaps").unwrap_err(),
+            "'vars.gaps' is not set"
+        );
+    }
+
     #[test]
     fn test_wallpaper_gravity_valid() {
         let mut config = Config::default();
@@ -0,0 +1,472 @@
+//! Which context variables each template reads, so an update can target only
+//! the templates affected by a change (`cfg update --affected-by fonts.mono`)
+//! and `cfg lint` can check those reads against the real context.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::tera_internals::{self, Expr, ExprVal, FunctionCall, LogicOperator, Node, Template};
+
+/// Flat context keys from `build_context` and the nested key they mirror.
+const ALIASES: &[(&str, &str)] = &[
+    ("font_mono", "fonts.mono"),
+    ("font_mono_size", "fonts.mono_size"),
+    ("font_sans", "fonts.sans"),
+    ("font_sans_size", "fonts.sans_size"),
+    ("wallpaper_path", "wallpaper.path"),
+    ("wallpaper_gravity", "wallpaper.gravity"),
+    ("wallpaper_cache_dir", "wallpaper.cache_dir"),
+    ("primary_name", "primary"),
+    ("secondary_name", "secondary"),
+];
+
+/// Top-level context keys that come from config. Everything else in the
+/// context is a palette color, which changes whenever the flavor does.
+const CONFIG_ROOTS: &[&str] = &[
+    "flavor",
+    "icon_theme",
+    "gtk_theme",
+    "qt_style",
+    "fonts",
+    "wallpaper",
+    "vars",
+    "home",
+];
+
+/// Canonical form of a context variable: flat aliases map to their nested
+/// key, and indexing (`fonts["mono"]`) is cut back to the object itself.
+pub fn normalize(var: &str) -> String {
+    let var = var.split('[').next().unwrap_or(var);
+    ALIASES
+        .iter()
+        .find(|(flat, _)| *flat == var)
+        .map(|(_, nested)| nested.to_string())
+        .unwrap_or_else(|| var.to_string())
+}
+
+/// Whether a template reading `vars` is affected by a change to `key`.
+///
+/// Reading a parent object counts as using its fields (`fonts` uses
+/// `fonts.mono`) and vice versa. `flavor` also covers every palette color.
+pub fn uses(vars: &BTreeSet<String>, key: &str) -> bool {
+    let key = normalize(key);
+    vars.iter().any(|var| {
+        var == &key
+            || var.starts_with(&format!("{}.", key))
+            || key.starts_with(&format!("{}.", var))
+            || (key == "flavor" && is_color(var))
+    })
+}
+
+fn is_color(var: &str) -> bool {
+    let root = var.split('.').next().unwrap_or(var);
+    !CONFIG_ROOTS.contains(&root)
+}
+
+/// Context variables read by template `name`, following `{% include %}`,
+/// `{% extends %}` and `{% import %}` into the other loaded templates.
+/// Loop variables, `{% set %}` names and macro arguments are not context
+/// reads and are left out.
+pub fn template_vars(templates: &HashMap<String, Template>, name: &str) -> BTreeSet<String> {
+    template_refs(templates, name)
+        .variables
+        .iter()
+        .map(|r| normalize(&r.ident))
+        .collect()
+}
+
+/// One context read as written in a template, for `cfg lint`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    /// Template the read appears in (a partial for included code)
+    pub template: String,
+    /// Identifier as written, e.g. `fonts.mono` or `blue_name`
+    pub ident: String,
+    /// Filters applied directly to the identifier, in order
+    pub filters: Vec<String>,
+    /// Whether an undefined value is tolerated here: `| default(...)`,
+    /// `is defined` or a bare `{% if %}` condition
+    pub guarded: bool,
+}
+
+/// A filter call and the template it appears in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterUse {
+    pub template: String,
+    pub filter: String,
+}
+
+/// Every context read and filter call reachable from template `name`.
+#[derive(Debug, Default)]
+pub struct TemplateRefs {
+    pub variables: Vec<Reference>,
+    pub filters: Vec<FilterUse>,
+    /// Loaded templates reached: `name` plus everything it includes,
+    /// extends or imports
+    pub templates: BTreeSet<String>,
+}
+
+/// Like `template_vars`, but keeps each read as written along with the
+/// filters applied to it, and lists every filter called.
+pub fn template_refs(templates: &HashMap<String, Template>, name: &str) -> TemplateRefs {
+    let mut collector = Collector {
+        templates,
+        visited: HashSet::new(),
+        current: String::new(),
+        refs: TemplateRefs::default(),
+        guards: Vec::new(),
+    };
+    collector.template(name);
+    collector.refs.templates = collector
+        .visited
+        .into_iter()
+        .filter(|name| templates.contains_key(name))
+        .collect();
+    collector.refs
+}
+
+struct Collector<'a> {
+    templates: &'a HashMap<String, Template>,
+    visited: HashSet<String>,
+    current: String,
+    refs: TemplateRefs,
+    /// Identifiers an enclosing `{% if %}` checked, so reading them is safe
+    guards: Vec<String>,
+}
+
+impl Collector<'_> {
+    fn template(&mut self, name: &str) {
+        if !self.visited.insert(name.to_string()) {
+            return;
+        }
+        let Some(template) = self.templates.get(name) else {
+            return;
+        };
+        let outer = std::mem::replace(&mut self.current, name.to_string());
+        self.nodes(tera_internals::ast(template), &mut Vec::new());
+        self.current = outer;
+        for dependency in tera_internals::dependencies(template) {
+            self.template(dependency);
+        }
+    }
+
+    fn nodes(&mut self, nodes: &[Node], locals: &mut Vec<String>) {
+        for node in nodes {
+            match node {
+                Node::VariableBlock(_, expr) => self.expr(expr, locals),
+                Node::MacroDefinition(_, definition, _) => {
+                    let mut args: Vec<String> = definition.args.keys().cloned().collect();
+                    for default in definition.args.values().flatten() {
+                        self.expr(default, locals);
+                    }
+                    self.nodes(&definition.body, &mut args);
+                }
+                Node::Extends(_, name) | Node::ImportMacro(_, name, _) => self.template(name),
+                Node::Include(_, names, _) => {
+                    for name in names {
+                        self.template(name);
+                    }
+                }
+                Node::Set(_, set) => {
+                    self.expr(&set.value, locals);
+                    locals.push(set.key.clone());
+                }
+                Node::FilterSection(_, section, _) => {
+                    self.filter(&section.filter, locals);
+                    self.nodes(&section.body, locals);
+                }
+                Node::Block(_, block, _) => self.nodes(&block.body, locals),
+                Node::Forloop(_, forloop, _) => {
+                    self.expr(&forloop.container, locals);
+                    let depth = locals.len();
+                    locals.push(forloop.value.clone());
+                    locals.extend(forloop.key.clone());
+                    self.nodes(&forloop.body, locals);
+                    if let Some(empty) = &forloop.empty_body {
+                        self.nodes(empty, locals);
+                    }
+                    locals.truncate(depth);
+                }
+                Node::If(condition, _) => {
+                    for (_, expr, body) in &condition.conditions {
+                        self.condition(expr, locals);
+                        let depth = self.guards.len();
+                        checked_idents(expr, &mut self.guards);
+                        self.nodes(body, locals);
+                        self.guards.truncate(depth);
+                    }
+                    if let Some((_, body)) = &condition.otherwise {
+                        self.nodes(body, locals);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// An `{% if %}` condition: Tera treats an undefined bare identifier
+    /// (also under `and`/`or`/`not`) as false instead of failing.
+    fn condition(&mut self, expr: &Expr, locals: &[String]) {
+        match &expr.val {
+            ExprVal::Ident(ident) => {
+                self.filters(expr, locals);
+                self.reference(ident, locals, expr, true);
+            }
+            ExprVal::Logic(logic)
+                if matches!(logic.operator, LogicOperator::And | LogicOperator::Or) =>
+            {
+                self.filters(expr, locals);
+                self.condition(&logic.lhs, locals);
+                self.condition(&logic.rhs, locals);
+            }
+            _ => self.expr(expr, locals),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, locals: &[String]) {
+        self.filters(expr, locals);
+        match &expr.val {
+            ExprVal::Ident(ident) => {
+                let guarded = expr.filters.first().is_some_and(|f| f.name == "default");
+                self.reference(ident, locals, expr, guarded);
+            }
+            value => self.value(value, locals),
+        }
+    }
+
+    fn filters(&mut self, expr: &Expr, locals: &[String]) {
+        for filter in &expr.filters {
+            self.filter(filter, locals);
+        }
+    }
+
+    fn filter(&mut self, filter: &FunctionCall, locals: &[String]) {
+        self.refs.filters.push(FilterUse {
+            template: self.current.clone(),
+            filter: filter.name.clone(),
+        });
+        self.call(filter, locals);
+    }
+
+    fn call(&mut self, call: &FunctionCall, locals: &[String]) {
+        for arg in call.args.values() {
+            self.expr(arg, locals);
+        }
+    }
+
+    fn value(&mut self, value: &ExprVal, locals: &[String]) {
+        match value {
+            ExprVal::Ident(ident) => self.ident(ident, locals, Vec::new(), false),
+            ExprVal::Math(math) => {
+                self.expr(&math.lhs, locals);
+                self.expr(&math.rhs, locals);
+            }
+            ExprVal::Logic(logic) => {
+                self.expr(&logic.lhs, locals);
+                self.expr(&logic.rhs, locals);
+            }
+            ExprVal::In(within) => {
+                self.expr(&within.lhs, locals);
+                self.expr(&within.rhs, locals);
+            }
+            ExprVal::Test(test) => {
+                let guarded = test.name == "defined" || test.name == "undefined";
+                self.ident(&test.ident, locals, Vec::new(), guarded);
+                for arg in &test.args {
+                    self.expr(arg, locals);
+                }
+            }
+            ExprVal::MacroCall(call) => {
+                for arg in call.args.values() {
+                    self.expr(arg, locals);
+                }
+            }
+            ExprVal::FunctionCall(call) => self.call(call, locals),
+            ExprVal::Array(items) => {
+                for item in items {
+                    self.expr(item, locals);
+                }
+            }
+            ExprVal::StringConcat(concat) => {
+                for value in &concat.values {
+                    self.value(value, locals);
+                }
+            }
+            ExprVal::String(_) | ExprVal::Int(_) | ExprVal::Float(_) | ExprVal::Bool(_) => {}
+        }
+    }
+
+    fn reference(&mut self, ident: &str, locals: &[String], expr: &Expr, guarded: bool) {
+        let filters = expr.filters.iter().map(|f| f.name.clone()).collect();
+        self.ident(ident, locals, filters, guarded);
+    }
+
+    fn ident(&mut self, ident: &str, locals: &[String], filters: Vec<String>, guarded: bool) {
+        let root = ident.split(['.', '[']).next().unwrap_or(ident);
+        if root == "loop" || root.starts_with("__tera") || locals.iter().any(|l| l == root) {
+            return;
+        }
+        self.refs.variables.push(Reference {
+            template: self.current.clone(),
+            ident: ident.to_string(),
+            filters,
+            guarded: guarded || self.guards.iter().any(|g| g == ident),
+        });
+    }
+}
+
+/// Identifiers a condition only passes for when they're defined: bare
+/// truthy checks and `is defined` tests, joined by `and`.
+fn checked_idents(expr: &Expr, out: &mut Vec<String>) {
+    if expr.negated {
+        return;
+    }
+    match &expr.val {
+        ExprVal::Ident(ident) => out.push(ident.clone()),
+        ExprVal::Test(test) if test.name == "defined" && !test.negated => {
+            out.push(test.ident.clone())
+        }
+        ExprVal::Logic(logic) if logic.operator == LogicOperator::And => {
+            checked_idents(&logic.lhs, out);
+            checked_idents(&logic.rhs, out);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(templates: &[(&str, &str)]) -> HashMap<String, Template> {
+        templates
+            .iter()
+            .map(|(name, body)| {
+                (
+                    name.to_string(),
+                    tera_internals::parse(name, body).expect("template should parse"),
+                )
+            })
+            .collect()
+    }
+
+    fn vars(list: &[&str]) -> BTreeSet<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn collects_variables_and_filter_args() {
+        let templates = parse(&[(
+            "kitty",
+            "font {{ fonts.mono }}\n{{ base | blend(with=primary, amount=0.2) | hex }}\n\
+             {% if wallpaper_gravity == 'center' %}{{ font_sans_size }}{% endif %}",
+        )]);
+        assert_eq!(
+            template_vars(&templates, "kitty"),
+            vars(&[
+                "base",
+                "fonts.mono",
+                "fonts.sans_size",
+                "primary",
+                "wallpaper.gravity"
+            ])
+        );
+    }
+
+    #[test]
+    fn skips_loop_set_and_macro_locals() {
+        let templates = parse(&[(
+            "t",
+            "{% set accent = mauve %}{{ accent | hex }}\
+             {% for c in [red, green] %}{{ c | hex }}{{ loop.index }}{% endfor %}\
+             {% macro row(color) %}{{ color | hex }}{{ text | hex }}{% endmacro row %}",
+        )]);
+        assert_eq!(
+            template_vars(&templates, "t"),
+            vars(&["green", "mauve", "red", "text"])
+        );
+    }
+
+    #[test]
+    fn follows_includes_parents_and_imports() {
+        let templates = parse(&[
+            ("partials/base", "{{ flavor }}{% block body %}{% endblock body %}"),
+            ("partials/macros", "{% macro m() %}{{ blue | hex }}{% endmacro m %}"),
+            ("partials/footer", "{{ home }}"),
+            (
+                "child",
+                "{% extends \"partials/base\" %}{% import \"partials/macros\" as macros %}\
+                 {% block body %}{{ macros::m() }}{% include \"partials/footer\" %}{% endblock body %}",
+            ),
+        ]);
+        assert_eq!(
+            template_vars(&templates, "child"),
+            vars(&["blue", "flavor", "home"])
+        );
+    }
+
+    #[test]
+    fn refs_keep_filters_guards_and_source_template() {
+        let templates = parse(&[
+            ("partials/footer", "{{ blue_name | ansi }}"),
+            (
+                "t",
+                "{{ overlay3 | lighten(amount=10) | hex }}{{ accent | default(value='x') }}\
+                 {% if extra and wallpaper.path %}{% endif %}{% if gaps is defined %}{{ gaps }}{% endif %}\
+                 {{ 'x' | shout }}{% include \"partials/footer\" %}",
+            ),
+        ]);
+        let refs = template_refs(&templates, "t");
+        let find = |ident: &str| {
+            refs.variables
+                .iter()
+                .find(|r| r.ident == ident)
+                .unwrap_or_else(|| panic!("no reference to {}", ident))
+        };
+
+        assert_eq!(find("overlay3").filters, vec!["lighten", "hex"]);
+        assert!(!find("overlay3").guarded);
+        assert!(find("accent").guarded);
+        assert!(find("extra").guarded);
+        assert!(find("wallpaper.path").guarded);
+        assert!(refs
+            .variables
+            .iter()
+            .filter(|r| r.ident == "gaps")
+            .all(|r| r.guarded));
+        assert_eq!(find("blue_name").template, "partials/footer");
+        assert!(refs
+            .filters
+            .iter()
+            .any(|f| f.filter == "shout" && f.template == "t"));
+    }
+
+    #[test]
+    fn uses_matches_aliases_parents_and_fields() {
+        let kitty = vars(&["fonts.mono", "primary"]);
+        assert!(uses(&kitty, "fonts.mono"));
+        assert!(uses(&kitty, "font_mono"));
+        assert!(uses(&kitty, "fonts"));
+        assert!(!uses(&kitty, "fonts.sans"));
+        assert!(uses(&kitty, "primary"));
+
+        let gtk = vars(&["fonts"]);
+        assert!(uses(&gtk, "fonts.mono_size"));
+        assert!(!uses(&gtk, "wallpaper"));
+    }
+
+    #[test]
+    fn flavor_covers_palette_colors() {
+        assert!(uses(&vars(&["base"]), "flavor"));
+        assert!(uses(&vars(&["primary"]), "flavor"));
+        assert!(!uses(&vars(&["fonts.mono", "wallpaper.path"]), "flavor"));
+    }
+
+    #[test]
+    fn user_vars_are_config_not_colors() {
+        let bar = vars(&["vars.rounding"]);
+        assert!(!uses(&bar, "flavor"));
+        assert!(uses(&bar, "vars.rounding"));
+        assert!(uses(&bar, "vars"));
+        assert!(!uses(&bar, "vars.gaps"));
+    }
+}
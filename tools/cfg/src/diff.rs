@@ -0,0 +1,243 @@
+//! Line diffs between an output file on disk and its freshly rendered
+//! content, for `cfg update --dry-run --diff/--stat`.
+
+/// Lines of context around each hunk, same as `diff -u`.
+pub const CONTEXT_LINES: usize = 3;
+
+/// Past this many edits the shortest-edit search gets expensive; the diff
+/// falls back to replacing the whole file.
+const MAX_EDIT_DISTANCE: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    /// Line present in both (old index, new index)
+    Equal(usize, usize),
+    /// Line only in the old text (old index)
+    Delete(usize),
+    /// Line only in the new text (new index)
+    Insert(usize),
+}
+
+/// Shortest edit script between `old` and `new` (Myers' O(ND) algorithm).
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = (n + m) as usize;
+    let offset = max as isize;
+    let index = |k: isize| (k + offset) as usize;
+
+    let mut v = vec![0isize; 2 * max + 2];
+    // trace[d] holds the furthest-reaching x for diagonals -d..=d after step d.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut found = false;
+
+    for d in 0..=max.min(MAX_EDIT_DISTANCE) as isize {
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                found = true;
+                break;
+            }
+            k += 2;
+        }
+        trace.push(v[index(-d)..=index(d)].to_vec());
+        if found {
+            break;
+        }
+    }
+
+    if !found {
+        let mut script: Vec<Edit> = (0..old.len()).map(Edit::Delete).collect();
+        script.extend((0..new.len()).map(Edit::Insert));
+        return script;
+    }
+
+    let mut script = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (0..trace.len() as isize).rev() {
+        let k = x - y;
+        let at = |k: isize| -> isize {
+            if d == 0 {
+                0
+            } else {
+                trace[(d - 1) as usize][(k + d - 1) as usize]
+            }
+        };
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = if d == 0 { 0 } else { at(prev_k) };
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            script.push(Edit::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                script.push(Edit::Insert(prev_y as usize));
+            } else {
+                script.push(Edit::Delete(prev_x as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    script.reverse();
+    script
+}
+
+/// Count `(added, removed)` lines between `old` and `new`.
+pub fn line_stats(old: &str, new: &str) -> (usize, usize) {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    edits(&old_lines, &new_lines)
+        .iter()
+        .fold((0, 0), |(added, removed), edit| match edit {
+            Edit::Insert(_) => (added + 1, removed),
+            Edit::Delete(_) => (added, removed + 1),
+            Edit::Equal(..) => (added, removed),
+        })
+}
+
+/// Render a unified diff (`diff -u` style) from `old` to `new`. Returns an
+/// empty string when the texts have identical lines.
+pub fn unified(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let script = edits(&old_lines, &new_lines);
+
+    let changes: Vec<usize> = script
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !matches!(e, Edit::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Group changes whose context windows touch into hunks of script indices.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + CONTEXT_LINES + 1).min(script.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    // Lines of each side consumed before every script index.
+    let mut positions = Vec::with_capacity(script.len());
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for edit in &script {
+        positions.push((old_pos, new_pos));
+        match edit {
+            Edit::Equal(..) => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Edit::Delete(_) => old_pos += 1,
+            Edit::Insert(_) => new_pos += 1,
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    for (start, end) in hunks {
+        let slice = &script[start..end];
+        let (old_start, new_start) = positions[start];
+        let old_count = slice
+            .iter()
+            .filter(|e| !matches!(e, Edit::Insert(_)))
+            .count();
+        let new_count = slice
+            .iter()
+            .filter(|e| !matches!(e, Edit::Delete(_)))
+            .count();
+
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for edit in slice {
+            match *edit {
+                Edit::Equal(o, _) => out.push_str(&format!(" {}\n", old_lines[o])),
+                Edit::Delete(o) => out.push_str(&format!("-{}\n", old_lines[o])),
+                Edit::Insert(n) => out.push_str(&format!("+{}\n", new_lines[n])),
+            }
+        }
+    }
+    out
+}
+
+/// Format one side of a hunk header. Empty ranges point at the line before.
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_texts_have_no_diff() {
+        assert_eq!(unified("a\nb\n", "a\nb\n", "old", "new"), "");
+        assert_eq!(line_stats("a\nb\n", "a\nb\n"), (0, 0));
+    }
+
+    #[test]
+    fn single_line_change_has_context() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+        assert_eq!(
+            unified(old, new, "old", "new"),
+            "--- old\n+++ new\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+        assert_eq!(line_stats(old, new), (1, 1));
+    }
+
+    #[test]
+    fn distant_changes_split_into_hunks() {
+        let old: String = (1..=20).map(|i| format!("{}\n", i)).collect();
+        let new = old.replace("2\n", "two\n").replace("19\n", "nineteen\n");
+        let diff = unified(&old, &new, "old", "new");
+        assert_eq!(diff.matches("@@ -").count(), 2, "diff was:\n{}", diff);
+    }
+
+    #[test]
+    fn new_file_diffs_against_empty() {
+        assert_eq!(
+            unified("", "a\nb\n", "/dev/null", "new"),
+            "--- /dev/null\n+++ new\n@@ -0,0 +1,2 @@\n+a\n+b\n"
+        );
+        assert_eq!(line_stats("", "a\nb\n"), (2, 0));
+    }
+
+    #[test]
+    fn pure_deletion() {
+        assert_eq!(
+            unified("a\nb\nc\n", "a\nc\n", "old", "new"),
+            "--- old\n+++ new\n@@ -1,3 +1,2 @@\n a\n-b\n c\n"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! `cfg doctor`: checks that config.toml, templates.toml and the palettes
+//! load and agree with each other, and that the external tools cfg runs are
+//! installed. Template contents are left to `cfg lint`.
+
+use std::collections::BTreeSet;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::lint::Issue;
+use crate::palette::Palette;
+use crate::profile;
+use crate::schedule;
+use crate::templates::TemplatesFile;
+
+/// External programs cfg runs, and what for.
+pub const TOOLS: &[(&str, &str)] = &[
+    ("magick", "wallpaper processing and font previews"),
+    ("fc-list", "listing fonts"),
+    ("fc-match", "resolving font names"),
+    ("hyprctl", "monitors and wallpaper"),
+    ("kitty", "terminal reloads and previews"),
+    ("systemctl", "systemd_unit reloads and hyprpaper"),
+];
+
+/// Run every check. Errors are things that stop `cfg update` or leave
+/// settings without effect; warnings are typos and missing optional tools.
+pub fn doctor(cfg_dir: &str, dotfiles_dir: &str) -> Vec<Issue> {
+    let mut issues = BTreeSet::new();
+    let palettes = check_palettes(cfg_dir, &mut issues);
+    check_config(cfg_dir, &palettes, &mut issues);
+    check_templates(cfg_dir, dotfiles_dir, &mut issues);
+    for (tool, purpose) in TOOLS {
+        if find_in_path(tool).is_none() {
+            issues.insert(Issue::warning(
+                tool,
+                format!("not found in PATH (needed for {})", purpose),
+            ));
+        }
+    }
+    issues.into_iter().collect()
+}
+
+/// Every palette that loads, by flavor name.
+fn check_palettes(cfg_dir: &str, issues: &mut BTreeSet<Issue>) -> Vec<(String, Palette)> {
+    let dir = format!("{}/palettes", cfg_dir);
+    let mut paths: Vec<_> = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect(),
+        Err(e) => {
+            issues.insert(Issue::error(
+                "palettes",
+                format!("can't read '{}': {}", dir, e),
+            ));
+            return Vec::new();
+        }
+    };
+    paths.sort();
+
+    let mut palettes = Vec::new();
+    for path in paths {
+        let flavor = path.file_stem().unwrap().to_string_lossy().into_owned();
+        match Palette::load(&path.to_string_lossy()) {
+            Ok(palette) => palettes.push((flavor, palette)),
+            Err(e) => {
+                issues.insert(Issue::error(&format!("palettes/{}.toml", flavor), e));
+            }
+        }
+    }
+    palettes
+}
+
+fn check_config(cfg_dir: &str, palettes: &[(String, Palette)], issues: &mut BTreeSet<Issue>) {
+    const SUBJECT: &str = "config.toml";
+    let path = format!("{}/config.toml", cfg_dir);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            issues.insert(Issue::warning(
+                SUBJECT,
+                "not found, the defaults are used".to_string(),
+            ));
+            String::new()
+        }
+        Err(e) => {
+            issues.insert(Issue::error(SUBJECT, format!("can't read: {}", e)));
+            return;
+        }
+    };
+    let config = match unknown_keys::<Config>(&content) {
+        Ok((config, unknown)) => {
+            for key in unknown {
+                issues.insert(Issue::warning(SUBJECT, format!("unknown key '{}'", key)));
+            }
+            config
+        }
+        Err(_) if content.is_empty() => Config::default(),
+        Err(e) => {
+            issues.insert(Issue::error(SUBJECT, e));
+            return;
+        }
+    };
+
+    match palettes.iter().find(|(flavor, _)| *flavor == config.flavor) {
+        Some((flavor, palette)) => {
+            for (key, color) in [
+                ("primary", &config.primary),
+                ("secondary", &config.secondary),
+            ] {
+                if palette.get(color).is_none() {
+                    issues.insert(Issue::error(
+                        SUBJECT,
+                        format!(
+                            "{} '{}' is not a color in the {} palette",
+                            key, color, flavor
+                        ),
+                    ));
+                }
+            }
+        }
+        None => {
+            let available: Vec<&str> = palettes.iter().map(|(f, _)| f.as_str()).collect();
+            issues.insert(Issue::error(
+                SUBJECT,
+                format!(
+                    "flavor '{}' has no palette (available: {})",
+                    config.flavor,
+                    available.join(", ")
+                ),
+            ));
+        }
+    }
+
+    let source_dir = config.wallpaper.source_dir.trim();
+    if !source_dir.is_empty() && !Path::new(&crate::wallpaper::expand_tilde(source_dir)).is_dir() {
+        issues.insert(Issue::warning(
+            SUBJECT,
+            format!("wallpaper.source_dir '{}' is not a directory", source_dir),
+        ));
+    }
+
+    match schedule::rules(&config.schedule) {
+        Ok(rules) => {
+            let profiles = profile::list(cfg_dir).unwrap_or_default();
+            for name in rules.iter().filter_map(|r| r.rule.profile.as_ref()) {
+                if !profiles.contains(name) {
+                    issues.insert(Issue::error(
+                        SUBJECT,
+                        format!("schedule uses unknown profile '{}'", name),
+                    ));
+                }
+            }
+        }
+        Err(e) => {
+            issues.insert(Issue::error(SUBJECT, e));
+        }
+    }
+}
+
+fn check_templates(cfg_dir: &str, dotfiles_dir: &str, issues: &mut BTreeSet<Issue>) {
+    const SUBJECT: &str = "templates.toml";
+    let path = format!("{}/templates.toml", cfg_dir);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            issues.insert(Issue::error(SUBJECT, format!("can't read: {}", e)));
+            return;
+        }
+    };
+    let templates = match unknown_keys::<TemplatesFile>(&content) {
+        Ok((templates, unknown)) => {
+            for key in unknown {
+                issues.insert(Issue::warning(SUBJECT, format!("unknown key '{}'", key)));
+            }
+            templates
+        }
+        Err(e) => {
+            issues.insert(Issue::error(SUBJECT, e));
+            return;
+        }
+    };
+
+    for name in templates.names() {
+        let tpl = templates.get(name).unwrap();
+        if !Path::new(dotfiles_dir).join(&tpl.path).is_file() {
+            issues.insert(Issue::error(
+                name,
+                format!("template file not found: {}", tpl.path),
+            ));
+        }
+    }
+}
+
+/// Parse `content` as `T`, and list the dotted keys it has that `T` doesn't
+/// know (and so silently ignores). Anything `T` keeps when serialized back
+/// is known, so free-form tables like `[vars]` never count.
+fn unknown_keys<T: DeserializeOwned + Serialize>(
+    content: &str,
+) -> Result<(T, Vec<String>), String> {
+    fn walk(prefix: &str, raw: &toml::Value, known: &toml::Value, out: &mut Vec<String>) {
+        match (raw, known) {
+            (toml::Value::Table(raw), toml::Value::Table(known)) => {
+                for (key, value) in raw {
+                    let path = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    };
+                    match known.get(key) {
+                        Some(known) => walk(&path, value, known, out),
+                        None if !is_empty(value) => out.push(path),
+                        None => {}
+                    }
+                }
+            }
+            (toml::Value::Array(raw), toml::Value::Array(known)) => {
+                for (i, (raw, known)) in raw.iter().zip(known).enumerate() {
+                    walk(&format!("{}[{}]", prefix, i), raw, known, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Empty tables and arrays are dropped when serializing, so they can't
+    /// be told apart from unknown keys; they don't do anything either way.
+    fn is_empty(value: &toml::Value) -> bool {
+        match value {
+            toml::Value::Table(table) => table.values().all(is_empty),
+            toml::Value::Array(array) => array.is_empty(),
+            _ => false,
+        }
+    }
+
+    let raw: toml::Value = toml::from_str(content).map_err(|e| e.to_string())?;
+    let parsed: T = raw.clone().try_into().map_err(|e| e.to_string())?;
+    let known = toml::Value::try_from(&parsed).map_err(|e| e.to_string())?;
+    let mut unknown = Vec::new();
+    walk("", &raw, &known, &mut unknown);
+    Ok((parsed, unknown))
+}
+
+/// Full path of executable `name` in `$PATH`.
+fn find_in_path(name: &str) -> Option<std::path::PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| {
+            candidate
+                .metadata()
+                .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_keys_skip_free_form_tables() {
+        let content = r#"
+flavor = "mocha"
+primry = "blue"
+primary = "blue"
+
+[fonts]
+mono = "Iosevka"
+size = 12
+
+[vars]
+anything = { goes = true }
+
+[[schedule.rules]]
+at = "07:00"
+set = { flavor = "latte" }
+when = "weekdays"
+"#;
+        let (config, unknown) = unknown_keys::<Config>(content).unwrap();
+        assert_eq!(config.fonts.mono, "Iosevka");
+        assert_eq!(
+            unknown,
+            vec!["fonts.size", "primry", "schedule.rules[0].when"]
+        );
+
+        let registry = r#"
+[templates]
+kitty = { path = "kitty.conf.tera", reloda = "true", tags = ["term"] }
+bat = { path = "bat.tera", overrides = { primary = "peach" } }
+"#;
+        let (_, unknown) = unknown_keys::<TemplatesFile>(registry).unwrap();
+        assert_eq!(unknown, vec!["templates.kitty.reloda"]);
+    }
+}
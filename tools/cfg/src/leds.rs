@@ -994,15 +994,30 @@ mod tests {
     #[test]
     fn rgb_to_hsv_maps_primary_colors_to_qmk_hue_scale() {
         assert_eq!(
-            rgb_to_hsv(Color { r: 255, g: 0, b: 0 }),
+            rgb_to_hsv(Color {
+                r: 255,
+                g: 0,
+                b: 0,
+                a: None
+            }),
             HsvColor { h: 0, s: 255 }
         );
         assert_eq!(
-            rgb_to_hsv(Color { r: 0, g: 255, b: 0 }),
+            rgb_to_hsv(Color {
+                r: 0,
+                g: 255,
+                b: 0,
+                a: None
+            }),
             HsvColor { h: 85, s: 255 }
         );
         assert_eq!(
-            rgb_to_hsv(Color { r: 0, g: 0, b: 255 }),
+            rgb_to_hsv(Color {
+                r: 0,
+                g: 0,
+                b: 255,
+                a: None
+            }),
             HsvColor { h: 170, s: 255 }
         );
     }
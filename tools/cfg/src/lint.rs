@@ -0,0 +1,430 @@
+//! `cfg lint`: static checks over the template registry, so mistakes show up
+//! before `cfg update` hits them at render time.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use tera::Value;
+
+use crate::config::Config;
+use crate::deps::{self, Reference};
+use crate::palette::Palette;
+use crate::render::{build_context, discover_templates, filter_kinds, ValueKind, PARTIALS_DIR};
+use crate::templates::{load_engine, TemplatesFile};
+use crate::tera_internals;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// One problem found by `lint` or `doctor`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Template name, partial, file or output path the issue is about
+    pub subject: String,
+    pub message: String,
+}
+
+impl Issue {
+    pub fn error(subject: &str, message: String) -> Self {
+        Issue {
+            severity: Severity::Error,
+            subject: subject.to_string(),
+            message,
+        }
+    }
+
+    pub fn warning(subject: &str, message: String) -> Self {
+        Issue {
+            severity: Severity::Warning,
+            subject: subject.to_string(),
+            message,
+        }
+    }
+}
+
+/// Check every template in `templates.toml` against the current config and
+/// palette. Errors are things that would break `cfg update`: missing or
+/// unparsable files, undefined variables, unknown filters, filters applied
+/// to the wrong kind of value, bad modes or reloads, outputs written twice
+/// and groups that name unknown templates.
+/// Warnings: `.tera` files under the dotfiles dir that aren't registered,
+/// and palette colors no template uses.
+pub fn lint(cfg_dir: &str, dotfiles_dir: &str) -> Result<Vec<Issue>, String> {
+    let config = Config::load_or_default(&format!("{}/config.toml", cfg_dir))?;
+    let palette_path = format!("{}/palettes/{}.toml", cfg_dir, config.flavor);
+    let palette =
+        Palette::load(&palette_path).map_err(|e| format!("Error loading palette: {}", e))?;
+    let base = build_context(&config, &palette);
+
+    let templates = TemplatesFile::load(&format!("{}/templates.toml", cfg_dir))?;
+    let engine = load_engine(cfg_dir, dotfiles_dir, &templates)?;
+
+    let mut issues: BTreeSet<Issue> = BTreeSet::new();
+    let mut outputs: BTreeMap<PathBuf, Vec<&str>> = BTreeMap::new();
+    let mut used: BTreeSet<String> = BTreeSet::new();
+
+    for name in templates.names() {
+        let tpl = templates.get(name).unwrap();
+        outputs
+            .entry(tpl.output_path(dotfiles_dir))
+            .or_default()
+            .push(name);
+        if let Err(e) = tpl.file_mode() {
+            issues.insert(Issue::error(name, e));
+        }
+        if let Err(e) = tpl.reload_action() {
+            issues.insert(Issue::error(name, e));
+        }
+
+        let context = match tpl.context(&base, &palette) {
+            Ok(context) => context.into_json(),
+            Err(e) => {
+                issues.insert(Issue::error(name, e));
+                continue;
+            }
+        };
+        // A palette color named in the registry counts as used
+        for value in tpl.overrides.values().chain(tpl.vars.values()) {
+            if let Some(color) = value.as_str().filter(|c| palette.get(c).is_some()) {
+                used.insert(color.to_string());
+            }
+        }
+        let refs = match engine.references(&tpl.path) {
+            Ok(refs) => refs,
+            Err(e) => {
+                issues.insert(Issue::error(name, e));
+                continue;
+            }
+        };
+        // Problems inside a shared partial are reported once, against it.
+        let subject = |template: &str| {
+            if template == tpl.path {
+                name.to_string()
+            } else {
+                template.to_string()
+            }
+        };
+
+        for reference in &refs.variables {
+            used.insert(deps::normalize(&reference.ident));
+            if let Some(message) = check_reference(reference, &context) {
+                issues.insert(Issue::error(&subject(&reference.template), message));
+            }
+        }
+        for call in &refs.filters {
+            if !engine.has_filter(&call.filter) {
+                issues.insert(Issue::error(
+                    &subject(&call.template),
+                    format!("unknown filter '{}'", call.filter),
+                ));
+            }
+        }
+    }
+
+    for (path, names) in &outputs {
+        if names.len() > 1 {
+            issues.insert(Issue::error(
+                &path.display().to_string(),
+                format!(
+                    "output written by more than one template: {}",
+                    names.join(", ")
+                ),
+            ));
+        }
+    }
+
+    for group in templates.groups.keys() {
+        if let Err(e) = templates.expand(&[format!("@{}", group)]) {
+            issues.insert(Issue::error(&format!("@{}", group), e));
+        }
+    }
+
+    for path in unregistered(cfg_dir, dotfiles_dir, &templates) {
+        issues.insert(Issue::warning(
+            &path,
+            "not registered in templates.toml".to_string(),
+        ));
+    }
+
+    let mut unused: Vec<&String> = palette
+        .colors
+        .keys()
+        .filter(|color| !color_used(color, &used, &config))
+        .collect();
+    if !unused.is_empty() {
+        unused.sort();
+        let unused: Vec<&str> = unused.iter().map(|c| c.as_str()).collect();
+        issues.insert(Issue::warning(
+            &format!("palettes/{}.toml", config.flavor),
+            format!("colors not used by any template: {}", unused.join(", ")),
+        ));
+    }
+
+    Ok(issues.into_iter().collect())
+}
+
+/// Problem with a single context read, if any: the variable isn't in the
+/// context, or a filter in its chain gets the wrong kind of value.
+fn check_reference(reference: &Reference, context: &Value) -> Option<String> {
+    let ident = reference
+        .ident
+        .split('[')
+        .next()
+        .unwrap_or(&reference.ident);
+    let Some(value) = tera_internals::lookup(context, ident) else {
+        if reference.guarded {
+            return None;
+        }
+        return Some(format!("undefined variable '{}'", ident));
+    };
+
+    let mut kind = value_kind(value);
+    for filter in &reference.filters {
+        if filter == "default" || filter == "safe" {
+            continue;
+        }
+        let Some((input, output)) = filter_kinds(filter) else {
+            // Tera built-in: its output isn't tracked.
+            return None;
+        };
+        match kind {
+            Some(actual) if actual != input => {
+                return Some(wrong_kind(filter, ident, input, actual, context));
+            }
+            _ => kind = Some(output),
+        }
+    }
+    None
+}
+
+fn wrong_kind(
+    filter: &str,
+    ident: &str,
+    expected: ValueKind,
+    actual: ValueKind,
+    context: &Value,
+) -> String {
+    match expected {
+        _ if actual == ValueKind::Number => format!(
+            "filter '{}' expects a {} but '{}' is a number here",
+            filter,
+            if expected == ValueKind::Color {
+                "color"
+            } else {
+                "color name"
+            },
+            ident
+        ),
+        ValueKind::String => {
+            let name = format!("{}_name", ident);
+            if tera_internals::lookup(context, &name).is_some() {
+                format!(
+                    "filter '{}' expects a color name but '{}' is a color (use '{}')",
+                    filter, ident, name
+                )
+            } else {
+                format!(
+                    "filter '{}' expects a color name but '{}' is a color",
+                    filter, ident
+                )
+            }
+        }
+        ValueKind::Color => format!(
+            "filter '{}' expects a color but '{}' is a string here",
+            filter, ident
+        ),
+        ValueKind::Number => format!("filter '{}' expects a number", filter),
+    }
+}
+
+/// Palette colors serialize as `{r, g, b}` objects.
+fn value_kind(value: &Value) -> Option<ValueKind> {
+    match value {
+        Value::String(_) => Some(ValueKind::String),
+        Value::Object(map) if ["r", "g", "b"].iter().all(|k| map.contains_key(*k)) => {
+            Some(ValueKind::Color)
+        }
+        _ => None,
+    }
+}
+
+fn color_used(color: &str, used: &BTreeSet<String>, config: &Config) -> bool {
+    used.iter().any(|var| {
+        let root = var.split('.').next().unwrap_or(var);
+        root == color
+            || root == format!("{}_name", color)
+            || (root == "primary" && config.primary == color)
+            || (root == "secondary" && config.secondary == color)
+    })
+}
+
+/// `.tera` files under the dotfiles dir (relative paths, sorted) that no
+/// registry entry points at. The cfg partials dir is skipped.
+fn unregistered(cfg_dir: &str, dotfiles_dir: &str, templates: &TemplatesFile) -> Vec<String> {
+    let partials_dir = Path::new(cfg_dir).join(PARTIALS_DIR);
+    let registered: BTreeSet<&str> = templates
+        .templates
+        .values()
+        .map(|tpl| tpl.path.as_str())
+        .collect();
+
+    let mut paths: Vec<String> = discover_templates(Path::new(dotfiles_dir))
+        .into_iter()
+        .filter(|path| !path.starts_with(&partials_dir))
+        .filter_map(|path| {
+            let rel = path.strip_prefix(dotfiles_dir).ok()?.display().to_string();
+            (!registered.contains(rel.as_str())).then_some(rel)
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(test: &str, registry: &str, files: &[(&str, &str)]) -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("cfg-lint-{}-{}", std::process::id(), test));
+        let _ = std::fs::remove_dir_all(&root);
+        let cfg_dir = root.join("cfg");
+        std::fs::create_dir_all(cfg_dir.join("palettes")).unwrap();
+        std::fs::write(
+            cfg_dir.join("config.toml"),
+            "flavor = \"mocha\"\nprimary = \"blue\"\nsecondary = \"mauve\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            cfg_dir.join("palettes/mocha.toml"),
+            "[colors]\nblue = \"89b4fa\"\nmauve = \"cba6f7\"\nbase = \"1e1e2e\"\n",
+        )
+        .unwrap();
+        std::fs::write(cfg_dir.join("templates.toml"), registry).unwrap();
+        for (rel, body) in files {
+            let path = root.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, body).unwrap();
+        }
+        (root, cfg_dir)
+    }
+
+    fn run(root: &Path, cfg_dir: &Path) -> Vec<(Severity, String, String)> {
+        lint(cfg_dir.to_str().unwrap(), root.to_str().unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|i| (i.severity, i.subject, i.message))
+            .collect()
+    }
+
+    #[test]
+    fn clean_registry_has_no_issues() {
+        let (root, cfg_dir) = setup(
+            "clean",
+            "[templates]\nkitty = { path = \"kitty.conf.tera\" }\n",
+            &[(
+                "kitty.conf.tera",
+                "{{ base | hex }} {{ primary | lighten(amount=10) | hex }} {{ mauve_name | ansi }}\n\
+                 {% if extra %}{{ extra }}{% endif %}{{ gaps | default(value=4) }}\n",
+            )],
+        );
+        assert_eq!(run(&root, &cfg_dir), vec![]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn reports_undefined_variables_filters_and_wrong_kinds() {
+        let (root, cfg_dir) = setup(
+            "refs",
+            "[templates]\nkitty = { path = \"kitty.conf.tera\" }\n",
+            &[(
+                "kitty.conf.tera",
+                "{{ overlay3 | hex }}\n{{ blue | ansi }}\n{{ blue_name | hex }}\n\
+                 {{ base | shout }}\n{{ mauve | hex }}\n",
+            )],
+        );
+        let issues = run(&root, &cfg_dir);
+        let messages: Vec<&str> = issues.iter().map(|(_, _, m)| m.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "filter 'ansi' expects a color name but 'blue' is a color (use 'blue_name')",
+                "filter 'hex' expects a color but 'blue_name' is a string here",
+                "undefined variable 'overlay3'",
+                "unknown filter 'shout'",
+            ]
+        );
+        assert!(issues
+            .iter()
+            .all(|(severity, subject, _)| *severity == Severity::Error && subject == "kitty"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn reports_missing_files_duplicates_and_unregistered() {
+        let (root, cfg_dir) = setup(
+            "registry",
+            "[templates]\n\
+             a = { path = \"a.conf.tera\", output = \"out.conf\" }\n\
+             b = { path = \"b.conf.tera\", output = \"out.conf\" }\n\
+             gone = { path = \"gone.conf.tera\" }\n",
+            &[
+                (
+                    "a.conf.tera",
+                    "{{ blue | hex }}{{ mauve | hex }}{{ base | hex }}",
+                ),
+                ("b.conf.tera", "{{ blue | hex }}"),
+                ("extra/stray.conf.tera", "{{ blue | hex }}"),
+                ("cfg/partials/shared.tera", "{{ blue | hex }}"),
+            ],
+        );
+        let issues = run(&root, &cfg_dir);
+        assert_eq!(issues.len(), 3, "{:?}", issues);
+        assert_eq!(issues[0].1, root.join("out.conf").display().to_string());
+        assert_eq!(
+            issues[0].2,
+            "output written by more than one template: a, b"
+        );
+        assert_eq!(issues[1].1, "gone");
+        assert!(issues[1].2.contains("template not found"));
+        assert_eq!(
+            issues[2],
+            (
+                Severity::Warning,
+                "extra/stray.conf.tera".to_string(),
+                "not registered in templates.toml".to_string()
+            )
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn reports_unused_palette_colors() {
+        let (root, cfg_dir) = setup(
+            "unused",
+            "[templates]\nkitty = { path = \"kitty.conf.tera\" }\n",
+            &[("kitty.conf.tera", "{{ primary | hex }}")],
+        );
+        assert_eq!(
+            run(&root, &cfg_dir),
+            vec![(
+                Severity::Warning,
+                "palettes/mocha.toml".to_string(),
+                "colors not used by any template: base, mauve".to_string()
+            )]
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -1,13 +1,24 @@
 mod color;
 mod config;
+mod deps;
+mod diff;
+mod doctor;
 mod fonts;
 mod leds;
+mod lint;
 mod palette;
+mod palette_import;
+mod profile;
 mod render;
+mod schedule;
+mod snapshot;
 mod templates;
+mod tera_internals;
 mod tmux_bridge;
 mod tui;
+mod validate;
 mod wallpaper;
+mod watch;
 
 use std::path::PathBuf;
 
@@ -15,13 +26,13 @@ use clap::{Parser, Subcommand};
 use color::format_color;
 use config::Config;
 use palette::Palette;
-use templates::{run_update, TemplatesFile};
+use templates::{run_rollback, run_update, TemplatesFile, UpdateResult};
 
 #[derive(Parser)]
 #[command(name = "cfg")]
 #[command(about = "Linux Configuration Manager")]
 struct Cli {
-    /// Interactive settings TUI (colors, fonts, wallpapers, keyboard, update)
+    /// Interactive settings TUI (colors, fonts, wallpapers, keyboard, update, profiles)
     #[arg(short, long, global = true)]
     interactive: bool,
 
@@ -33,15 +44,79 @@ struct Cli {
 enum Command {
     /// Render templates + symlink + reload apps
     Update {
-        /// Names to update. If empty, updates all.
+        /// Names to update; `@name` expands to a tag or group. If empty, updates all.
         #[arg(conflicts_with = "list")]
         names: Vec<String>,
+        /// Also update every template with this tag or in this group (repeatable)
+        #[arg(long, value_name = "TAG", conflicts_with_all = ["list", "affected_by"])]
+        tag: Vec<String>,
         /// List available templates
         #[arg(long, conflicts_with = "dry_run")]
         list: bool,
+        /// With --list, show only templates that use this context variable
+        #[arg(long, value_name = "VAR", requires = "list")]
+        uses: Option<String>,
+        /// Update only templates that use this context variable (e.g. fonts.mono, primary)
+        #[arg(long, value_name = "VAR", conflicts_with_all = ["names", "list"])]
+        affected_by: Option<String>,
         /// Preview without writing
         #[arg(long)]
         dry_run: bool,
+        /// With --dry-run, show a unified diff of each output that would change
+        #[arg(long, requires = "dry_run", conflicts_with = "stat")]
+        diff: bool,
+        /// With --dry-run, list only outputs that would change, with line counts
+        #[arg(long, requires = "dry_run")]
+        stat: bool,
+        /// Restore the files written by the last update and re-run their reloads
+        #[arg(long, conflicts_with_all = ["names", "tag", "list", "dry_run", "affected_by"])]
+        rollback: bool,
+        /// Keep running: re-render templates whose sources, partials, config or
+        /// palette change, and reload them
+        #[arg(long, conflicts_with_all = ["names", "tag", "list", "dry_run", "affected_by", "rollback"])]
+        watch: bool,
+        /// Print a JSON report (per-template status, reloads, LEDs). Exit code is
+        /// 3 if any template failed, 4 if only reloads failed.
+        #[arg(long, conflicts_with_all = ["list", "dry_run", "watch"])]
+        json: bool,
+    },
+    /// Render a template to stdout without touching templates.toml
+    Render {
+        /// Template file (.tera)
+        template: PathBuf,
+        /// Override a context value (format: key=value, repeatable; "#rrggbb" becomes a color)
+        #[arg(long, value_name = "KEY=VALUE")]
+        set: Vec<String>,
+        /// Render with this palette instead of the configured flavor
+        #[arg(long)]
+        flavor: Option<String>,
+    },
+    /// Check every registered template for undefined variables, unknown or
+    /// misapplied filters, missing files and duplicate outputs
+    Lint,
+    /// Check config.toml, templates.toml and palettes for mistakes, and that
+    /// the external tools cfg uses are installed
+    Doctor,
+    /// Read or change any config.toml setting by dotted path
+    ///
+    /// Paths follow the TOML layout, with [n] for array elements:
+    /// `fonts.mono`, `vars.gaps`, `wallpaper.sources[1].path`.
+    Config {
+        /// Print the whole config (the default)
+        #[arg(long, group = "mode")]
+        dump: bool,
+        /// With --dump, print JSON instead of TOML
+        #[arg(long, conflicts_with_all = ["get", "set", "unset"])]
+        json: bool,
+        /// Print one value
+        #[arg(long, group = "mode", value_name = "PATH")]
+        get: Option<String>,
+        /// Set one value (format: path=value; arrays and tables as inline TOML)
+        #[arg(long, group = "mode", value_name = "PATH=VALUE")]
+        set: Option<String>,
+        /// Remove a value, restoring its default
+        #[arg(long, group = "mode", value_name = "PATH")]
+        unset: Option<String>,
     },
     /// Theme configuration (colors)
     Theme {
@@ -66,6 +141,10 @@ enum Command {
         /// Interactive picker mode
         #[arg(short, long, group = "mode")]
         interactive: bool,
+        /// Report the contrast of primary/secondary against base and text
+        /// (fails if a pair is below WCAG AA)
+        #[arg(long, group = "mode")]
+        check_contrast: bool,
     },
     /// Font configuration
     Font {
@@ -149,6 +228,28 @@ enum Command {
         #[arg(long, value_name = "PATH")]
         device: Option<PathBuf>,
     },
+    /// Color palettes beyond the Catppuccin flavors
+    Palette {
+        #[command(subcommand)]
+        action: PaletteAction,
+    },
+    /// Named snapshots of the whole config: save, switch, list and compare
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+    /// Switch profiles or theme settings at set times or sunrise/sunset
+    ///
+    /// Runs until stopped, applying each `[[schedule.rules]]` entry from
+    /// config.toml when its time comes (like `cfg theme --set ... --apply`).
+    Schedule {
+        /// Print today's switch times and exit
+        #[arg(long, conflicts_with = "once")]
+        list: bool,
+        /// Apply the rule in effect now and exit
+        #[arg(long)]
+        once: bool,
+    },
     /// Internal tmux compatibility bridge for selected tools
     #[command(hide = true, trailing_var_arg = true)]
     Tmux {
@@ -157,6 +258,63 @@ enum Command {
     },
 }
 
+#[derive(Subcommand)]
+enum PaletteAction {
+    /// Convert a base16/base24 YAML scheme, kitty theme, or Alacritty or
+    /// WezTerm TOML color scheme into a palette usable as a flavor
+    Import {
+        path: PathBuf,
+        /// Palette (flavor) name; defaults to the file name
+        #[arg(long)]
+        name: Option<String>,
+        /// Source format: base16, kitty, alacritty or wezterm (detected
+        /// from the file by default)
+        #[arg(long)]
+        format: Option<String>,
+        /// Replace an existing palette of the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// Generate a palette from a wallpaper's colors (the current one by
+    /// default), with text and accents readable on its background
+    FromWallpaper {
+        path: Option<String>,
+        /// Palette (flavor) name
+        #[arg(long, default_value = "dynamic")]
+        name: String,
+        /// Light background instead of dark
+        #[arg(long)]
+        light: bool,
+        /// Make the palette the flavor
+        #[arg(long)]
+        set_flavor: bool,
+        /// After --set-flavor, update (render + reload)
+        #[arg(long, requires = "set_flavor")]
+        apply: bool,
+        /// Replace an existing palette that wasn't generated from a wallpaper
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileAction {
+    /// Save the current config as a profile (replaces one with the same name)
+    Save { name: String },
+    /// Make a profile the current config
+    Use {
+        name: String,
+        /// Render, reload and re-apply the wallpaper after switching
+        #[arg(long)]
+        apply: bool,
+    },
+    /// List saved profiles; `*` marks ones matching the current config
+    List,
+    /// Show settings that differ between two profiles, or between a profile
+    /// and the current config
+    Diff { a: String, b: Option<String> },
+}
+
 /// Get the cfg configuration directory
 fn get_cfg_dir() -> String {
     std::env::var("CFG_DIR").unwrap_or_else(|_| {
@@ -170,6 +328,15 @@ fn get_cfg_dir() -> String {
     })
 }
 
+/// Load config.toml (defaults if it doesn't exist), exiting on a parse
+/// error instead of carrying on with defaults.
+fn load_config(config_path: &str) -> Config {
+    Config::load_or_default(config_path).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    })
+}
+
 /// Get the dotfiles root directory
 fn get_dotfiles_dir() -> String {
     // First try DOTFILES_DIR env var, then default to ~/.dotfiles
@@ -179,107 +346,451 @@ fn get_dotfiles_dir() -> String {
     })
 }
 
-/// Update apps: render + symlink + reload
-fn update_apps(cfg_dir: &str, dotfiles_dir: &str, app_names: &[String], dry_run: bool) {
-    if dry_run {
-        // Dry-run still uses direct rendering for preview output
-        let config = Config::load(&format!("{}/config.toml", cfg_dir)).unwrap_or_default();
-        let palette_path = format!("{}/palettes/{}.toml", cfg_dir, config.flavor);
-        let palette = match Palette::load(&palette_path) {
-            Ok(p) => p,
-            Err(e) => {
-                eprintln!("Error loading palette: {}", e);
+/// What `cfg update --dry-run` prints for each template
+#[derive(Clone, Copy, PartialEq)]
+enum Preview {
+    /// Every template and its output path
+    List,
+    /// Unified diff of each output that would change
+    Diff,
+    /// Changed outputs only, with added/removed line counts
+    Stat,
+}
+
+/// Render without writing and report what an update would do
+fn preview_apps(cfg_dir: &str, dotfiles_dir: &str, app_names: &[String], preview: Preview) {
+    let config = load_config(&format!("{}/config.toml", cfg_dir));
+    let palette_path = format!("{}/palettes/{}.toml", cfg_dir, config.flavor);
+    let palette = match Palette::load(&palette_path) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Error loading palette: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let context = render::build_context(&config, &palette);
+    let templates_path = format!("{}/templates.toml", cfg_dir);
+    let templates = match TemplatesFile::load(&templates_path) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error loading templates.toml: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let engine = match templates::load_engine(cfg_dir, dotfiles_dir, &templates) {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let app_names = &match templates.expand(app_names) {
+        Ok(n) => n,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let include_leds = app_names.is_empty()
+        || app_names
+            .iter()
+            .any(|name| name == leds::LEDS_UPDATE_TARGET);
+    let targets: Vec<&String> = if app_names.is_empty() {
+        templates.names()
+    } else {
+        for name in app_names {
+            if name != leds::LEDS_UPDATE_TARGET && templates.get(name).is_none() {
+                eprintln!("Unknown: {}", name);
                 std::process::exit(1);
             }
-        };
-        let context = render::build_context(&config, &palette);
-        let templates_path = format!("{}/templates.toml", cfg_dir);
-        let templates = match TemplatesFile::load(&templates_path) {
-            Ok(t) => t,
-            Err(e) => {
-                eprintln!("Error loading templates.toml: {}", e);
+        }
+        app_names
+            .iter()
+            .filter(|name| name.as_str() != leds::LEDS_UPDATE_TARGET)
+            .collect()
+    };
+    let mut changed = 0;
+    for name in &targets {
+        let tpl_config = match templates.get(name) {
+            Some(c) => c,
+            None => {
+                eprintln!("Unknown: {}", name);
                 std::process::exit(1);
             }
         };
-        let include_leds = app_names.is_empty()
-            || app_names
-                .iter()
-                .any(|name| name == leds::LEDS_UPDATE_TARGET);
-        let targets: Vec<&String> = if app_names.is_empty() {
-            templates.names()
-        } else {
-            for name in app_names {
-                if name != leds::LEDS_UPDATE_TARGET && templates.get(name).is_none() {
-                    eprintln!("Unknown: {}", name);
-                    std::process::exit(1);
-                }
+        let output = tpl_config.output_path(dotfiles_dir);
+        let rendered = tpl_config
+            .context(&context, &palette)
+            .and_then(|context| engine.render(&tpl_config.path, &context))
+            .and_then(|r| tpl_config.validate_output(&output, &r).map(|()| r));
+        let rendered = match rendered {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("  {} - error: {}", name, e);
+                continue;
             }
-            app_names
-                .iter()
-                .filter(|name| name.as_str() != leds::LEDS_UPDATE_TARGET)
-                .collect()
         };
-        for name in &targets {
-            let tpl_config = match templates.get(name) {
-                Some(c) => c,
-                None => {
-                    eprintln!("Unknown: {}", name);
-                    std::process::exit(1);
-                }
+        if preview == Preview::List {
+            println!("[dry-run] {}  →  {}", name, output.display());
+            continue;
+        }
+
+        let current = std::fs::read_to_string(&output).ok();
+        if current.as_deref() == Some(rendered.as_str()) {
+            continue;
+        }
+        changed += 1;
+        let old = current.as_deref().unwrap_or("");
+        if preview == Preview::Stat {
+            let (added, removed) = diff::line_stats(old, &rendered);
+            println!(
+                "{}  →  {}  (+{} -{})",
+                name,
+                output.display(),
+                added,
+                removed
+            );
+        } else {
+            let old_label = match current {
+                Some(_) => output.display().to_string(),
+                None => "/dev/null".to_string(),
             };
-            let template_path = PathBuf::from(format!("{}/{}", dotfiles_dir, tpl_config.path));
-            match render::render_to_file(&template_path, &context, true) {
-                Ok(output) => println!("[dry-run] {}  →  {}", name, output.display()),
-                Err(e) => eprintln!("  {} - error: {}", name, e),
-            }
+            let new_label = format!("{} (rendered)", output.display());
+            print_diff(&diff::unified(old, &rendered, &old_label, &new_label));
+        }
+    }
+    match preview {
+        Preview::List if include_leds => {
+            println!("[dry-run] leds  →  apply current theme to configured LED targets")
         }
-        if include_leds {
-            println!("[dry-run] leds  →  apply current theme to configured LED targets");
+        Preview::Stat => {
+            println!("\n{} of {} templates would change", changed, targets.len())
         }
-        return;
+        _ => {}
     }
+}
 
-    match run_update(cfg_dir, dotfiles_dir, app_names) {
+/// `cfg update` exit code when any template failed to render or write.
+/// 1 is left for errors that stop the update entirely, 2 for usage errors.
+const EXIT_RENDER_ERRORS: i32 = 3;
+/// `cfg update` exit code when everything rendered but a reload failed.
+const EXIT_RELOAD_ERRORS: i32 = 4;
+
+/// Update apps: render + symlink + reload. Returns the exit code instead
+/// of exiting, so `--apply` callers can finish their own steps first.
+fn update_apps(cfg_dir: &str, dotfiles_dir: &str, app_names: &[String]) -> i32 {
+    finish_update(run_update(cfg_dir, dotfiles_dir, app_names), false)
+}
+
+/// Exit with `code` unless it is 0
+fn exit_on_failure(code: i32) {
+    if code != 0 {
+        std::process::exit(code);
+    }
+}
+
+/// Print an update's result, as text or a JSON report, and return
+/// `update_exit_code` for it, or 1 if the update failed entirely.
+fn finish_update(result: Result<UpdateResult, String>, json: bool) -> i32 {
+    match result {
         Ok(result) => {
-            for r in &result.rendered {
-                match &r.output {
-                    Ok(output) => println!("{}  →  {}", r.name, output.display()),
-                    Err(e) => eprintln!("  {} - error: {}", r.name, e),
-                }
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&update_report(&result)).unwrap()
+                );
+            } else {
+                print_update_result(&result);
             }
-            if !result.reloaded.is_empty() {
-                println!("\nReloading...");
-                for r in &result.reloaded {
-                    let label = r.names.join(", ");
-                    match &r.result {
-                        Ok(()) => println!("  {}... ok", label),
-                        Err(e) => println!("  {}... failed: {}", label, e),
-                    }
-                }
+            update_exit_code(&result)
+        }
+        Err(e) => {
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&serde_json::json!({
+                        "status": "error",
+                        "error": e,
+                    }))
+                    .unwrap()
+                );
+            } else {
+                eprintln!("Error: {}", e);
             }
-            if let Some(led_result) = &result.leds {
-                println!("\nLEDs...");
-                match led_result {
-                    Ok(()) => println!("  leds... ok"),
-                    Err(e) => println!("  leds... warning: {}", e),
-                }
+            1
+        }
+    }
+}
+
+/// 0 when everything rendered and reloaded, otherwise `EXIT_RENDER_ERRORS`
+/// or `EXIT_RELOAD_ERRORS` (render errors win). LED failures are warnings.
+fn update_exit_code(result: &UpdateResult) -> i32 {
+    if result.rendered.iter().any(|r| r.output.is_err()) {
+        EXIT_RENDER_ERRORS
+    } else if result.reloaded.iter().any(|r| r.result.is_err()) {
+        EXIT_RELOAD_ERRORS
+    } else {
+        0
+    }
+}
+
+/// Machine-readable form of an update for `--json`
+fn update_report(result: &UpdateResult) -> serde_json::Value {
+    let millis = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+    let templates: Vec<serde_json::Value> = result
+        .rendered
+        .iter()
+        .map(|r| {
+            let status = match &r.output {
+                Ok(_) if r.unchanged => "unchanged",
+                Ok(_) => "written",
+                Err(_) => "error",
+            };
+            serde_json::json!({
+                "name": r.name,
+                "status": status,
+                "output": r.output.as_ref().ok().map(|p| p.display().to_string()),
+                "error": r.output.as_ref().err(),
+                "duration_ms": millis(r.duration),
+            })
+        })
+        .collect();
+    let reloads: Vec<serde_json::Value> = result
+        .reloaded
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "templates": r.names,
+                "command": r.cmd,
+                "background": r.background,
+                "status": if r.result.is_ok() { "ok" } else { "error" },
+                "exit_code": r.exit_code,
+                "error": r.result.as_ref().err(),
+                "duration_ms": millis(r.duration),
+            })
+        })
+        .collect();
+    let leds = result.leds.as_ref().map(|leds| match leds {
+        Ok(()) => serde_json::json!({ "status": "ok", "error": null }),
+        Err(e) => serde_json::json!({ "status": "error", "error": e }),
+    });
+    let status = match update_exit_code(result) {
+        EXIT_RENDER_ERRORS => "render_error",
+        EXIT_RELOAD_ERRORS => "reload_error",
+        _ => "ok",
+    };
+
+    serde_json::json!({
+        "status": status,
+        "templates": templates,
+        "reloads": reloads,
+        "leds": leds,
+    })
+}
+
+fn watch_apps(cfg_dir: &str, dotfiles_dir: &str) {
+    let result = watch::start(cfg_dir, dotfiles_dir).and_then(|watch| {
+        println!(
+            "Watching {} templates, config and palettes for changes...",
+            watch.len()
+        );
+        watch.run(|result| match result {
+            Ok(result) => {
+                println!();
+                print_update_result(&result);
             }
+            Err(e) => eprintln!("Error: {}", e),
+        })
+    });
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Apply a saved config change (`previous` → `config`): render and reload
+/// the templates in `scope` (all when empty), then re-pick the wallpaper if
+/// the picker is active (source_dir set, path empty) or a `wallpaper.*`
+/// setting changed. A failed re-pick is only a warning. Returns the exit
+/// code `cfg update` would use.
+fn apply_config_change(
+    cfg_dir: &str,
+    dotfiles_dir: &str,
+    scope: &[String],
+    previous: &Config,
+    config: &Config,
+) -> i32 {
+    let code = update_apps(cfg_dir, dotfiles_dir, scope);
+    let picker_active =
+        !config.wallpaper.source_dir.trim().is_empty() && config.wallpaper.path.trim().is_empty();
+    let wallpaper_changed = profile::diff(previous, config)
+        .iter()
+        .any(|change| change.key.starts_with("wallpaper."));
+    if picker_active || wallpaper_changed {
+        if let Err(e) = wallpaper::apply(config, cfg_dir) {
+            eprintln!("warning: wallpaper re-pick failed: {}", e);
         }
+    }
+    code
+}
+
+/// Apply a schedule rule the way `cfg theme --set ... --apply` does.
+/// Returns the exit code `cfg update` would use.
+fn apply_schedule_rule(cfg_dir: &str, dotfiles_dir: &str, rule: &schedule::Rule) -> i32 {
+    let (previous, config) = match schedule::apply(cfg_dir, &rule.rule) {
+        Ok(configs) => configs,
         Err(e) => {
             eprintln!("Error: {}", e);
-            std::process::exit(1);
+            return 1;
+        }
+    };
+    println!("Schedule: {}", rule);
+    apply_config_change(cfg_dir, dotfiles_dir, &[], &previous, &config)
+}
+
+/// Print lint or doctor issues with a summary; exit 1 if any is an error.
+fn report_issues(issues: &[lint::Issue]) {
+    for issue in issues {
+        println!("{}: {}: {}", issue.severity, issue.subject, issue.message);
+    }
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity == lint::Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    if issues.is_empty() {
+        println!("No problems found");
+    } else {
+        println!("\n{} error(s), {} warning(s)", errors, warnings);
+    }
+    if errors > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Print a unified diff, colored when stdout is a terminal
+fn print_diff(diff: &str) {
+    use std::io::IsTerminal;
+    if !std::io::stdout().is_terminal() {
+        print!("{}", diff);
+        return;
+    }
+    for line in diff.lines() {
+        let color = if line.starts_with("+++") || line.starts_with("---") {
+            "\x1b[1m"
+        } else if line.starts_with('+') {
+            "\x1b[32m"
+        } else if line.starts_with('-') {
+            "\x1b[31m"
+        } else if line.starts_with("@@") {
+            "\x1b[36m"
+        } else {
+            ""
+        };
+        if color.is_empty() {
+            println!("{}", line);
+        } else {
+            println!("{}{}\x1b[0m", color, line);
+        }
+    }
+}
+
+/// Restore the last update's snapshot and re-run the affected reloads
+fn rollback_apps(cfg_dir: &str, json: bool) {
+    let result = run_rollback(cfg_dir);
+    if result.is_ok() && !json {
+        println!("Restored snapshot from last update:");
+    }
+    exit_on_failure(finish_update(result, json));
+}
+
+fn print_update_result(result: &UpdateResult) {
+    for r in &result.rendered {
+        match &r.output {
+            Ok(_) if r.unchanged => println!("{}  unchanged", r.name),
+            Ok(output) => println!("{}  →  {}", r.name, output.display()),
+            Err(e) => eprintln!("  {} - error: {}", r.name, e),
+        }
+    }
+    if !result.reloaded.is_empty() {
+        println!("\nReloading...");
+        for r in &result.reloaded {
+            let label = r.names.join(", ");
+            match &r.result {
+                Ok(()) => println!("  {}... ok", label),
+                Err(e) => println!("  {}... failed: {}", label, e),
+            }
+        }
+    }
+    if let Some(led_result) = &result.leds {
+        println!("\nLEDs...");
+        match led_result {
+            Ok(()) => println!("  leds... ok"),
+            Err(e) => println!("  leds... warning: {}", e),
         }
     }
 }
 
 fn load_config_and_palette(cfg_dir: &str) -> Result<(Config, Palette), String> {
-    let config = Config::load(&format!("{}/config.toml", cfg_dir)).unwrap_or_default();
+    let config = Config::load_or_default(&format!("{}/config.toml", cfg_dir))?;
     let palette_path = format!("{}/palettes/{}.toml", cfg_dir, config.flavor);
     let palette =
         Palette::load(&palette_path).map_err(|e| format!("Error loading palette: {}", e))?;
     Ok((config, palette))
 }
 
+/// Print the contrast of primary/secondary as text on `base`, and of the
+/// more readable of `text`/`base` on them (as in highlighted labels),
+/// returning how many pairs are below WCAG AA.
+fn print_contrast_report(config: &Config, palette: &Palette) -> Result<usize, String> {
+    let color = |name: &str| {
+        palette
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("the {} palette has no '{}' color", config.flavor, name))
+    };
+    let (base, text) = (color("base")?, color("text")?);
+
+    println!(
+        "Contrast for {} (WCAG AA needs {}:1)",
+        config.flavor,
+        color::WCAG_AA
+    );
+    let mut failed = 0;
+    for (role, name) in [
+        ("primary", &config.primary),
+        ("secondary", &config.secondary),
+    ] {
+        let accent = color(name)?;
+        let (label, label_name) = if text.contrast_ratio(accent) >= base.contrast_ratio(accent) {
+            (text, "text")
+        } else {
+            (base, "base")
+        };
+        for (pair, fg, bg) in [
+            (format!("{} on base", name), accent, base),
+            (format!("{} on {}", label_name, name), label, accent),
+        ] {
+            let ratio = fg.contrast_ratio(bg);
+            let verdict = if ratio < color::WCAG_AA {
+                failed += 1;
+                "below AA"
+            } else {
+                "ok"
+            };
+            println!(
+                "  {:10} {:20} {:>6.2}:1  Lc {:>6.1}  {}",
+                role,
+                pair,
+                ratio,
+                fg.apca_contrast(bg),
+                verdict
+            );
+        }
+    }
+    Ok(failed)
+}
+
 fn print_led_statuses(
     statuses: &[leds::LedStatus],
     target_statuses: Option<&[leds::LedTargetStatus]>,
@@ -325,7 +836,7 @@ fn main() {
             std::process::exit(1);
         }
 
-        let config = Config::load(&config_path).unwrap_or_default();
+        let config = load_config(&config_path);
         let palette_path = format!("{}/palettes/{}.toml", cfg_dir, config.flavor);
         let palette = match Palette::load(&palette_path) {
             Ok(p) => p,
@@ -367,13 +878,36 @@ fn main() {
     match command {
         Command::Update {
             names,
+            tag,
             list,
+            uses,
+            affected_by,
             dry_run,
+            diff,
+            stat,
+            rollback,
+            watch,
+            json,
         } => {
             let cfg_dir = get_cfg_dir();
             let dotfiles_dir = get_dotfiles_dir();
 
-            if list {
+            if rollback {
+                rollback_apps(&cfg_dir, json);
+            } else if watch {
+                watch_apps(&cfg_dir, &dotfiles_dir);
+            } else if let Some(var) = uses {
+                let names = match templates::templates_using(&cfg_dir, &dotfiles_dir, &[&var]) {
+                    Ok(n) => n,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                for name in names {
+                    println!("{}", name);
+                }
+            } else if list {
                 // List available templates
                 let templates_path = format!("{}/templates.toml", cfg_dir);
                 let templates = match TemplatesFile::load(&templates_path) {
@@ -386,11 +920,152 @@ fn main() {
                 let mut names: Vec<String> = templates.names().into_iter().cloned().collect();
                 names.push(leds::LEDS_UPDATE_TARGET.to_string());
                 names.sort();
+                let width = names.iter().map(|n| n.len()).max().unwrap_or(0);
                 for name in names {
-                    println!("{}", name);
+                    match templates.get(&name).filter(|t| !t.tags.is_empty()) {
+                        Some(t) => {
+                            let tags: Vec<String> =
+                                t.tags.iter().map(|t| format!("@{}", t)).collect();
+                            println!("{:<width$}  {}", name, tags.join(" "));
+                        }
+                        None => println!("{}", name),
+                    }
+                }
+                if !templates.groups.is_empty() {
+                    println!("\nGroups:");
+                    for name in templates.groups.keys() {
+                        let members = templates
+                            .expand(&[format!("@{}", name)])
+                            .map(|m| m.join(", "))
+                            .unwrap_or_else(|e| format!("error: {}", e));
+                        println!("  @{}  {}", name, members);
+                    }
+                }
+            } else {
+                let names = match affected_by {
+                    Some(var) => {
+                        match templates::templates_using(&cfg_dir, &dotfiles_dir, &[&var]) {
+                            Ok(n) if n.is_empty() => {
+                                if json {
+                                    let empty = UpdateResult {
+                                        rendered: Vec::new(),
+                                        reloaded: Vec::new(),
+                                        leds: None,
+                                    };
+                                    exit_on_failure(finish_update(Ok(empty), true));
+                                } else {
+                                    println!("No templates use {}", var);
+                                }
+                                return;
+                            }
+                            Ok(n) => n,
+                            Err(e) => {
+                                eprintln!("Error: {}", e);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    None => {
+                        let mut names = names;
+                        names.extend(tag.iter().map(|t| format!("@{}", t)));
+                        names
+                    }
+                };
+                if dry_run {
+                    let preview = if diff {
+                        Preview::Diff
+                    } else if stat {
+                        Preview::Stat
+                    } else {
+                        Preview::List
+                    };
+                    preview_apps(&cfg_dir, &dotfiles_dir, &names, preview);
+                } else {
+                    exit_on_failure(finish_update(
+                        run_update(&cfg_dir, &dotfiles_dir, &names),
+                        json,
+                    ));
+                }
+            }
+        }
+        Command::Lint => {
+            let cfg_dir = get_cfg_dir();
+            let dotfiles_dir = get_dotfiles_dir();
+            match lint::lint(&cfg_dir, &dotfiles_dir) {
+                Ok(issues) => report_issues(&issues),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Doctor => {
+            let cfg_dir = get_cfg_dir();
+            let dotfiles_dir = get_dotfiles_dir();
+            report_issues(&doctor::doctor(&cfg_dir, &dotfiles_dir));
+        }
+        Command::Render {
+            template,
+            set,
+            flavor,
+        } => {
+            let cfg_dir = get_cfg_dir();
+            let dotfiles_dir = get_dotfiles_dir();
+            match templates::render_file(
+                &cfg_dir,
+                &dotfiles_dir,
+                &template,
+                flavor.as_deref(),
+                &set,
+            ) {
+                Ok(rendered) => print!("{}", rendered),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Config {
+            json,
+            get,
+            set,
+            unset,
+            ..
+        } => {
+            let cfg_dir = get_cfg_dir();
+            let config_path = format!("{}/config.toml", cfg_dir);
+            let mut config = load_config(&config_path);
+            let fail = |e: String| -> ! {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            };
+
+            if let Some(key) = get {
+                match config.get(&key) {
+                    Some(value) => println!("{}", value),
+                    None => fail(format!("Unknown config key: {}", key)),
+                }
+            } else if let Some(key_value) = set {
+                let Some((key, value)) = key_value.split_once('=') else {
+                    fail("--set requires format path=value".to_string());
+                };
+                config.set(key, value).unwrap_or_else(|e| fail(e));
+                if key == "flavor" {
+                    palette::check_flavor(&cfg_dir, &config.flavor).unwrap_or_else(|e| fail(e));
                 }
+                config.save(&config_path).unwrap_or_else(|e| fail(e));
+                println!("{}={}", key, config.get(key).unwrap_or_default());
+            } else if let Some(key) = unset {
+                config.unset(&key).unwrap_or_else(|e| fail(e));
+                config.save(&config_path).unwrap_or_else(|e| fail(e));
+                match config.get(&key).filter(|_| !key.ends_with(']')) {
+                    Some(value) => println!("{}={} (default)", key, value),
+                    None => println!("Unset {}", key),
+                }
+            } else if json {
+                println!("{}", serde_json::to_string_pretty(&config).unwrap());
             } else {
-                update_apps(&cfg_dir, &dotfiles_dir, &names, dry_run);
+                print!("{}", toml::to_string_pretty(&config).unwrap());
             }
         }
         Command::Theme {
@@ -401,14 +1076,30 @@ fn main() {
             format,
             json,
             interactive,
+            check_contrast,
         } => {
             let cfg_dir = get_cfg_dir();
             let dotfiles_dir = get_dotfiles_dir();
             let config_path = format!("{}/config.toml", cfg_dir);
 
-            let mut config = Config::load(&config_path).unwrap_or_default();
+            let mut config = load_config(&config_path);
 
-            if interactive {
+            if check_contrast {
+                let palette_path = format!("{}/palettes/{}.toml", cfg_dir, config.flavor);
+                let failed = Palette::load(&palette_path)
+                    .and_then(|palette| print_contrast_report(&config, &palette));
+                match failed {
+                    Ok(0) => {}
+                    Ok(failed) => {
+                        eprintln!("{} pair(s) below WCAG AA", failed);
+                        std::process::exit(1);
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else if interactive {
                 // Interactive TUI picker
                 if !tui::is_tty() {
                     eprintln!("Interactive mode requires a terminal");
@@ -427,7 +1118,7 @@ fn main() {
                 match tui::colors::run_picker(&config, &palette, &config_path) {
                     Ok(Some(true)) => {
                         // User wants to apply
-                        update_apps(&cfg_dir, &dotfiles_dir, &[], false);
+                        exit_on_failure(update_apps(&cfg_dir, &dotfiles_dir, &[]));
                     }
                     Ok(Some(false)) => {
                         // Saved but no apply
@@ -486,7 +1177,11 @@ fn main() {
                     eprintln!("Error: --set requires format key=value");
                     std::process::exit(1);
                 }
-                if let Err(e) = config.set(parts[0], parts[1]) {
+                let previous = config.clone();
+                if let Err(e) = config.set(parts[0], parts[1]).and_then(|_| match parts[0] {
+                    "flavor" => palette::check_flavor(&cfg_dir, &config.flavor),
+                    _ => Ok(()),
+                }) {
                     eprintln!("Error: {}", e);
                     std::process::exit(1);
                 }
@@ -497,17 +1192,13 @@ fn main() {
                 println!("{}={}", parts[0], parts[1]);
 
                 if let Some(scope) = apply {
-                    update_apps(&cfg_dir, &dotfiles_dir, &scope, false);
-                    // Re-pick wallpaper when the picker is active (source_dir set,
-                    // path empty). Pin mode is preserved; picker failure does not
-                    // fail the theme apply.
-                    if !config.wallpaper.source_dir.trim().is_empty()
-                        && config.wallpaper.path.trim().is_empty()
-                    {
-                        if let Err(e) = wallpaper::apply(&config, &cfg_dir) {
-                            eprintln!("warning: wallpaper re-pick failed: {}", e);
-                        }
-                    }
+                    exit_on_failure(apply_config_change(
+                        &cfg_dir,
+                        &dotfiles_dir,
+                        &scope,
+                        &previous,
+                        &config,
+                    ));
                 }
             } else if let Some(key) = get {
                 match config.get(&key) {
@@ -602,6 +1293,210 @@ fn main() {
                 }
             }
         }
+        Command::Palette { action } => {
+            let cfg_dir = get_cfg_dir();
+            let fail = |e: String| -> ! {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            };
+
+            match action {
+                PaletteAction::Import {
+                    path,
+                    name,
+                    format,
+                    force,
+                } => {
+                    let format = format
+                        .map(|f| palette_import::Format::parse(&f))
+                        .transpose()
+                        .unwrap_or_else(|e| fail(e));
+                    let imported =
+                        palette_import::import(&path, format).unwrap_or_else(|e| fail(e));
+                    let name = name.unwrap_or_else(|| palette_import::default_name(&path));
+                    let saved = palette_import::save(&cfg_dir, &name, &imported, &path, force)
+                        .unwrap_or_else(|e| fail(e));
+
+                    println!(
+                        "Imported {} as palette '{}' ({})",
+                        imported.scheme.as_deref().unwrap_or("scheme"),
+                        name,
+                        saved.display()
+                    );
+                    if !imported.derived.is_empty() {
+                        println!("Derived: {}", imported.derived.join(", "));
+                    }
+                    println!("Use it with: cfg theme --set flavor={}", name);
+                }
+                PaletteAction::FromWallpaper {
+                    path,
+                    name,
+                    light,
+                    set_flavor,
+                    apply,
+                    force,
+                } => {
+                    let config_path = format!("{}/config.toml", cfg_dir);
+                    let mut config = load_config(&config_path);
+                    let image = path
+                        .map_or_else(|| wallpaper::current(&config), Ok)
+                        .unwrap_or_else(|e| fail(e));
+
+                    // Regenerating replaces the last generated palette, but
+                    // not a shipped or imported one.
+                    const GENERATED: &str = "generated from wallpaper";
+                    let palette_path =
+                        palette::palette_path(&cfg_dir, &name).unwrap_or_else(|e| fail(e));
+                    let existing = std::fs::read_to_string(&palette_path).ok();
+                    if existing.is_some_and(|content| !content.contains(GENERATED)) && !force {
+                        fail(format!(
+                            "Palette '{}' already exists and wasn't generated from a wallpaper \
+                             (use --force to replace it)",
+                            name
+                        ));
+                    }
+
+                    let colors =
+                        wallpaper::generate::palette(&image, light).unwrap_or_else(|e| fail(e));
+                    let comment =
+                        format!("{} {} by `cfg palette from-wallpaper`", GENERATED, image);
+                    let saved = palette::write(&cfg_dir, &name, &colors, &comment)
+                        .unwrap_or_else(|e| fail(e));
+                    println!(
+                        "Generated palette '{}' from {} ({})",
+                        name,
+                        image,
+                        saved.display()
+                    );
+
+                    if set_flavor {
+                        config.set("flavor", &name).unwrap_or_else(|e| fail(e));
+                        config.save(&config_path).unwrap_or_else(|e| fail(e));
+                        println!("flavor={}", name);
+                        if apply {
+                            exit_on_failure(update_apps(&cfg_dir, &get_dotfiles_dir(), &[]));
+                        }
+                    } else if config.flavor != name {
+                        println!("Use it with: cfg theme --set flavor={}", name);
+                    }
+                }
+            }
+        }
+        Command::Profile { action } => {
+            let cfg_dir = get_cfg_dir();
+            let config_path = format!("{}/config.toml", cfg_dir);
+            let fail = |e: String| -> ! {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            };
+
+            match action {
+                ProfileAction::Save { name } => {
+                    let config = load_config(&config_path);
+                    let path = profile::save(&cfg_dir, &name, &config).unwrap_or_else(|e| fail(e));
+                    println!("Saved profile '{}' to {}", name, path.display());
+                }
+                ProfileAction::Use { name, apply } => {
+                    let previous = load_config(&config_path);
+                    let config = profile::switch(&cfg_dir, &name).unwrap_or_else(|e| fail(e));
+                    println!("Switched to profile '{}'", name);
+                    if apply {
+                        exit_on_failure(apply_config_change(
+                            &cfg_dir,
+                            &get_dotfiles_dir(),
+                            &[],
+                            &previous,
+                            &config,
+                        ));
+                    }
+                }
+                ProfileAction::List => {
+                    let names = profile::list(&cfg_dir).unwrap_or_else(|e| fail(e));
+                    if names.is_empty() {
+                        println!("No profiles saved (use 'cfg profile save <name>')");
+                    }
+                    let config = load_config(&config_path);
+                    let current = profile::matching(&cfg_dir, &config);
+                    for name in names {
+                        let marker = if current.contains(&name) { "*" } else { " " };
+                        println!("{} {}", marker, name);
+                    }
+                }
+                ProfileAction::Diff { a, b } => {
+                    let left = profile::load(&cfg_dir, &a).unwrap_or_else(|e| fail(e));
+                    let right = match &b {
+                        Some(b) => profile::load(&cfg_dir, b).unwrap_or_else(|e| fail(e)),
+                        None => load_config(&config_path),
+                    };
+                    let changes = profile::diff(&left, &right);
+                    if changes.is_empty() {
+                        println!("No differences");
+                    }
+                    for change in changes {
+                        println!(
+                            "{}: {} → {}",
+                            change.key,
+                            change.old.as_deref().unwrap_or("(unset)"),
+                            change.new.as_deref().unwrap_or("(unset)")
+                        );
+                    }
+                }
+            }
+        }
+        Command::Schedule { list, once } => {
+            let cfg_dir = get_cfg_dir();
+            let config_path = format!("{}/config.toml", cfg_dir);
+            let fail = |e: String| -> ! {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            };
+
+            if list || once {
+                let config = load_config(&config_path);
+                let rules = schedule::rules(&config.schedule).unwrap_or_else(|e| fail(e));
+                if rules.is_empty() {
+                    println!("No schedule rules (add [[schedule.rules]] to config.toml)");
+                    return;
+                }
+                let now = schedule::Now::current();
+                let active = schedule::active(&rules, &now, &config.schedule);
+                if once {
+                    if let Some((rule, _)) = active {
+                        let code = apply_schedule_rule(&cfg_dir, &get_dotfiles_dir(), rule);
+                        if code != 0 {
+                            std::process::exit(code);
+                        }
+                    }
+                    return;
+                }
+                for (rule, time) in schedule::today(&rules, &now, &config.schedule) {
+                    let marker = if active.is_some_and(|(a, _)| a.index == rule.index) {
+                        "*"
+                    } else {
+                        " "
+                    };
+                    let clock = time.map_or("--:--".to_string(), |t| now.clock(t));
+                    println!("{} {}  {}", marker, clock, rule);
+                }
+                if let Some((rule, time)) = schedule::next(&rules, &now, &config.schedule) {
+                    println!("\nNext: {} at {}", rule.rule.at, now.clock(time));
+                }
+            } else {
+                let dotfiles_dir = get_dotfiles_dir();
+                println!(
+                    "Following schedule in {} (checked every {}s)...",
+                    config_path,
+                    schedule::TICK.as_secs()
+                );
+                schedule::run(&cfg_dir, |event| match event {
+                    Ok(rule) => {
+                        println!();
+                        apply_schedule_rule(&cfg_dir, &dotfiles_dir, rule);
+                    }
+                    Err(e) => eprintln!("Error: {}", e),
+                });
+            }
+        }
         Command::Tmux { args } => {
             if let Err(e) = tmux_bridge::run(&args) {
                 eprintln!("Error: {}", e);
@@ -624,7 +1519,7 @@ fn main() {
             let dotfiles_dir = get_dotfiles_dir();
             let config_path = format!("{}/config.toml", cfg_dir);
 
-            let mut config = Config::load(&config_path).unwrap_or_default();
+            let mut config = load_config(&config_path);
 
             if scratchpad {
                 // Scratchpad preview mode - read font from temp file
@@ -647,7 +1542,7 @@ fn main() {
                 ) {
                     Ok(true) => {
                         // User saved - update apps that use mono font
-                        update_apps(&cfg_dir, &dotfiles_dir, &[], false);
+                        exit_on_failure(update_apps(&cfg_dir, &dotfiles_dir, &[]));
                     }
                     Ok(false) => {}
                     Err(e) => {
@@ -686,7 +1581,7 @@ fn main() {
                 match tui::fonts::run_picker(&config, &palette, &config_path) {
                     Ok(Some(true)) => {
                         // User wants to apply
-                        update_apps(&cfg_dir, &dotfiles_dir, &[], false);
+                        exit_on_failure(update_apps(&cfg_dir, &dotfiles_dir, &[]));
                     }
                     Ok(Some(false)) => {
                         // Saved but no apply
@@ -909,7 +1804,7 @@ fn main() {
                 println!("{}={}", key, value);
 
                 if let Some(scope) = apply {
-                    update_apps(&cfg_dir, &dotfiles_dir, &scope, false);
+                    exit_on_failure(update_apps(&cfg_dir, &dotfiles_dir, &scope));
                 }
             } else if let Some(key) = get {
                 // Get specific font value
@@ -945,7 +1840,7 @@ fn main() {
             let cfg_dir = get_cfg_dir();
             let config_path = format!("{}/config.toml", cfg_dir);
 
-            let mut config = Config::load(&config_path).unwrap_or_default();
+            let mut config = load_config(&config_path);
 
             if let Some(path) = scratchpad {
                 if let Err(e) = wallpaper::scratchpad::run(&path) {
@@ -970,7 +1865,7 @@ fn main() {
                 };
                 match tui::wallpapers::run(&config, &palette, &config_path, &cfg_dir) {
                     Ok(Some(true)) => {
-                        let config = Config::load(&config_path).unwrap_or_default();
+                        let config = load_config(&config_path);
                         if let Err(e) = wallpaper::apply(&config, &cfg_dir) {
                             eprintln!("Error: {}", e);
                             std::process::exit(1);
@@ -1,13 +1,15 @@
 use crate::color::Color;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 
 #[derive(Deserialize)]
 struct PaletteFile {
     colors: HashMap<String, String>,
 }
 
-/// Catppuccin color palette
+/// Color palette: the four Catppuccin flavors, or any other palette using
+/// their color names (see `palette_import`)
 pub struct Palette {
     pub colors: HashMap<String, Color>,
 }
@@ -22,8 +24,11 @@ impl Palette {
             .map_err(|e| format!("Failed to parse palette file '{}': {}", path, e))?;
 
         let mut colors = HashMap::new();
-        for (name, hex) in file.colors {
-            colors.insert(name.clone(), Color::from_hex(&hex)?);
+        for (name, value) in file.colors {
+            let color = Color::parse(&value).map_err(|e| {
+                format!("Invalid color '{}' in palette file '{}': {}", name, path, e)
+            })?;
+            colors.insert(name, color);
         }
 
         Ok(Palette { colors })
@@ -34,3 +39,114 @@ impl Palette {
         self.colors.get(name)
     }
 }
+
+/// The Catppuccin color names templates use, in the order palette files
+/// list them. Imported and generated palettes have all of them.
+pub const NAMES: &[&str] = &[
+    "rosewater",
+    "flamingo",
+    "pink",
+    "mauve",
+    "red",
+    "maroon",
+    "peach",
+    "yellow",
+    "green",
+    "teal",
+    "sky",
+    "sapphire",
+    "blue",
+    "lavender",
+    "text",
+    "subtext1",
+    "subtext0",
+    "overlay2",
+    "overlay1",
+    "overlay0",
+    "surface2",
+    "surface1",
+    "surface0",
+    "base",
+    "mantle",
+    "crust",
+];
+
+/// Directory holding the palettes, one `<flavor>.toml` each.
+pub fn palettes_dir(cfg_dir: &str) -> PathBuf {
+    PathBuf::from(format!("{}/palettes", cfg_dir))
+}
+
+/// Error unless `name` can name a palette file.
+pub fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(format!(
+            "Invalid palette name '{}' (use letters, digits, '-', '_' and '.')",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Path of palette `name`.
+pub fn palette_path(cfg_dir: &str, name: &str) -> Result<PathBuf, String> {
+    check_name(name)?;
+    Ok(palettes_dir(cfg_dir).join(format!("{}.toml", name)))
+}
+
+/// Flavors with a palette file, sorted. No palettes dir means none.
+pub fn flavors(cfg_dir: &str) -> Result<Vec<String>, String> {
+    let dir = palettes_dir(cfg_dir);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read '{}': {}", dir.display(), e)),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "toml" {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().into_owned())
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Error unless `flavor` has a palette file in `cfg_dir`.
+pub fn check_flavor(cfg_dir: &str, flavor: &str) -> Result<(), String> {
+    let valid = flavors(cfg_dir)?;
+    if !valid.iter().any(|name| name == flavor) {
+        return Err(format!("Invalid flavor '{}'. Valid: {:?}", flavor, valid));
+    }
+    Ok(())
+}
+
+/// Write `colors` as palette `name` in the order of `NAMES`, with `comment`
+/// as a header line. Replaces any palette of that name.
+pub fn write(
+    cfg_dir: &str,
+    name: &str,
+    colors: &BTreeMap<String, Color>,
+    comment: &str,
+) -> Result<PathBuf, String> {
+    let path = palette_path(cfg_dir, name)?;
+    let mut content = format!("# {}\n[colors]\n", comment);
+    for name in NAMES {
+        if let Some(color) = colors.get(*name) {
+            content.push_str(&format!("{} = \"{}\"\n", name, color.to_hex()));
+        }
+    }
+    std::fs::create_dir_all(palettes_dir(cfg_dir))
+        .map_err(|e| format!("Failed to create palettes dir: {}", e))?;
+    std::fs::write(&path, content)
+        .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+    Ok(path)
+}
@@ -0,0 +1,561 @@
+//! `cfg palette import`: convert base16/base24 schemes, kitty themes and
+//! Alacritty/WezTerm color schemes into cfg palettes.
+//!
+//! Templates use the Catppuccin color names, so imported colors are mapped
+//! onto them the way Catppuccin's own ports map theirs: the background is
+//! `base`, the foreground `text` and the ANSI colors the accents. Names a
+//! scheme has no color for are derived from the ones it has (see `DERIVED`).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::color::Color;
+use crate::palette;
+
+/// base16 (base00-base0F) and base24 (base10-base17) slots. base24's darker
+/// backgrounds replace base01 as `mantle`.
+const BASE16: &[(&str, &str)] = &[
+    ("base00", "base"),
+    ("base01", "mantle"),
+    ("base02", "surface0"),
+    ("base03", "surface1"),
+    ("base04", "surface2"),
+    ("base05", "text"),
+    ("base06", "rosewater"),
+    ("base07", "lavender"),
+    ("base08", "red"),
+    ("base09", "peach"),
+    ("base0a", "yellow"),
+    ("base0b", "green"),
+    ("base0c", "teal"),
+    ("base0d", "blue"),
+    ("base0e", "mauve"),
+    ("base0f", "flamingo"),
+    ("base10", "mantle"),
+    ("base11", "crust"),
+    ("base12", "maroon"),
+    ("base15", "sky"),
+    ("base16", "sapphire"),
+    ("base17", "pink"),
+];
+
+/// Terminal ANSI colors 0-15. Bright green and yellow have no name of
+/// their own.
+const ANSI: &[(usize, &str)] = &[
+    (0, "surface1"),
+    (1, "red"),
+    (2, "green"),
+    (3, "yellow"),
+    (4, "blue"),
+    (5, "mauve"),
+    (6, "teal"),
+    (7, "subtext0"),
+    (8, "surface2"),
+    (9, "maroon"),
+    (12, "lavender"),
+    (13, "pink"),
+    (14, "sky"),
+    (15, "subtext1"),
+];
+
+/// How a missing color is made from others.
+enum Derive {
+    Same(&'static str),
+    /// OKLab mix, with this percentage of the first color
+    Mix(&'static str, &'static str, f64),
+    /// OKLCH lightness points darker
+    Darker(&'static str, f64),
+}
+
+/// Missing colors, in the order they're derived (later ones can use
+/// earlier ones). The greys sit between `base` and `text` where
+/// Catppuccin's do.
+const DERIVED: &[(&str, Derive)] = &[
+    ("mantle", Derive::Darker("base", 2.5)),
+    ("crust", Derive::Darker("base", 5.0)),
+    ("surface0", Derive::Mix("text", "base", 12.0)),
+    ("surface1", Derive::Mix("text", "base", 25.0)),
+    ("surface2", Derive::Mix("text", "base", 37.0)),
+    ("overlay0", Derive::Mix("text", "base", 49.0)),
+    ("overlay1", Derive::Mix("text", "base", 60.0)),
+    ("overlay2", Derive::Mix("text", "base", 71.0)),
+    ("subtext0", Derive::Mix("text", "base", 81.0)),
+    ("subtext1", Derive::Mix("text", "base", 90.0)),
+    ("peach", Derive::Mix("red", "yellow", 50.0)),
+    ("maroon", Derive::Same("red")),
+    ("pink", Derive::Same("mauve")),
+    ("flamingo", Derive::Mix("red", "text", 50.0)),
+    ("rosewater", Derive::Mix("flamingo", "text", 50.0)),
+    ("sky", Derive::Mix("teal", "blue", 50.0)),
+    ("sapphire", Derive::Mix("blue", "sky", 50.0)),
+    ("lavender", Derive::Mix("blue", "text", 70.0)),
+];
+
+/// Scheme file formats `cfg palette import` reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Base16,
+    Kitty,
+    Alacritty,
+    WezTerm,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "base16" | "base24" => Ok(Format::Base16),
+            "kitty" => Ok(Format::Kitty),
+            "alacritty" => Ok(Format::Alacritty),
+            "wezterm" => Ok(Format::WezTerm),
+            _ => Err(format!(
+                "Unknown format '{}' (use base16, kitty, alacritty or wezterm)",
+                name
+            )),
+        }
+    }
+
+    /// Guess from the extension, and for TOML from the tables it has.
+    fn detect(path: &Path, content: &str) -> Result<Self, String> {
+        let unknown = || {
+            format!(
+                "Can't tell the format of '{}' (use --format base16, kitty, alacritty or wezterm)",
+                path.display()
+            )
+        };
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => Ok(Format::Base16),
+            Some("conf") => Ok(Format::Kitty),
+            Some("toml") => {
+                let value: toml::Value = toml::from_str(content).map_err(|_| unknown())?;
+                match value.get("colors") {
+                    Some(colors) if colors.get("ansi").is_some() => Ok(Format::WezTerm),
+                    Some(colors) if colors.get("normal").is_some() => Ok(Format::Alacritty),
+                    _ => Err(unknown()),
+                }
+            }
+            _ => Err(unknown()),
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Format::Base16 => "base16",
+            Format::Kitty => "kitty",
+            Format::Alacritty => "alacritty",
+            Format::WezTerm => "wezterm",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A scheme converted to cfg color names.
+pub struct Imported {
+    pub format: Format,
+    /// The scheme's own name, if the file has one
+    pub scheme: Option<String>,
+    /// Every name in `palette::NAMES`
+    pub colors: BTreeMap<String, Color>,
+    /// Names the scheme had no color for
+    pub derived: Vec<String>,
+}
+
+/// Read and convert the scheme at `path`. `format` is detected when `None`.
+pub fn import(path: &Path, format: Option<Format>) -> Result<Imported, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let format = match format {
+        Some(format) => format,
+        None => Format::detect(path, &content)?,
+    };
+    let (scheme, mut colors) = match format {
+        Format::Base16 => parse_base16(&content),
+        Format::Kitty => parse_kitty(&content),
+        Format::Alacritty => parse_alacritty(&content),
+        Format::WezTerm => parse_wezterm(&content),
+    }
+    .map_err(|e| format!("{} ({} scheme): {}", path.display(), format, e))?;
+
+    let derived = derive_missing(&mut colors);
+    Ok(Imported {
+        format,
+        scheme,
+        colors,
+        derived,
+    })
+}
+
+/// Fill in every palette name `colors` lacks from the ones it has (see
+/// `DERIVED`), returning the names filled in. Needs `base`, `text`, `red`,
+/// `yellow`, `green`, `teal`, `blue` and `mauve`.
+pub fn derive_missing(colors: &mut BTreeMap<String, Color>) -> Vec<String> {
+    let mut derived = Vec::new();
+    for (role, derive) in DERIVED {
+        if colors.contains_key(*role) {
+            continue;
+        }
+        let get = |name: &str| colors[name];
+        let color = match derive {
+            Derive::Same(from) => get(from),
+            Derive::Mix(a, b, amount) => get(a).mix_oklab(&get(b), *amount),
+            Derive::Darker(from, amount) => get(from).lighten_l(-amount),
+        };
+        colors.insert(role.to_string(), color);
+        derived.push(role.to_string());
+    }
+    derived
+}
+
+/// Palette name for a scheme file: its file name, lowercased, with anything
+/// but letters and digits turned into '-' ("Tokyo Night.conf" -> "tokyo-night").
+pub fn default_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    stem.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Write `imported` as palette `name`. An existing palette is only replaced
+/// with `force`.
+pub fn save(
+    cfg_dir: &str,
+    name: &str,
+    imported: &Imported,
+    source: &Path,
+    force: bool,
+) -> Result<PathBuf, String> {
+    if palette::palette_path(cfg_dir, name)?.exists() && !force {
+        return Err(format!(
+            "Palette '{}' already exists (use --force to replace it)",
+            name
+        ));
+    }
+    let comment = format!(
+        "{}imported from {} ({}) by `cfg palette import`",
+        imported
+            .scheme
+            .as_ref()
+            .map(|scheme| format!("{}, ", scheme))
+            .unwrap_or_default(),
+        source.display(),
+        imported.format
+    );
+    palette::write(cfg_dir, name, &imported.colors, &comment)
+}
+
+type Parsed = (Option<String>, BTreeMap<String, Color>);
+
+/// Imported colors are opaque; alpha in the source is dropped.
+fn color(value: &str, key: &str) -> Result<Color, String> {
+    let hex = value.trim();
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    Color::parse(hex)
+        .map(|c| c.with_alpha(None))
+        .map_err(|_| format!("invalid color for {}: '{}'", key, value))
+}
+
+/// base16/base24 YAML, flat (`base00: "1d2021"`) or with the slots under
+/// `palette:` as in tinted-theming's newer files. Only `key: value` lines
+/// are needed, so this isn't a YAML parser.
+fn parse_base16(content: &str) -> Result<Parsed, String> {
+    let mut scheme = None;
+    let mut slots = BTreeMap::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+            _ => value.split(" #").next().unwrap_or_default().trim(),
+        };
+        match key.as_str() {
+            "scheme" | "name" if !value.is_empty() => scheme = Some(value.to_string()),
+            _ if key.starts_with("base") && key.len() == 6 => {
+                slots.insert(key, color(value, line.trim())?);
+            }
+            _ => {}
+        }
+    }
+
+    let missing: Vec<&str> = BASE16[..16]
+        .iter()
+        .map(|(slot, _)| *slot)
+        .filter(|slot| !slots.contains_key(*slot))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("missing {}", missing.join(", ")));
+    }
+    let colors = BASE16
+        .iter()
+        .filter_map(|(slot, role)| Some((role.to_string(), *slots.get(*slot)?)))
+        .collect();
+    Ok((scheme, colors))
+}
+
+/// kitty themes: `background`, `foreground` and `color0`-`color15` lines,
+/// and the name from a `## name:` comment.
+fn parse_kitty(content: &str) -> Result<Parsed, String> {
+    let mut scheme = None;
+    let (mut background, mut foreground) = (None, None);
+    let mut ansi = [None; 16];
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("## name:") {
+            scheme = Some(name.trim().to_string());
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        match key {
+            "background" => background = Some(color(value, key)?),
+            "foreground" => foreground = Some(color(value, key)?),
+            _ => {
+                if let Some(slot) = key
+                    .strip_prefix("color")
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .and_then(|n| ansi.get_mut(n))
+                {
+                    *slot = Some(color(value, key)?);
+                }
+            }
+        }
+    }
+    Ok((scheme, terminal(background, foreground, ansi)?))
+}
+
+/// Alacritty: `[colors.primary]` background/foreground, and
+/// `[colors.normal]`/`[colors.bright]` black, red, ... white.
+fn parse_alacritty(content: &str) -> Result<Parsed, String> {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    let value: toml::Value = toml::from_str(content).map_err(|e| e.to_string())?;
+    let lookup = |path: &str| -> Result<Option<Color>, String> {
+        let mut current = &value;
+        for part in path.split('.') {
+            match current.get(part) {
+                Some(next) => current = next,
+                None => return Ok(None),
+            }
+        }
+        match current.as_str() {
+            Some(s) => color(s, path).map(Some),
+            None => Err(format!("{} is not a color string", path)),
+        }
+    };
+
+    let mut ansi = [None; 16];
+    for (i, name) in NAMES.iter().enumerate() {
+        ansi[i] = lookup(&format!("colors.normal.{}", name))?;
+        ansi[i + 8] = lookup(&format!("colors.bright.{}", name))?;
+    }
+    let colors = terminal(
+        lookup("colors.primary.background")?,
+        lookup("colors.primary.foreground")?,
+        ansi,
+    )?;
+    Ok((None, colors))
+}
+
+/// WezTerm: `[colors]` background, foreground, `ansi` and `brights`
+/// arrays, and `[metadata] name`.
+fn parse_wezterm(content: &str) -> Result<Parsed, String> {
+    let value: toml::Value = toml::from_str(content).map_err(|e| e.to_string())?;
+    let colors = value.get("colors").ok_or("no [colors] table")?;
+    let get = |key: &str| -> Result<Option<Color>, String> {
+        colors
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(|s| color(s, key))
+            .transpose()
+    };
+
+    let mut ansi = [None; 16];
+    for (offset, key) in [(0, "ansi"), (8, "brights")] {
+        let list = colors.get(key).and_then(|v| v.as_array());
+        for (i, item) in list.into_iter().flatten().take(8).enumerate() {
+            let s = item
+                .as_str()
+                .ok_or_else(|| format!("{}[{}] is not a color string", key, i))?;
+            ansi[offset + i] = Some(color(s, &format!("{}[{}]", key, i))?);
+        }
+    }
+    let scheme = value
+        .get("metadata")
+        .and_then(|m| m.get("name"))
+        .and_then(|n| n.as_str())
+        .map(str::to_string);
+    Ok((
+        scheme,
+        terminal(get("background")?, get("foreground")?, ansi)?,
+    ))
+}
+
+/// Map a terminal scheme onto cfg names. The background, foreground and
+/// the eight normal colors are required.
+fn terminal(
+    background: Option<Color>,
+    foreground: Option<Color>,
+    ansi: [Option<Color>; 16],
+) -> Result<BTreeMap<String, Color>, String> {
+    let mut missing = BTreeSet::new();
+    if background.is_none() {
+        missing.insert("background".to_string());
+    }
+    if foreground.is_none() {
+        missing.insert("foreground".to_string());
+    }
+    for (i, color) in ansi[..8].iter().enumerate() {
+        if color.is_none() {
+            missing.insert(format!("color{}", i));
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!(
+            "missing {}",
+            missing.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+
+    let mut colors = BTreeMap::from([
+        ("base".to_string(), background.unwrap()),
+        ("text".to_string(), foreground.unwrap()),
+    ]);
+    for (i, role) in ANSI {
+        if let Some(color) = ansi[*i] {
+            colors.insert(role.to_string(), color);
+        }
+    }
+    Ok(colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("cfg-palette-import-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn imports_every_format_onto_catppuccin_names() {
+        let base16 = write(
+            "gruvbox-dark.yaml",
+            "scheme: \"Gruvbox dark, medium\" # comment\nauthor: someone\n\
+             base00: \"282828\"\nbase01: \"3c3836\"\nbase02: \"504945\"\n\
+             base03: \"665c54\"\nbase04: \"bdae93\"\nbase05: \"d5c4a1\"\n\
+             base06: \"ebdbb2\"\nbase07: \"fbf1c7\"\nbase08: \"fb4934\"\n\
+             base09: \"fe8019\"\nbase0A: \"fabd2f\"\nbase0B: \"b8bb26\"\n\
+             base0C: \"8ec07c\"\nbase0D: \"83a598\"\nbase0E: \"d3869b\"\n\
+             base0F: \"d65d0e\"\n",
+        );
+        let imported = import(&base16, None).unwrap();
+        assert_eq!(imported.format, Format::Base16);
+        assert_eq!(imported.scheme.as_deref(), Some("Gruvbox dark, medium"));
+        assert_eq!(imported.colors["base"].to_hex(), "282828");
+        assert_eq!(imported.colors["peach"].to_hex(), "fe8019");
+        assert_eq!(imported.colors["yellow"].to_hex(), "fabd2f");
+        assert_eq!(imported.colors.len(), palette::NAMES.len());
+        assert!(imported.derived.contains(&"crust".to_string()));
+        assert!(!imported.derived.contains(&"peach".to_string()));
+
+        let kitty = write(
+            "Nord.conf",
+            "## name: Nord\nbackground #2e3440\nforeground   #d8dee9\n\
+             color0 #3b4252\ncolor1 #bf616a\ncolor2 #a3be8c\ncolor3 #ebcb8b\n\
+             color4 #81a1c1\ncolor5 #b48ead\ncolor6 #88c0d0\ncolor7 #e5e9f0\n\
+             # bright colors\ncolor13 #b48ead\n",
+        );
+        let imported = import(&kitty, None).unwrap();
+        assert_eq!(imported.format, Format::Kitty);
+        assert_eq!(imported.scheme.as_deref(), Some("Nord"));
+        assert_eq!(imported.colors["text"].to_hex(), "d8dee9");
+        assert_eq!(imported.colors["mauve"].to_hex(), "b48ead");
+        assert_eq!(imported.colors["maroon"].to_hex(), "bf616a");
+        assert_eq!(imported.colors.len(), palette::NAMES.len());
+
+        let alacritty = write(
+            "tokyo.toml",
+            "[colors.primary]\nbackground = '0x1a1b26'\nforeground = '#c0caf5'\n\
+             [colors.normal]\nblack = '#15161e'\nred = '#f7768e'\ngreen = '#9ece6a'\n\
+             yellow = '#e0af68'\nblue = '#7aa2f7'\nmagenta = '#bb9af7'\n\
+             cyan = '#7dcfff'\nwhite = '#a9b1d6'\n",
+        );
+        let imported = import(&alacritty, None).unwrap();
+        assert_eq!(imported.format, Format::Alacritty);
+        assert_eq!(imported.colors["base"].to_hex(), "1a1b26");
+        assert_eq!(imported.colors["teal"].to_hex(), "7dcfff");
+
+        let wezterm = write(
+            "scheme.toml",
+            "[colors]\nbackground = '#1a1b26'\nforeground = '#c0caf5'\n\
+             ansi = ['#15161e', '#f7768e', '#9ece6a', '#e0af68', '#7aa2f7', '#bb9af7', '#7dcfff', '#a9b1d6']\n\
+             brights = ['#414868', '#ff899d', '#9fe044', '#faba4a', '#8db0ff', '#c7a9ff', '#a4daff', '#c0caf5']\n\
+             [metadata]\nname = 'Tokyo Night'\n",
+        );
+        let imported = import(&wezterm, None).unwrap();
+        assert_eq!(imported.format, Format::WezTerm);
+        assert_eq!(imported.scheme.as_deref(), Some("Tokyo Night"));
+        assert_eq!(imported.colors["surface2"].to_hex(), "414868");
+        assert_eq!(imported.colors["pink"].to_hex(), "c7a9ff");
+
+        let partial = write("partial.conf", "background #000000\ncolor1 #ff0000\n");
+        let err = import(&partial, None).err().unwrap();
+        assert!(
+            err.contains(
+                "missing color0, color2, color3, color4, color5, color6, color7, foreground"
+            ),
+            "{}",
+            err
+        );
+        assert_eq!(
+            default_name(Path::new("/x/Tokyo Night (Storm).conf")),
+            "tokyo-night-storm"
+        );
+    }
+
+    #[test]
+    fn derived_greys_go_from_base_to_text() {
+        let path = write(
+            "derive.toml",
+            &format!(
+                "[colors]\nbackground = '#{}'\nforeground = '#{}'\nansi = [{}]\n",
+                "1e1e2e",
+                "cdd6f4",
+                ["45475a", "f38ba8", "a6e3a1", "f9e2af", "89b4fa", "cba6f7", "94e2d5", "bac2de"]
+                    .map(|hex| format!("'#{}'", hex))
+                    .join(", ")
+            ),
+        );
+        let imported = import(&path, Some(Format::WezTerm)).unwrap();
+        let lightness = |role: &str| imported.colors[role].to_oklab().l;
+        let greys = [
+            "crust", "mantle", "base", "surface0", "surface1", "surface2", "overlay0", "overlay1",
+            "overlay2", "subtext0", "subtext1", "text",
+        ];
+        for pair in greys.windows(2) {
+            // surface1 and subtext0 come from the scheme (color0, color7)
+            if pair.contains(&"surface1") || pair.contains(&"subtext0") {
+                continue;
+            }
+            assert!(lightness(pair[0]) < lightness(pair[1]), "{:?}", pair);
+        }
+        assert!(imported.derived.contains(&"overlay1".to_string()));
+        assert!(!imported.derived.contains(&"subtext0".to_string()));
+    }
+}
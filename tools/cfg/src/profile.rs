@@ -0,0 +1,239 @@
+//! Named snapshots of the whole `config.toml`.
+//!
+//! Each profile is a complete `Config` saved as `<cfg_dir>/profiles/<name>.toml`,
+//! so switching profiles is a copy over `config.toml`. The `[schedule]`
+//! section is left out: it decides when to switch profiles, so it stays
+//! with `config.toml`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::palette;
+
+/// Directory holding saved profiles.
+pub fn profiles_dir(cfg_dir: &str) -> PathBuf {
+    PathBuf::from(format!("{}/profiles", cfg_dir))
+}
+
+fn profile_path(cfg_dir: &str, name: &str) -> Result<PathBuf, String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(format!(
+            "Invalid profile name '{}' (use letters, digits, '-', '_' and '.')",
+            name
+        ));
+    }
+    Ok(profiles_dir(cfg_dir).join(format!("{}.toml", name)))
+}
+
+/// Save `config` as profile `name`, replacing any profile of that name.
+pub fn save(cfg_dir: &str, name: &str, config: &Config) -> Result<PathBuf, String> {
+    let path = profile_path(cfg_dir, name)?;
+    let config = Config {
+        schedule: Default::default(),
+        ..config.clone()
+    };
+    config.save(&path.to_string_lossy())?;
+    Ok(path)
+}
+
+/// Load profile `name`.
+pub fn load(cfg_dir: &str, name: &str) -> Result<Config, String> {
+    let path = profile_path(cfg_dir, name)?;
+    if !path.exists() {
+        return Err(format!("Unknown profile: {}", name));
+    }
+    Config::load(&path.to_string_lossy())
+}
+
+/// Make profile `name` the current config. Returns the new config. Fails,
+/// leaving config.toml alone, if the profile's flavor has no palette.
+pub fn switch(cfg_dir: &str, name: &str) -> Result<Config, String> {
+    let config_path = format!("{}/config.toml", cfg_dir);
+    let config = Config {
+        schedule: Config::load_or_default(&config_path)?.schedule,
+        ..load(cfg_dir, name)?
+    };
+    palette::check_flavor(cfg_dir, &config.flavor)?;
+    config.save(&config_path)?;
+    Ok(config)
+}
+
+/// Names of all saved profiles, sorted. No profiles dir means no profiles.
+pub fn list(cfg_dir: &str) -> Result<Vec<String>, String> {
+    let dir = profiles_dir(cfg_dir);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read '{}': {}", dir.display(), e)),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "toml" {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().into_owned())
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Profiles whose settings match `config` exactly.
+pub fn matching(cfg_dir: &str, config: &Config) -> Vec<String> {
+    list(cfg_dir)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| load(cfg_dir, name).is_ok_and(|p| diff(&p, config).is_empty()))
+        .collect()
+}
+
+/// One setting that differs between two configs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// Dotted key, as accepted by `cfg theme --get` (e.g. "fonts.mono")
+    pub key: String,
+    /// Value on the left side, `None` if unset there
+    pub old: Option<String>,
+    /// Value on the right side, `None` if unset there
+    pub new: Option<String>,
+}
+
+/// Settings that differ between `a` and `b`, sorted by key.
+pub fn diff(a: &Config, b: &Config) -> Vec<Change> {
+    let (a, b) = (flatten(a), flatten(b));
+    let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|key| a.get(*key) != b.get(*key))
+        .map(|key| Change {
+            key: key.clone(),
+            old: a.get(key).cloned(),
+            new: b.get(key).cloned(),
+        })
+        .collect()
+}
+
+/// Every leaf of `config` except the schedule as dotted key → value. Arrays
+/// (LED devices, wallpaper sources) are compared whole.
+fn flatten(config: &Config) -> BTreeMap<String, String> {
+    fn walk(prefix: &str, value: &toml::Value, out: &mut BTreeMap<String, String>) {
+        match value {
+            toml::Value::Table(table) => {
+                for (key, value) in table {
+                    let key = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    };
+                    walk(&key, value, out);
+                }
+            }
+            toml::Value::String(s) => {
+                out.insert(prefix.to_string(), s.clone());
+            }
+            other => {
+                out.insert(prefix.to_string(), other.to_string());
+            }
+        }
+    }
+
+    let config = Config {
+        schedule: Default::default(),
+        ..config.clone()
+    };
+    let mut out = BTreeMap::new();
+    if let Ok(value) = toml::Value::try_from(&config) {
+        walk("", &value, &mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cfg_dir(name: &str) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("cfg-profile-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn save_list_and_switch() {
+        let cfg_dir = temp_cfg_dir("switch");
+        assert!(list(&cfg_dir).unwrap().is_empty());
+        fs::create_dir_all(format!("{}/palettes", cfg_dir)).unwrap();
+        for flavor in ["mocha", "latte"] {
+            fs::write(
+                format!("{}/palettes/{}.toml", cfg_dir, flavor),
+                "[colors]\n",
+            )
+            .unwrap();
+        }
+
+        let mut work = Config::default();
+        work.set("flavor", "latte").unwrap();
+        work.set("primary", "peach").unwrap();
+        save(&cfg_dir, "work-latte", &work).unwrap();
+        save(&cfg_dir, "evening", &Config::default()).unwrap();
+
+        assert_eq!(list(&cfg_dir).unwrap(), vec!["evening", "work-latte"]);
+        assert_eq!(matching(&cfg_dir, &work), vec!["work-latte"]);
+
+        // The schedule belongs to config.toml, not to profiles
+        let mut scheduled = Config::default();
+        scheduled.set("schedule.latitude", "52.5").unwrap();
+        scheduled.save(&format!("{}/config.toml", cfg_dir)).unwrap();
+
+        let current = switch(&cfg_dir, "work-latte").unwrap();
+        assert_eq!(current.flavor, "latte");
+        assert_eq!(current.schedule.latitude, Some(52.5));
+        let saved = Config::load(&format!("{}/config.toml", cfg_dir)).unwrap();
+        assert_eq!(saved.primary, "peach");
+
+        assert_eq!(
+            switch(&cfg_dir, "nope").unwrap_err(),
+            "Unknown profile: nope"
+        );
+
+        // A profile whose palette is gone doesn't replace the config
+        fs::remove_file(format!("{}/palettes/mocha.toml", cfg_dir)).unwrap();
+        let err = switch(&cfg_dir, "evening").unwrap_err();
+        assert!(err.starts_with("Invalid flavor 'mocha'"), "{}", err);
+        let saved = Config::load(&format!("{}/config.toml", cfg_dir)).unwrap();
+        assert_eq!(saved.flavor, "latte");
+
+        assert!(save(&cfg_dir, "../escape", &work).is_err());
+        assert!(save(&cfg_dir, ".hidden", &work).is_err());
+    }
+
+    #[test]
+    fn diff_lists_changed_nested_and_added_keys() {
+        let a = Config::default();
+        let mut b = Config::default();
+        b.set("flavor", "latte").unwrap();
+        b.set("fonts.mono_size", "12").unwrap();
+        b.set("vars.gaps", "8").unwrap();
+
+        let changes = diff(&a, &b);
+        let keys: Vec<&str> = changes.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(keys, vec!["flavor", "fonts.mono_size", "vars.gaps"]);
+        assert_eq!(changes[0].old.as_deref(), Some("mocha"));
+        assert_eq!(changes[0].new.as_deref(), Some("latte"));
+        assert_eq!(changes[2].old, None);
+        assert_eq!(changes[2].new.as_deref(), Some("8"));
+        assert!(diff(&a, &a).is_empty());
+    }
+}
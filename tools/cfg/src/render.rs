@@ -1,12 +1,14 @@
 use glob::glob;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 use tera::{Context, Result as TeraResult, Tera, Value};
 
-use crate::color::Color;
+use crate::color::{Color, WCAG_AA};
 use crate::config::Config;
+use crate::deps;
 use crate::palette::Palette;
+use crate::tera_internals::{self, Template};
 
 /// Map Catppuccin color names to ANSI color names
 fn catppuccin_to_ansi(color_name: &str) -> &'static str {
@@ -84,15 +86,19 @@ fn rgb_css_filter(value: &Value, _args: &HashMap<String, Value>) -> TeraResult<V
     Ok(Value::String(color.to_rgb_css()))
 }
 
+/// Alpha for the rgba-style formatters: the `alpha` argument if given,
+/// otherwise the color's own (1 for opaque colors)
+fn alpha_arg(color: &Color, args: &HashMap<String, Value>) -> f32 {
+    args.get("alpha")
+        .and_then(|v| v.as_f64())
+        .map_or(color.alpha(), |a| a as f32)
+}
+
 /// Custom Tera filter: CSS rgba()
-/// Usage: {{ blue | rgba_css(alpha=0.9) }}
+/// Usage: {{ blue | rgba_css(alpha=0.9) }} or {{ blue | alpha(value=0.9) | rgba_css }}
 fn rgba_css_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
     let color = value_to_color(value)?;
-    let alpha =
-        args.get("alpha")
-            .and_then(|v| v.as_f64())
-            .ok_or_else(|| tera::Error::msg("rgba_css: missing 'alpha' argument"))? as f32;
-    Ok(Value::String(color.to_rgba(alpha)))
+    Ok(Value::String(color.to_rgba(alpha_arg(&color, args))))
 }
 
 /// Custom Tera filter: Hyprlang rgb()
@@ -103,23 +109,19 @@ fn hyprlang_filter(value: &Value, _args: &HashMap<String, Value>) -> TeraResult<
 }
 
 /// Custom Tera filter: Hyprlang rgba()
-/// Usage: {{ blue | hyprlang_rgba(alpha=0.9) }}
+/// Usage: {{ blue | hyprlang_rgba(alpha=0.9) }} or {{ blue | alpha(value=0.9) | hyprlang_rgba }}
 fn hyprlang_rgba_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
     let color = value_to_color(value)?;
-    let alpha = args
-        .get("alpha")
-        .and_then(|v| v.as_f64())
-        .ok_or_else(|| tera::Error::msg("hyprlang_rgba: missing 'alpha' argument"))?
-        as f32;
-    Ok(Value::String(color.to_hyprlang_rgba(alpha)))
+    Ok(Value::String(
+        color.to_hyprlang_rgba(alpha_arg(&color, args)),
+    ))
 }
 
 /// Custom Tera filter: Qt ARGB hex format
 /// Usage: {{ blue | hex_argb }} or {{ blue | hex_argb(alpha=0.5) }}
 fn hex_argb_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
     let color = value_to_color(value)?;
-    let alpha = args.get("alpha").and_then(|v| v.as_f64()).unwrap_or(1.0) as f32;
-    Ok(Value::String(color.to_hex_argb(alpha)))
+    Ok(Value::String(color.to_hex_argb(alpha_arg(&color, args))))
 }
 
 /// Custom Tera filter: ANSI color name
@@ -175,8 +177,129 @@ fn darken_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Val
     Ok(tera::to_value(color.darken(amount))?)
 }
 
+/// Helper: required numeric filter argument
+fn number_arg(filter: &str, args: &HashMap<String, Value>, name: &str) -> TeraResult<f64> {
+    args.get(name)
+        .and_then(|v| v.as_f64())
+        .ok_or_else(|| tera::Error::msg(format!("{}: missing '{}' argument", filter, name)))
+}
+
+/// A color argument, like `base` in `contrast(with=base)`.
+fn color_arg(filter: &str, args: &HashMap<String, Value>, name: &str) -> TeraResult<Color> {
+    let value = args
+        .get(name)
+        .ok_or_else(|| tera::Error::msg(format!("{}: missing '{}' argument", filter, name)))?;
+    value_to_color(value)
+}
+
+/// Custom Tera filter: lighten by OKLCH lightness points, keeping hue and
+/// chroma (negative amounts darken)
+/// Usage: {{ blue | lighten_l(amount=8) }}
+fn lighten_l_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
+    let color = value_to_color(value)?;
+    let amount = number_arg("lighten_l", args, "amount")?;
+    Ok(tera::to_value(color.lighten_l(amount))?)
+}
+
+/// Custom Tera filter: add HSL saturation points
+/// Usage: {{ blue | saturate(amount=10) }}
+fn saturate_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
+    let color = value_to_color(value)?;
+    let amount = number_arg("saturate", args, "amount")?;
+    Ok(tera::to_value(color.saturate(amount))?)
+}
+
+/// Custom Tera filter: remove HSL saturation points
+/// Usage: {{ blue | desaturate(amount=30) }}
+fn desaturate_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
+    let color = value_to_color(value)?;
+    let amount = number_arg("desaturate", args, "amount")?;
+    Ok(tera::to_value(color.desaturate(amount))?)
+}
+
+/// Custom Tera filter: rotate the OKLCH hue
+/// Usage: {{ blue | rotate_hue(degrees=30) }}
+fn rotate_hue_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
+    let color = value_to_color(value)?;
+    let degrees = number_arg("rotate_hue", args, "degrees")?;
+    Ok(tera::to_value(color.rotate_hue(degrees))?)
+}
+
+/// Custom Tera filter: blend colors in OKLab (same arguments as blend)
+/// Usage: {{ red | mix_oklab(base=base, amount=25) }}
+fn mix_oklab_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
+    let fg = value_to_color(value)?;
+    let bg_value = args
+        .get("base")
+        .ok_or_else(|| tera::Error::msg("mix_oklab: missing 'base' argument"))?;
+    let bg = value_to_color(bg_value)?;
+    let amount = number_arg("mix_oklab", args, "amount")?;
+    Ok(tera::to_value(fg.mix_oklab(&bg, amount))?)
+}
+
+/// Custom Tera filter: set OKLCH chroma, keeping lightness and hue
+/// Usage: {{ blue | with_chroma(chroma=0.04) }}
+fn with_chroma_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
+    let color = value_to_color(value)?;
+    let chroma = number_arg("with_chroma", args, "chroma")?;
+    Ok(tera::to_value(color.with_chroma(chroma))?)
+}
+
+/// Custom Tera filter: set the alpha channel (0-1), which every formatter
+/// then includes (`hex` gives rrggbbaa, `rgb_css` gives rgba())
+/// Usage: {{ base | alpha(value=0.8) | hex }}
+fn alpha_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
+    let color = value_to_color(value)?;
+    let alpha = number_arg("alpha", args, "value")?;
+    if !(0.0..=1.0).contains(&alpha) {
+        return Err(tera::Error::msg(format!(
+            "alpha: value must be between 0 and 1, got {}",
+            alpha
+        )));
+    }
+    Ok(tera::to_value(color.with_alpha(Some(alpha as f32)))?)
+}
+
+/// Custom Tera filter: contrast against another color, as a WCAG 2.1 ratio
+/// (1-21, AA is 4.5) or, with `method="apca"`, an APCA Lc value
+/// Usage: {% if primary | contrast(with=base) < 4.5 %} or
+/// {{ text | contrast(with=base, method="apca") }}
+fn contrast_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
+    let color = value_to_color(value)?;
+    let other = color_arg("contrast", args, "with")?;
+    let contrast = match args.get("method").and_then(|v| v.as_str()) {
+        None | Some("wcag") => (color.contrast_ratio(other) * 100.0).round() / 100.0,
+        Some("apca") => (color.apca_contrast(other) * 10.0).round() / 10.0,
+        Some(other) => {
+            return Err(tera::Error::msg(format!(
+                "contrast: unknown method '{}' (use wcag or apca)",
+                other
+            )))
+        }
+    };
+    Ok(tera::to_value(contrast)?)
+}
+
+/// Custom Tera filter: pick the text color for a background, the first
+/// candidate with at least `min` contrast (default 4.5, WCAG AA), or the
+/// most contrasting one if none has
+/// Usage: {{ primary | readable_on(candidates=[text, base, crust]) | hex }}
+fn readable_on_filter(value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
+    let background = value_to_color(value)?;
+    let candidates = args
+        .get("candidates")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| tera::Error::msg("readable_on: missing 'candidates' argument"))?
+        .iter()
+        .map(value_to_color)
+        .collect::<TeraResult<Vec<_>>>()?;
+    let min = args.get("min").and_then(|v| v.as_f64()).unwrap_or(WCAG_AA);
+    let color = Color::readable_on(background, &candidates, min)
+        .ok_or_else(|| tera::Error::msg("readable_on: 'candidates' is empty"))?;
+    Ok(tera::to_value(color)?)
+}
+
 /// Discover all .tera template files in a directory (recursively)
-#[allow(dead_code)]
 pub fn discover_templates(root: &Path) -> Vec<PathBuf> {
     let pattern = format!("{}/**/*.tera", root.display());
     glob(&pattern)
@@ -214,6 +337,9 @@ pub fn build_context(config: &Config, palette: &Palette) -> Context {
     ctx.insert("wallpaper_cache_dir", &config.wallpaper.cache_dir);
     ctx.insert("wallpaper", &config.wallpaper);
 
+    // User-defined values from config.toml's [vars]
+    ctx.insert("vars", &config.vars);
+
     // Add HOME for path templating
     if let Ok(home) = std::env::var("HOME") {
         ctx.insert("home", &home);
@@ -240,18 +366,195 @@ pub fn build_context(config: &Config, palette: &Palette) -> Context {
     ctx
 }
 
-/// Render a single template file
-pub fn render_template(template_path: &Path, context: &Context) -> Result<String, String> {
-    let content = std::fs::read_to_string(template_path).map_err(|e| {
-        format!(
-            "Failed to read template '{}': {}",
-            template_path.display(),
-            e
-        )
-    })?;
+/// Override one context value from a `key=value` string. Dotted keys set a
+/// field inside an object (`fonts.mono=Iosevka`). Hex (`#rrggbb`,
+/// `#rrggbbaa`), `rgb()` and `hsl()` values become colors, numbers and
+/// `true`/`false` keep their type, anything else is a string.
+pub fn set_context_value(ctx: &mut Context, assignment: &str) -> Result<(), String> {
+    let (key, raw) = assignment
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid --set '{}' (expected key=value)", assignment))?;
+    let value = if Color::is_literal(raw) {
+        serde_json::to_value(Color::parse(raw)?).map_err(|e| e.to_string())?
+    } else if let Ok(n) = raw.parse::<i64>() {
+        Value::from(n)
+    } else if let Ok(n) = raw.parse::<f64>() {
+        Value::from(n)
+    } else if let Ok(b) = raw.parse::<bool>() {
+        Value::from(b)
+    } else {
+        Value::from(raw)
+    };
+
+    let mut json = ctx.clone().into_json();
+    let mut target = &mut json;
+    let mut parts = key.split('.').peekable();
+    while let Some(part) = parts.next() {
+        let object = target
+            .as_object_mut()
+            .ok_or_else(|| format!("Can't set '{}': '{}' is not an object", key, part))?;
+        if parts.peek().is_none() {
+            object.insert(part.to_string(), value);
+            break;
+        }
+        target = object
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Object(Default::default()));
+    }
 
-    // Create a one-off Tera instance for this template
-    let mut tera = Tera::default();
+    *ctx = Context::from_value(json).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Context for one template: `base` with a registry entry's `overrides`
+/// replacing existing values (`primary = "peach"`, `fonts.mono = "Iosevka"`)
+/// and its `vars` added as new ones. In both, a string naming a palette color
+/// or starting with `#` becomes a color; overriding a color also updates its
+/// `_name` twin.
+pub fn template_context(
+    base: &Context,
+    palette: &Palette,
+    overrides: &toml::Table,
+    vars: &toml::Table,
+) -> Result<Context, String> {
+    if overrides.is_empty() && vars.is_empty() {
+        return Ok(base.clone());
+    }
+    let mut json = base.clone().into_json();
+    let object = json.as_object_mut().expect("context is an object");
+    for (key, value) in overrides {
+        override_value(object, key, key, value, palette)?;
+    }
+    for (key, value) in vars {
+        if object.contains_key(key) {
+            return Err(format!(
+                "var '{}' is already in the context (use overrides to replace it)",
+                key
+            ));
+        }
+        object.insert(key.clone(), context_value(value, palette)?);
+    }
+    Context::from_value(json).map_err(|e| e.to_string())
+}
+
+fn override_value(
+    object: &mut serde_json::Map<String, Value>,
+    path: &str,
+    key: &str,
+    value: &toml::Value,
+    palette: &Palette,
+) -> Result<(), String> {
+    if let Some((head, rest)) = key.split_once('.') {
+        let mut nested = toml::Table::new();
+        nested.insert(rest.to_string(), value.clone());
+        return override_value(object, path, head, &toml::Value::Table(nested), palette);
+    }
+    let Some(current) = object.get_mut(key) else {
+        return Err(format!(
+            "override '{}' is not in the context (use vars to add it)",
+            path
+        ));
+    };
+    match (current, value) {
+        // Merge into nested objects (fonts, wallpaper) instead of replacing them
+        (Value::Object(inner), toml::Value::Table(table)) if !is_color(inner) => {
+            for (k, v) in table {
+                override_value(inner, &format!("{}.{}", path, k), k, v, palette)?;
+            }
+        }
+        (current, _) => {
+            *current = context_value(value, palette)?;
+            let name_key = format!("{}_name", key);
+            if let (Some(name), true) = (value.as_str(), object.contains_key(&name_key)) {
+                if palette.get(name).is_some() {
+                    object.insert(name_key, Value::from(name));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_color(object: &serde_json::Map<String, Value>) -> bool {
+    ["r", "g", "b"].iter().all(|c| object.contains_key(*c))
+}
+
+/// Convert a registry value for the context; see `template_context`.
+fn context_value(value: &toml::Value, palette: &Palette) -> Result<Value, String> {
+    match value {
+        toml::Value::String(s) => match palette.get(s) {
+            Some(color) => serde_json::to_value(color).map_err(|e| e.to_string()),
+            None if Color::is_literal(s) => {
+                serde_json::to_value(Color::parse(s)?).map_err(|e| e.to_string())
+            }
+            None => Ok(Value::from(s.as_str())),
+        },
+        toml::Value::Array(items) => items
+            .iter()
+            .map(|v| context_value(v, palette))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        toml::Value::Table(table) => table
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), context_value(v, palette)?)))
+            .collect::<Result<serde_json::Map<_, _>, String>>()
+            .map(Value::Object),
+        other => serde_json::to_value(other).map_err(|e| e.to_string()),
+    }
+}
+
+/// Subdirectory of the cfg dir holding shared partials, macros and base
+/// layouts. Files load into the engine as `partials/<relative path>`.
+pub const PARTIALS_DIR: &str = "partials";
+
+/// What a filter takes or returns, as far as `cfg lint` cares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// A palette color object (`blue`, `primary`)
+    Color,
+    /// A string (`blue_name`, or the output of a formatting filter)
+    String,
+    /// A number (the output of `contrast`)
+    Number,
+}
+
+/// `(filter, input, output)` for every custom filter. Keep in sync with
+/// `register_filters`.
+const FILTER_KINDS: &[(&str, ValueKind, ValueKind)] = &[
+    ("hex", ValueKind::Color, ValueKind::String),
+    ("upper", ValueKind::Color, ValueKind::String),
+    ("lower", ValueKind::Color, ValueKind::String),
+    ("rgb", ValueKind::Color, ValueKind::String),
+    ("rgb_css", ValueKind::Color, ValueKind::String),
+    ("rgba_css", ValueKind::Color, ValueKind::String),
+    ("hyprlang", ValueKind::Color, ValueKind::String),
+    ("hyprlang_rgba", ValueKind::Color, ValueKind::String),
+    ("hex_argb", ValueKind::Color, ValueKind::String),
+    ("ansi", ValueKind::String, ValueKind::String),
+    ("blend", ValueKind::Color, ValueKind::Color),
+    ("lighten", ValueKind::Color, ValueKind::Color),
+    ("darken", ValueKind::Color, ValueKind::Color),
+    ("lighten_l", ValueKind::Color, ValueKind::Color),
+    ("saturate", ValueKind::Color, ValueKind::Color),
+    ("desaturate", ValueKind::Color, ValueKind::Color),
+    ("rotate_hue", ValueKind::Color, ValueKind::Color),
+    ("mix_oklab", ValueKind::Color, ValueKind::Color),
+    ("with_chroma", ValueKind::Color, ValueKind::Color),
+    ("alpha", ValueKind::Color, ValueKind::Color),
+    ("contrast", ValueKind::Color, ValueKind::Number),
+    ("readable_on", ValueKind::Color, ValueKind::Color),
+];
+
+/// Input and output kind of a custom filter, `None` for Tera's built-ins.
+pub fn filter_kinds(name: &str) -> Option<(ValueKind, ValueKind)> {
+    FILTER_KINDS
+        .iter()
+        .find(|(filter, _, _)| *filter == name)
+        .map(|(_, input, output)| (*input, *output))
+}
+
+/// Register every custom filter on a Tera instance.
+fn register_filters(tera: &mut Tera) {
     tera.register_filter("hex", hex_filter);
     tera.register_filter("upper", upper_filter);
     tera.register_filter("lower", lower_filter);
@@ -265,24 +568,168 @@ pub fn render_template(template_path: &Path, context: &Context) -> Result<String
     tera.register_filter("blend", blend_filter);
     tera.register_filter("lighten", lighten_filter);
     tera.register_filter("darken", darken_filter);
-    tera.add_raw_template("template", &content).map_err(|e| {
-        format!(
-            "Failed to parse template '{}': {}",
-            template_path.display(),
-            e
-        )
-    })?;
+    tera.register_filter("lighten_l", lighten_l_filter);
+    tera.register_filter("saturate", saturate_filter);
+    tera.register_filter("desaturate", desaturate_filter);
+    tera.register_filter("rotate_hue", rotate_hue_filter);
+    tera.register_filter("mix_oklab", mix_oklab_filter);
+    tera.register_filter("with_chroma", with_chroma_filter);
+    tera.register_filter("alpha", alpha_filter);
+    tera.register_filter("contrast", contrast_filter);
+    tera.register_filter("readable_on", readable_on_filter);
+}
 
-    let rendered = tera.render("template", context).map_err(|e| {
-        format!(
-            "Failed to render template '{}': {:?}",
-            template_path.display(),
-            e
-        )
-    })?;
+/// A single Tera instance holding every registered template plus the shared
+/// partials, so templates can `{% include %}`, `{% import %}` macros from and
+/// `{% extends %}` each other.
+///
+/// A template that fails to load (missing file, parse error, unknown parent or
+/// macro file) is kept out of the instance and its error is returned when it
+/// is rendered, so one broken template doesn't take down the rest.
+pub struct Engine {
+    tera: Tera,
+    errors: HashMap<String, String>,
+}
+
+impl Engine {
+    /// Load every file under `partials_dir` plus the given `(name, path)`
+    /// templates. Partials are shared by everything, so an unreadable or
+    /// unparsable partial fails the whole load.
+    pub fn load(partials_dir: &Path, templates: &[(String, PathBuf)]) -> Result<Self, String> {
+        let mut sources: Vec<(String, String)> = Vec::new();
+        let mut parsed: HashMap<String, Template> = HashMap::new();
+
+        if partials_dir.is_dir() {
+            let pattern = format!("{}/**/*", partials_dir.display());
+            let mut files: Vec<PathBuf> = glob(&pattern)
+                .map_err(|e| format!("Invalid partials pattern '{}': {}", pattern, e))?
+                .filter_map(Result::ok)
+                .filter(|p| p.is_file())
+                .collect();
+            files.sort();
+
+            for path in files {
+                let rel = path.strip_prefix(partials_dir).unwrap_or(&path);
+                let name = format!("{}/{}", PARTIALS_DIR, rel.display());
+                let content = read_template(&path)?;
+                let template = parse_template(&name, &path, &content)?;
+                parsed.insert(name.clone(), template);
+                sources.push((name, content));
+            }
+        }
+
+        let mut errors = HashMap::new();
+        for (name, path) in templates {
+            if !path.exists() {
+                errors.insert(
+                    name.clone(),
+                    format!("template not found: {}", path.display()),
+                );
+                continue;
+            }
+            let parsed_template = read_template(path)
+                .and_then(|content| Ok((parse_template(name, path, &content)?, content)));
+            match parsed_template {
+                Ok((template, content)) => {
+                    parsed.insert(name.clone(), template);
+                    sources.push((name.clone(), content));
+                }
+                Err(e) => {
+                    errors.insert(name.clone(), e);
+                }
+            }
+        }
+
+        // Drop templates whose parent or macro file isn't loadable, repeating
+        // until stable since removing one can orphan another.
+        loop {
+            let broken: Vec<(String, String)> = parsed
+                .iter()
+                .filter_map(|(name, t)| {
+                    let missing =
+                        tera_internals::dependencies(t).find(|dep| !parsed.contains_key(*dep))?;
+                    Some((name.clone(), missing.to_string()))
+                })
+                .collect();
+            if broken.is_empty() {
+                break;
+            }
+            for (name, missing) in broken {
+                parsed.remove(&name);
+                if name.starts_with(&format!("{}/", PARTIALS_DIR)) {
+                    return Err(format!(
+                        "Partial '{}' depends on '{}' which isn't loaded",
+                        name, missing
+                    ));
+                }
+                errors.insert(name, format!("depends on '{}' which isn't loaded", missing));
+            }
+        }
+        sources.retain(|(name, _)| parsed.contains_key(name));
+
+        let mut tera = Tera::default();
+        tera.autoescape_on(vec![]);
+        register_filters(&mut tera);
+        tera.add_raw_templates(sources)
+            .map_err(|e| format!("Failed to load templates: {:?}", e))?;
+
+        Ok(Engine { tera, errors })
+    }
+
+    /// Render a loaded template by name
+    pub fn render(&self, name: &str, context: &Context) -> Result<String, String> {
+        if let Some(e) = self.errors.get(name) {
+            return Err(e.clone());
+        }
+
+        let rendered = self
+            .tera
+            .render(name, context)
+            .map_err(|e| format!("Failed to render template '{}': {:?}", name, e))?;
+
+        // Strip vim/editor modelines from output (they're only for template editing)
+        Ok(strip_modelines(&rendered))
+    }
+
+    /// Context variables a loaded template reads (see `deps::template_vars`).
+    /// Errors with the load error if the template isn't loaded.
+    pub fn variables(&self, name: &str) -> Result<BTreeSet<String>, String> {
+        if let Some(e) = self.errors.get(name) {
+            return Err(e.clone());
+        }
+        Ok(deps::template_vars(
+            tera_internals::templates(&self.tera),
+            name,
+        ))
+    }
+
+    /// Context reads and filter calls of a loaded template (see
+    /// `deps::template_refs`). Errors with the load error if it isn't loaded.
+    pub fn references(&self, name: &str) -> Result<deps::TemplateRefs, String> {
+        if let Some(e) = self.errors.get(name) {
+            return Err(e.clone());
+        }
+        Ok(deps::template_refs(
+            tera_internals::templates(&self.tera),
+            name,
+        ))
+    }
 
-    // Strip vim/editor modelines from output (they're only for template editing)
-    Ok(strip_modelines(&rendered))
+    /// Whether `name` is a registered filter (custom or Tera built-in).
+    /// `default` is handled by Tera itself rather than registered.
+    pub fn has_filter(&self, name: &str) -> bool {
+        name == "default" || tera_internals::has_filter(&self.tera, name)
+    }
+}
+
+fn read_template(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read template '{}': {}", path.display(), e))
+}
+
+fn parse_template(name: &str, path: &Path, content: &str) -> Result<Template, String> {
+    tera_internals::parse(name, content)
+        .map_err(|e| format!("Failed to parse template '{}': {}", path.display(), e))
 }
 
 /// Strip editor modelines from rendered output (they're only for template editing)
@@ -309,22 +756,258 @@ fn strip_modelines(content: &str) -> String {
     result
 }
 
-/// Render a template and write to output file
-pub fn render_to_file(
-    template_path: &Path,
-    context: &Context,
-    dry_run: bool,
-) -> Result<PathBuf, String> {
-    let output = output_path(template_path);
-    let rendered = render_template(template_path, context)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!(
+            "cfg-render-test-{}-{}-{}",
+            std::process::id(),
+            nanos,
+            name
+        ));
+        std::fs::create_dir_all(dir.join(PARTIALS_DIR)).unwrap();
+        dir
+    }
+
+    fn write(root: &Path, rel: &str, content: &str) -> PathBuf {
+        let path = root.join(rel);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn context() -> Context {
+        let mut ctx = Context::new();
+        ctx.insert("blue", &Color::from_hex("89b4fa").unwrap());
+        ctx.insert("flavor", "mocha");
+        ctx
+    }
+
+    #[test]
+    fn engine_resolves_include_import_and_extends() {
+        let root = temp_dir("shared");
+        let partials = root.join(PARTIALS_DIR);
+        write(
+            &partials,
+            "colors.css",
+            "@define-color blue #{{ blue | hex }};",
+        );
+        write(
+            &partials,
+            "macros.tera",
+            "{% macro accent(c) %}accent={{ c | hex }}{% endmacro accent %}",
+        );
+        write(
+            &partials,
+            "base.tera",
+            "head {{ flavor }}\n{% block body %}{% endblock body %}",
+        );
+        let templates = vec![
+            (
+                "inc".to_string(),
+                write(&root, "inc.tera", "{% include \"partials/colors.css\" %}"),
+            ),
+            (
+                "mac".to_string(),
+                write(
+                    &root,
+                    "mac.tera",
+                    "{% import \"partials/macros.tera\" as m %}{{ m::accent(c=blue) }}",
+                ),
+            ),
+            (
+                "ext".to_string(),
+                write(
+                    &root,
+                    "ext.tera",
+                    "{% extends \"partials/base.tera\" %}{% block body %}child{% endblock body %}",
+                ),
+            ),
+        ];
+
+        let engine = Engine::load(&partials, &templates).unwrap();
+        let ctx = context();
+        assert_eq!(
+            engine.render("inc", &ctx).unwrap(),
+            "@define-color blue #89b4fa;\n"
+        );
+        assert_eq!(engine.render("mac", &ctx).unwrap(), "accent=89b4fa\n");
+        assert_eq!(engine.render("ext", &ctx).unwrap(), "head mocha\nchild\n");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn engine_isolates_broken_templates() {
+        let root = temp_dir("broken");
+        let partials = root.join(PARTIALS_DIR);
+        let templates = vec![
+            ("ok".to_string(), write(&root, "ok.tera", "{{ flavor }}")),
+            ("bad".to_string(), write(&root, "bad.tera", "{{ unclosed")),
+            (
+                "orphan".to_string(),
+                write(&root, "orphan.tera", "{% extends \"partials/nope\" %}"),
+            ),
+            ("missing".to_string(), root.join("missing.tera")),
+        ];
+
+        let engine = Engine::load(&partials, &templates).unwrap();
+        let ctx = context();
+        assert_eq!(engine.render("ok", &ctx).unwrap(), "mocha\n");
+        assert!(engine
+            .render("bad", &ctx)
+            .unwrap_err()
+            .contains("Failed to parse"));
+        assert!(engine
+            .render("orphan", &ctx)
+            .unwrap_err()
+            .contains("partials/nope"));
+        assert!(engine
+            .render("missing", &ctx)
+            .unwrap_err()
+            .contains("template not found"));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
 
-    if dry_run {
-        // Just return the output path without writing
-        return Ok(output);
+    #[test]
+    fn set_context_value_types_and_nesting() {
+        let mut ctx = context();
+        ctx.insert(
+            "fonts",
+            &serde_json::json!({ "mono": "JetBrains Mono", "mono_size": 11 }),
+        );
+
+        set_context_value(&mut ctx, "blue=#ff0000").unwrap();
+        set_context_value(&mut ctx, "fonts.mono=Iosevka Term").unwrap();
+        set_context_value(&mut ctx, "gaps=8").unwrap();
+        set_context_value(&mut ctx, "extra.nested=yes").unwrap();
+
+        let json = ctx.into_json();
+        assert_eq!(json["blue"]["r"], 255);
+        assert_eq!(json["fonts"]["mono"], "Iosevka Term");
+        assert_eq!(json["fonts"]["mono_size"], 11);
+        assert_eq!(json["gaps"], 8);
+        assert_eq!(json["extra"]["nested"], "yes");
     }
 
-    std::fs::write(&output, rendered)
-        .map_err(|e| format!("Failed to write '{}': {}", output.display(), e))?;
+    #[test]
+    fn set_context_value_rejects_bad_input() {
+        let mut ctx = context();
+        assert!(set_context_value(&mut ctx, "novalue").is_err());
+        assert!(set_context_value(&mut ctx, "blue=#nothex").is_err());
+        assert!(set_context_value(&mut ctx, "flavor.x=1").is_err());
+    }
 
-    Ok(output)
+    #[test]
+    fn template_context_applies_overrides_and_vars() {
+        let palette = Palette {
+            colors: HashMap::from([
+                ("blue".to_string(), Color::from_hex("89b4fa").unwrap()),
+                ("peach".to_string(), Color::from_hex("fab387").unwrap()),
+            ]),
+        };
+        let mut base = context();
+        base.insert("primary", palette.get("blue").unwrap());
+        base.insert("primary_name", "blue");
+        base.insert(
+            "fonts",
+            &serde_json::json!({ "mono": "JetBrains Mono", "mono_size": 11 }),
+        );
+        let table = |s: &str| s.parse::<toml::Table>().unwrap();
+
+        let ctx = template_context(
+            &base,
+            &palette,
+            &table("primary = \"peach\"\nfonts = { mono = \"Iosevka\" }"),
+            &table("gaps = 8\nborder = \"#ff0000\"\nlabel = \"peachy\""),
+        )
+        .unwrap()
+        .into_json();
+        assert_eq!(ctx["primary"]["r"], 250);
+        assert_eq!(ctx["primary_name"], "peach");
+        assert_eq!(ctx["fonts"]["mono"], "Iosevka");
+        assert_eq!(ctx["fonts"]["mono_size"], 11);
+        assert_eq!(ctx["gaps"], 8);
+        assert_eq!(ctx["border"]["r"], 255);
+        assert_eq!(ctx["label"], "peachy");
+
+        let dotted = template_context(
+            &base,
+            &palette,
+            &table("\"fonts.mono_size\" = 13"),
+            &toml::Table::new(),
+        )
+        .unwrap()
+        .into_json();
+        assert_eq!(dotted["fonts"]["mono_size"], 13);
+        assert_eq!(dotted["fonts"]["mono"], "JetBrains Mono");
+    }
+
+    #[test]
+    fn template_context_rejects_unknown_overrides_and_shadowing_vars() {
+        let palette = Palette {
+            colors: HashMap::new(),
+        };
+        let table = |s: &str| s.parse::<toml::Table>().unwrap();
+        let none = toml::Table::new();
+
+        assert_eq!(
+            template_context(&context(), &palette, &table("primray = \"x\""), &none).unwrap_err(),
+            "override 'primray' is not in the context (use vars to add it)"
+        );
+        assert_eq!(
+            template_context(&context(), &palette, &none, &table("flavor = \"latte\""))
+                .unwrap_err(),
+            "var 'flavor' is already in the context (use overrides to replace it)"
+        );
+        assert!(template_context(&context(), &palette, &table("blue = \"#zz\""), &none).is_err());
+    }
+
+    #[test]
+    fn filter_kinds_cover_custom_filters() {
+        let mut tera = Tera::default();
+        let builtin: Vec<String> = tera_internals::filter_names(&tera)
+            .map(String::from)
+            .collect();
+        register_filters(&mut tera);
+        for name in
+            tera_internals::filter_names(&tera).filter(|name| !builtin.iter().any(|b| b == name))
+        {
+            assert!(
+                filter_kinds(name).is_some(),
+                "no FILTER_KINDS entry for {}",
+                name
+            );
+        }
+        for (name, _, _) in FILTER_KINDS {
+            assert!(
+                tera_internals::has_filter(&tera, name),
+                "{} isn't registered",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn engine_strips_modelines() {
+        let root = temp_dir("modelines");
+        let partials = root.join(PARTIALS_DIR);
+        let templates = vec![(
+            "conf".to_string(),
+            write(&root, "conf.tera", "a = 1\n# vim: ft=conf\n"),
+        )];
+
+        let engine = Engine::load(&partials, &templates).unwrap();
+        assert_eq!(engine.render("conf", &context()).unwrap(), "a = 1\n");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
 }
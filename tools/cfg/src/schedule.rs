@@ -0,0 +1,460 @@
+//! `cfg schedule`: switch profiles or theme settings at fixed times or at
+//! sunrise/sunset, computed offline from `schedule.latitude`/`longitude`.
+//!
+//! The active rule is always the one whose time passed most recently, so
+//! starting the daemon (or waking from suspend) catches up immediately.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::{Config, ScheduleConfig, ScheduleRule};
+use crate::palette;
+use crate::profile;
+
+/// How often the daemon re-reads the config and checks the clock. Short
+/// enough that a switch lands within a minute, including after suspend.
+pub const TICK: Duration = Duration::from_secs(30);
+
+/// When a rule fires, relative to local midnight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trigger {
+    /// Minutes after midnight
+    Fixed(i64),
+    /// Sunrise plus an offset in minutes
+    Sunrise(i64),
+    /// Sunset plus an offset in minutes
+    Sunset(i64),
+}
+
+impl Trigger {
+    /// Parse "HH:MM", "sunrise", "sunset", "sunset-30m" or "sunrise+1h30m".
+    pub fn parse(at: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Invalid schedule time '{}' (use HH:MM, sunrise or sunset, e.g. sunset-30m)",
+                at
+            )
+        };
+        let at = at.trim();
+        for (name, make) in [
+            ("sunrise", Trigger::Sunrise as fn(i64) -> Trigger),
+            ("sunset", Trigger::Sunset),
+        ] {
+            if let Some(rest) = at.strip_prefix(name) {
+                let offset = match rest.chars().next() {
+                    None => 0,
+                    Some('+') => parse_duration(&rest[1..]).ok_or_else(invalid)?,
+                    Some('-') => -parse_duration(&rest[1..]).ok_or_else(invalid)?,
+                    Some(_) => return Err(invalid()),
+                };
+                return Ok(make(offset));
+            }
+        }
+        let (h, m) = at.split_once(':').ok_or_else(invalid)?;
+        let (h, m): (i64, i64) = (
+            h.parse().map_err(|_| invalid())?,
+            m.parse().map_err(|_| invalid())?,
+        );
+        if !(0..24).contains(&h) || !(0..60).contains(&m) || at.len() > 5 {
+            return Err(invalid());
+        }
+        Ok(Trigger::Fixed(h * 60 + m))
+    }
+
+    fn needs_location(self) -> bool {
+        !matches!(self, Trigger::Fixed(_))
+    }
+}
+
+/// "30m", "1h", "1h30m" → minutes
+fn parse_duration(s: &str) -> Option<i64> {
+    let (hours, rest) = match s.split_once('h') {
+        Some((h, rest)) => (h.parse::<i64>().ok()?, rest),
+        None => (0, s),
+    };
+    let minutes = match rest {
+        "" => 0,
+        _ => rest.strip_suffix('m')?.parse::<i64>().ok()?,
+    };
+    (!s.is_empty()).then_some(hours * 60 + minutes)
+}
+
+/// A rule with its trigger parsed.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub index: usize,
+    pub trigger: Trigger,
+    pub rule: ScheduleRule,
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.rule.profile {
+            Some(name) => write!(f, "{} → profile {}", self.rule.at, name),
+            None => {
+                let set: Vec<String> = self
+                    .rule
+                    .set
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect();
+                write!(f, "{} → {}", self.rule.at, set.join(" "))
+            }
+        }
+    }
+}
+
+/// Parse and check every rule in `schedule`.
+pub fn rules(schedule: &ScheduleConfig) -> Result<Vec<Rule>, String> {
+    schedule
+        .rules
+        .iter()
+        .enumerate()
+        .map(|(index, rule)| {
+            let trigger = Trigger::parse(&rule.at)?;
+            if trigger.needs_location()
+                && (schedule.latitude.is_none() || schedule.longitude.is_none())
+            {
+                return Err(format!(
+                    "Schedule rule '{}' needs schedule.latitude and schedule.longitude",
+                    rule.at
+                ));
+            }
+            if rule.profile.is_some() != rule.set.is_empty() {
+                return Err(format!(
+                    "Schedule rule '{}' needs exactly one of profile and set",
+                    rule.at
+                ));
+            }
+            Ok(Rule {
+                index,
+                trigger,
+                rule: rule.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Where the clock is: unix time and the local UTC offset in seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct Now {
+    pub time: i64,
+    pub utc_offset: i64,
+}
+
+impl Now {
+    pub fn current() -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        Now {
+            time,
+            utc_offset: local_utc_offset(time),
+        }
+    }
+
+    /// Days since 1970-01-01 of the local date
+    fn day(&self) -> i64 {
+        (self.time + self.utc_offset).div_euclid(86400)
+    }
+
+    /// Local "HH:MM" of unix time `time`
+    pub fn clock(&self, time: i64) -> String {
+        let minutes = (time + self.utc_offset).rem_euclid(86400) / 60;
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+fn local_utc_offset(time: i64) -> i64 {
+    let t = time as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+        0
+    } else {
+        tm.tm_gmtoff as i64
+    }
+}
+
+/// Unix time `trigger` fires on local day `day`, or `None` when the sun
+/// doesn't rise or set that day.
+fn fire_time(trigger: Trigger, day: i64, now: &Now, schedule: &ScheduleConfig) -> Option<i64> {
+    let local_minutes = match trigger {
+        Trigger::Fixed(minutes) => minutes,
+        Trigger::Sunrise(offset) | Trigger::Sunset(offset) => {
+            let rising = matches!(trigger, Trigger::Sunrise(_));
+            let (lat, lon) = (schedule.latitude?, schedule.longitude?);
+            let utc_hours = sun_event(lat, lon, day, rising)?;
+            let local = (utc_hours * 60.0).round() as i64 + now.utc_offset / 60;
+            local.rem_euclid(1440) + offset
+        }
+    };
+    Some(day * 86400 - now.utc_offset + local_minutes * 60)
+}
+
+/// The rule whose time passed most recently, with the time it fired.
+pub fn active<'a>(
+    rules: &'a [Rule],
+    now: &Now,
+    schedule: &ScheduleConfig,
+) -> Option<(&'a Rule, i64)> {
+    let today = now.day();
+    rules
+        .iter()
+        .flat_map(|rule| {
+            (today - 2..=today).filter_map(move |day| {
+                fire_time(rule.trigger, day, now, schedule).map(|time| (rule, time))
+            })
+        })
+        .filter(|(_, time)| *time <= now.time)
+        .max_by_key(|(rule, time)| (*time, rule.index))
+}
+
+/// The next rule to fire after now, with its time.
+pub fn next<'a>(
+    rules: &'a [Rule],
+    now: &Now,
+    schedule: &ScheduleConfig,
+) -> Option<(&'a Rule, i64)> {
+    let today = now.day();
+    rules
+        .iter()
+        .flat_map(|rule| {
+            (today..=today + 2).filter_map(move |day| {
+                fire_time(rule.trigger, day, now, schedule).map(|time| (rule, time))
+            })
+        })
+        .filter(|(_, time)| *time > now.time)
+        .min_by_key(|(rule, time)| (*time, rule.index))
+}
+
+/// Today's fire time of each rule, in rule order.
+pub fn today<'a>(
+    rules: &'a [Rule],
+    now: &Now,
+    schedule: &ScheduleConfig,
+) -> Vec<(&'a Rule, Option<i64>)> {
+    rules
+        .iter()
+        .map(|rule| (rule, fire_time(rule.trigger, now.day(), now, schedule)))
+        .collect()
+}
+
+/// Sunrise or sunset in UTC hours on `day` (days since 1970-01-01), using
+/// the NOAA almanac approximation (good to a minute or two). `None` during
+/// polar day or night.
+pub fn sun_event(latitude: f64, longitude: f64, day: i64, rising: bool) -> Option<f64> {
+    const ZENITH: f64 = 90.833;
+    let (sin, cos, tan) = (
+        |d: f64| d.to_radians().sin(),
+        |d: f64| d.to_radians().cos(),
+        |d: f64| d.to_radians().tan(),
+    );
+
+    let day_of_year = (day - days_before_year(day)) as f64 + 1.0;
+    let lng_hour = longitude / 15.0;
+    let t = day_of_year + ((if rising { 6.0 } else { 18.0 }) - lng_hour) / 24.0;
+
+    let mean_anomaly = 0.9856 * t - 3.289;
+    let true_longitude =
+        (mean_anomaly + 1.916 * sin(mean_anomaly) + 0.020 * sin(2.0 * mean_anomaly) + 282.634)
+            .rem_euclid(360.0);
+
+    let mut right_ascension = (0.91764 * tan(true_longitude))
+        .atan()
+        .to_degrees()
+        .rem_euclid(360.0);
+    right_ascension +=
+        (true_longitude / 90.0).floor() * 90.0 - (right_ascension / 90.0).floor() * 90.0;
+    right_ascension /= 15.0;
+
+    let sin_dec = 0.39782 * sin(true_longitude);
+    let cos_dec = sin_dec.asin().cos();
+    let cos_h = (cos(ZENITH) - sin_dec * sin(latitude)) / (cos_dec * cos(latitude));
+    if !(-1.0..=1.0).contains(&cos_h) {
+        return None;
+    }
+    let hour_angle = if rising {
+        360.0 - cos_h.acos().to_degrees()
+    } else {
+        cos_h.acos().to_degrees()
+    } / 15.0;
+
+    let local_mean_time = hour_angle + right_ascension - 0.06571 * t - 6.622;
+    Some((local_mean_time - lng_hour).rem_euclid(24.0))
+}
+
+/// Days since 1970-01-01 of January 1st of the year containing `day`.
+fn days_before_year(day: i64) -> i64 {
+    let mut year = 1970 + day.div_euclid(366);
+    while days_from_year(year + 1) <= day {
+        year += 1;
+    }
+    days_from_year(year)
+}
+
+fn days_from_year(year: i64) -> i64 {
+    let y = year - 1;
+    365 * (year - 1970) + (y / 4 - y / 100 + y / 400) - (1969 / 4 - 1969 / 100 + 1969 / 400)
+}
+
+/// Apply `rule` to `config.toml`. Returns the config before and after.
+pub fn apply(cfg_dir: &str, rule: &ScheduleRule) -> Result<(Config, Config), String> {
+    let config_path = format!("{}/config.toml", cfg_dir);
+    let previous = Config::load_or_default(&config_path)?;
+    let config = match &rule.profile {
+        Some(name) => profile::switch(cfg_dir, name)?,
+        None => {
+            let mut config = previous.clone();
+            for (key, value) in &rule.set {
+                config.set(key, value)?;
+            }
+            if rule.set.contains_key("flavor") {
+                palette::check_flavor(cfg_dir, &config.flavor)?;
+            }
+            config.save(&config_path)?;
+            config
+        }
+    };
+    Ok((previous, config))
+}
+
+/// Run forever: whenever a different rule becomes active, pass it to
+/// `report`. The config is re-read every tick, so edited rules take effect
+/// without a restart; while it fails to load (reported once per distinct
+/// error) the last good rules stay in force.
+pub fn run(cfg_dir: &str, mut report: impl FnMut(Result<&Rule, String>)) {
+    let config_path = format!("{}/config.toml", cfg_dir);
+    let mut schedule = ScheduleConfig::default();
+    let mut current: Vec<Rule> = Vec::new();
+    let mut last_error: Option<String> = None;
+    let mut last_fired: Option<(usize, i64)> = None;
+
+    loop {
+        let loaded = Config::load_or_default(&config_path).and_then(|config| {
+            let parsed = rules(&config.schedule)?;
+            Ok((config.schedule, parsed))
+        });
+        match loaded {
+            Ok((loaded, parsed)) => {
+                schedule = loaded;
+                current = parsed;
+                last_error = None;
+            }
+            Err(e) => {
+                if last_error.as_ref() != Some(&e) {
+                    last_error = Some(e.clone());
+                    report(Err(e));
+                }
+            }
+        }
+
+        let now = Now::current();
+        if let Some((rule, time)) = active(&current, &now, &schedule) {
+            if last_fired != Some((rule.index, time)) {
+                last_fired = Some((rule.index, time));
+                report(Ok(rule));
+            }
+        }
+        std::thread::sleep(TICK);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn rule(at: &str, flavor: &str) -> ScheduleRule {
+        ScheduleRule {
+            at: at.to_string(),
+            profile: None,
+            set: BTreeMap::from([("flavor".to_string(), flavor.to_string())]),
+        }
+    }
+
+    fn berlin(rules: Vec<ScheduleRule>) -> ScheduleConfig {
+        ScheduleConfig {
+            latitude: Some(52.52),
+            longitude: Some(13.405),
+            rules,
+        }
+    }
+
+    /// 2024-06-21 in days since 1970-01-01
+    const MIDSUMMER: i64 = 19895;
+
+    #[test]
+    fn parses_fixed_and_sun_triggers() {
+        assert_eq!(Trigger::parse("07:30"), Ok(Trigger::Fixed(450)));
+        assert_eq!(Trigger::parse("sunset"), Ok(Trigger::Sunset(0)));
+        assert_eq!(Trigger::parse("sunset-30m"), Ok(Trigger::Sunset(-30)));
+        assert_eq!(Trigger::parse("sunrise+1h30m"), Ok(Trigger::Sunrise(90)));
+        for bad in [
+            "25:00", "7", "noon", "sunset+", "sunset+5", "sunsetx", "07:300",
+        ] {
+            assert!(Trigger::parse(bad).is_err(), "{} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn sun_events_match_almanac() {
+        assert_eq!(days_before_year(MIDSUMMER), 19723); // 2024-01-01
+        assert_eq!(days_before_year(19722), 19358); // 2023-12-31 → 2023-01-01
+
+        // Berlin, 2024-06-21: sunrise 02:43 UTC, sunset 19:33 UTC
+        let sunrise = sun_event(52.52, 13.405, MIDSUMMER, true).unwrap();
+        let sunset = sun_event(52.52, 13.405, MIDSUMMER, false).unwrap();
+        assert!(
+            (sunrise - (2.0 + 43.0 / 60.0)).abs() < 4.0 / 60.0,
+            "{}",
+            sunrise
+        );
+        assert!(
+            (sunset - (19.0 + 33.0 / 60.0)).abs() < 4.0 / 60.0,
+            "{}",
+            sunset
+        );
+
+        // Midnight sun in Tromsø
+        assert_eq!(sun_event(69.65, 18.96, MIDSUMMER, true), None);
+    }
+
+    #[test]
+    fn active_rule_is_the_latest_passed() {
+        let schedule = berlin(vec![rule("07:00", "latte"), rule("sunset", "mocha")]);
+        let rules = rules(&schedule).unwrap();
+        let at = |hh: i64, mm: i64| Now {
+            time: MIDSUMMER * 86400 - 7200 + (hh * 60 + mm) * 60,
+            utc_offset: 7200,
+        };
+
+        // Before 07:00 the previous evening's sunset still applies
+        let (rule, time) = active(&rules, &at(6, 0), &schedule).unwrap();
+        assert_eq!(rule.index, 1);
+        assert!(time < at(0, 0).time);
+
+        let (rule, _) = active(&rules, &at(12, 0), &schedule).unwrap();
+        assert_eq!(rule.index, 0);
+        let (rule, time) = next(&rules, &at(12, 0), &schedule).unwrap();
+        assert_eq!(rule.index, 1);
+        assert_eq!(at(12, 0).clock(time), "21:33");
+
+        let (rule, _) = active(&rules, &at(22, 0), &schedule).unwrap();
+        assert_eq!(rule.index, 1);
+    }
+
+    #[test]
+    fn rules_need_location_and_one_action() {
+        let mut schedule = berlin(vec![rule("sunset", "mocha")]);
+        schedule.latitude = None;
+        assert!(rules(&schedule)
+            .unwrap_err()
+            .contains("needs schedule.latitude"));
+
+        let mut both = rule("07:00", "latte");
+        both.profile = Some("work".to_string());
+        assert!(rules(&berlin(vec![both]))
+            .unwrap_err()
+            .contains("exactly one of profile and set"));
+    }
+}
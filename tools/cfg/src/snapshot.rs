@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Previous contents of every output written by one `cfg update`.
+///
+/// Lives in `<cfg_dir>/snapshot/` as `manifest.json` plus one backup file
+/// per output. Only the most recent update is kept.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub entries: Vec<SnapshotEntry>,
+}
+
+/// One output file captured before it was overwritten.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    /// Template name from `templates.toml`, used to re-run its reload.
+    pub name: String,
+    /// Absolute output path.
+    pub path: PathBuf,
+    /// Backup file name inside the snapshot dir. `None` when the output did
+    /// not exist yet, so restoring removes it.
+    pub backup: Option<String>,
+    /// Permission bits of the original file, restored along with its content.
+    #[serde(default)]
+    pub mode: Option<u32>,
+}
+
+/// Directory holding the last update's snapshot.
+pub fn snapshot_dir(cfg_dir: &str) -> PathBuf {
+    PathBuf::from(format!("{}/snapshot", cfg_dir))
+}
+
+impl Snapshot {
+    /// Copy the current contents of each `(name, path)` output into `dir`,
+    /// replacing any previous snapshot. The manifest is written last so a
+    /// failed capture never leaves a manifest pointing at missing backups.
+    pub fn capture(outputs: &[(String, PathBuf)], dir: &Path) -> Result<Self, String> {
+        if dir.exists() {
+            fs::remove_dir_all(dir)
+                .map_err(|e| format!("failed to clear snapshot dir '{}': {}", dir.display(), e))?;
+        }
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create snapshot dir '{}': {}", dir.display(), e))?;
+
+        let mut entries = Vec::new();
+        for (index, (name, path)) in outputs.iter().enumerate() {
+            let (backup, mode) = match fs::read(path) {
+                Ok(content) => {
+                    let file = index.to_string();
+                    fs::write(dir.join(&file), content)
+                        .map_err(|e| format!("failed to back up '{}': {}", path.display(), e))?;
+                    let mode = fs::metadata(path)
+                        .ok()
+                        .map(|meta| meta.permissions().mode() & 0o7777);
+                    (Some(file), mode)
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => (None, None),
+                Err(e) => return Err(format!("failed to read '{}': {}", path.display(), e)),
+            };
+            entries.push(SnapshotEntry {
+                name: name.clone(),
+                path: path.clone(),
+                backup,
+                mode,
+            });
+        }
+
+        let snapshot = Snapshot { entries };
+        let json = serde_json::to_string_pretty(&snapshot)
+            .map_err(|e| format!("failed to serialize snapshot: {}", e))?;
+        fs::write(dir.join("manifest.json"), json)
+            .map_err(|e| format!("failed to write snapshot manifest: {}", e))?;
+        Ok(snapshot)
+    }
+
+    /// Load the snapshot stored in `dir`.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let manifest = dir.join("manifest.json");
+        let content = match fs::read_to_string(&manifest) {
+            Ok(s) => s,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err("no snapshot to roll back to (run 'cfg update' first)".to_string());
+            }
+            Err(e) => {
+                return Err(format!(
+                    "failed to read snapshot manifest '{}': {}",
+                    manifest.display(),
+                    e
+                ))
+            }
+        };
+        serde_json::from_str(&content).map_err(|e| {
+            format!(
+                "failed to parse snapshot manifest '{}': {}",
+                manifest.display(),
+                e
+            )
+        })
+    }
+
+    /// Put every captured output back the way it was. Files are rewritten in
+    /// place and get their original mode back. Outputs that did not exist before
+    /// the update are removed. Keeps going after a failure and reports all
+    /// failures together.
+    pub fn restore(&self, dir: &Path) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for entry in &self.entries {
+            let result = match &entry.backup {
+                Some(file) => fs::read(dir.join(file))
+                    .and_then(|content| fs::write(&entry.path, content))
+                    .and_then(|()| match entry.mode {
+                        Some(mode) => {
+                            fs::set_permissions(&entry.path, fs::Permissions::from_mode(mode))
+                        }
+                        None => Ok(()),
+                    }),
+                None => match fs::remove_file(&entry.path) {
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                    other => other,
+                },
+            };
+            if let Err(e) = result {
+                errors.push(format!("{}: {}", entry.path.display(), e));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("failed to restore {}", errors.join("; ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!(
+            "cfg-snapshot-test-{}-{}-{}",
+            std::process::id(),
+            nanos,
+            name
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn restore_puts_back_previous_contents() {
+        let root = temp_dir("roundtrip");
+        let output = root.join("kitty.conf");
+        fs::write(&output, "old").unwrap();
+
+        let snap_dir = root.join("snapshot");
+        let outputs = vec![("kitty".to_string(), output.clone())];
+        Snapshot::capture(&outputs, &snap_dir).unwrap();
+        fs::write(&output, "new").unwrap();
+
+        Snapshot::load(&snap_dir)
+            .unwrap()
+            .restore(&snap_dir)
+            .unwrap();
+        assert_eq!(fs::read_to_string(&output).unwrap(), "old");
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn restore_removes_outputs_that_did_not_exist() {
+        let root = temp_dir("created");
+        let output = root.join("fresh.css");
+
+        let snap_dir = root.join("snapshot");
+        let outputs = vec![("fresh".to_string(), output.clone())];
+        let snapshot = Snapshot::capture(&outputs, &snap_dir).unwrap();
+        assert!(snapshot.entries[0].backup.is_none());
+        fs::write(&output, "new").unwrap();
+
+        snapshot.restore(&snap_dir).unwrap();
+        assert!(!output.exists());
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn restore_puts_back_previous_mode() {
+        let root = temp_dir("mode");
+        let output = root.join("set-folders.sh");
+        fs::write(&output, "old").unwrap();
+        fs::set_permissions(&output, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let snap_dir = root.join("snapshot");
+        let outputs = vec![("papirus".to_string(), output.clone())];
+        let snapshot = Snapshot::capture(&outputs, &snap_dir).unwrap();
+        fs::set_permissions(&output, fs::Permissions::from_mode(0o755)).unwrap();
+
+        snapshot.restore(&snap_dir).unwrap();
+        let mode = fs::metadata(&output).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode, 0o644);
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn capture_replaces_previous_snapshot() {
+        let root = temp_dir("replace");
+        let a = root.join("a");
+        let b = root.join("b");
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+
+        let snap_dir = root.join("snapshot");
+        Snapshot::capture(&[("a".to_string(), a)], &snap_dir).unwrap();
+        Snapshot::capture(&[("b".to_string(), b)], &snap_dir).unwrap();
+
+        let loaded = Snapshot::load(&snap_dir).unwrap();
+        assert_eq!(loaded.entries.len(), 1);
+        assert_eq!(loaded.entries[0].name, "b");
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn load_without_snapshot_errors() {
+        let root = temp_dir("missing");
+        let err = Snapshot::load(&root.join("snapshot")).unwrap_err();
+        assert!(err.contains("no snapshot"));
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
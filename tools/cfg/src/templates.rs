@@ -1,37 +1,241 @@
-use serde::Deserialize;
-use std::collections::HashMap;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::path::PathBuf;
-use std::process::Command;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::OnceLock;
 use std::time::{Duration, Instant};
+use tera::Context;
 
 use crate::config::Config;
+use crate::deps;
 use crate::leds;
 use crate::palette::Palette;
-use crate::render::{build_context, render_to_file};
+use crate::render::{
+    build_context, output_path, set_context_value, template_context, Engine, PARTIALS_DIR,
+};
+use crate::snapshot::{snapshot_dir, Snapshot};
+use crate::validate;
+use crate::wallpaper::expand_tilde;
 
 pub const DEFAULT_RELOAD_TIMEOUT: Duration = Duration::from_secs(5);
 
-#[derive(Deserialize, Debug, Clone)]
+/// Reload groups run at once. Reloads mostly wait on child processes, so they
+/// get their own small pool rather than rayon's CPU-sized global one.
+pub const MAX_PARALLEL_RELOADS: usize = 4;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 pub struct TemplateConfig {
     pub path: String,
+    /// Shell command run after the output changes
     pub reload: Option<String>,
+    /// Instead of `reload`: signal every process with this name
+    pub signal: Option<SignalReload>,
+    /// Instead of `reload`: restart this systemd user unit
+    pub systemd_unit: Option<String>,
+    /// Instead of `reload`: send this request over Hyprland's IPC socket
+    /// (e.g., "reload", "dispatch exec swayosd-server")
+    pub hyprctl: Option<String>,
     /// Rotz module to re-link after rendering (e.g., "/arch/wlogout")
     pub link: Option<String>,
     /// Fire-and-forget: spawn and return immediately without waiting
     #[serde(default)]
     pub background: bool,
+    /// Write here instead of next to the template. `~` expands to $HOME;
+    /// relative paths are relative to the dotfiles dir.
+    pub output: Option<String>,
+    /// Octal file mode for the output (e.g., "0755")
+    pub mode: Option<String>,
+    /// Command that checks the rendered file before it's written; `{}` is
+    /// the file's path (e.g., "jq . {}"). "none" skips the built-in check.
+    pub validate: Option<String>,
+    /// Tags for `cfg update @tag` (e.g., ["gtk", "qt"])
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Extra variables for this template only
+    #[serde(default)]
+    pub vars: toml::Table,
+    /// Replace context values for this template only (e.g., { primary = "peach" })
+    #[serde(default)]
+    pub overrides: toml::Table,
+}
+
+impl TemplateConfig {
+    /// The shared context with this template's `overrides` and `vars` applied
+    pub fn context(&self, base: &Context, palette: &Palette) -> Result<Context, String> {
+        template_context(base, palette, &self.overrides, &self.vars)
+    }
+
+    /// Where this template renders to: `output` if set, otherwise the
+    /// template path with `.tera` stripped.
+    pub fn output_path(&self, dotfiles_dir: &str) -> PathBuf {
+        match &self.output {
+            Some(output) => {
+                let path = PathBuf::from(expand_tilde(output));
+                if path.is_absolute() {
+                    path
+                } else {
+                    Path::new(dotfiles_dir).join(path)
+                }
+            }
+            None => output_path(&PathBuf::from(format!("{}/{}", dotfiles_dir, self.path))),
+        }
+    }
+
+    /// Check rendered `content` bound for `output`: the built-in validator
+    /// for its extension, then the `validate` command if one is set.
+    pub fn validate_output(&self, output: &Path, content: &str) -> Result<(), String> {
+        match self.validate.as_deref() {
+            Some(validate::DISABLE_BUILTIN) => Ok(()),
+            Some(cmd) => {
+                validate::builtin(output, content)?;
+                validate::command(cmd, output, content)
+            }
+            None => validate::builtin(output, content),
+        }
+    }
+
+    /// The reload to run after the output changes, if any. Errors if more
+    /// than one of `reload`, `signal`, `systemd_unit` and `hyprctl` is set,
+    /// or the signal name is unknown.
+    pub fn reload_action(&self) -> Result<Option<ReloadAction>, String> {
+        let mut actions = Vec::new();
+        if let Some(cmd) = &self.reload {
+            actions.push(ReloadAction::Shell(cmd.clone()));
+        }
+        if let Some(signal) = &self.signal {
+            let (name, _) = parse_signal(&signal.sig)?;
+            actions.push(ReloadAction::Signal {
+                process: signal.process.clone(),
+                sig: name.to_string(),
+            });
+        }
+        if let Some(unit) = &self.systemd_unit {
+            actions.push(ReloadAction::SystemdUnit(unit.clone()));
+        }
+        if let Some(request) = &self.hyprctl {
+            actions.push(ReloadAction::Hyprctl(request.clone()));
+        }
+        if actions.len() > 1 {
+            return Err("set only one of reload, signal, systemd_unit and hyprctl".to_string());
+        }
+        Ok(actions.pop())
+    }
+
+    /// Parsed `mode`, if set.
+    pub fn file_mode(&self) -> Result<Option<u32>, String> {
+        let Some(mode) = &self.mode else {
+            return Ok(None);
+        };
+        u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+            .ok()
+            .filter(|m| *m <= 0o7777)
+            .map(Some)
+            .ok_or_else(|| format!("invalid mode '{}' (expected octal like \"0755\")", mode))
+    }
+}
+
+/// `signal = { process = "kitty", sig = "USR1" }`
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SignalReload {
+    pub process: String,
+    pub sig: String,
+}
+
+/// Signals a `signal` reload may send, by name without the `SIG` prefix.
+const SIGNALS: &[(&str, libc::c_int)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("TERM", libc::SIGTERM),
+    ("KILL", libc::SIGKILL),
+    ("CONT", libc::SIGCONT),
+    ("WINCH", libc::SIGWINCH),
+];
+
+/// Look up a signal by name, with or without `SIG`, in any case.
+fn parse_signal(sig: &str) -> Result<(&'static str, libc::c_int), String> {
+    let upper = sig.to_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .copied()
+        .ok_or_else(|| {
+            let known: Vec<&str> = SIGNALS.iter().map(|(n, _)| *n).collect();
+            format!(
+                "unknown signal '{}' (expected one of {})",
+                sig,
+                known.join(", ")
+            )
+        })
+}
+
+/// How to tell an app its config changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReloadAction {
+    /// `reload`: run through `sh -c`
+    Shell(String),
+    /// `signal`: send `sig` to every process named `process`
+    Signal { process: String, sig: String },
+    /// `systemd_unit`: `systemctl --user restart <unit>`
+    SystemdUnit(String),
+    /// `hyprctl`: request sent to Hyprland's IPC socket
+    Hyprctl(String),
+}
+
+impl std::fmt::Display for ReloadAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReloadAction::Shell(cmd) => write!(f, "{}", cmd),
+            ReloadAction::Signal { process, sig } => write!(f, "signal {} to {}", sig, process),
+            ReloadAction::SystemdUnit(unit) => write!(f, "systemctl --user restart {}", unit),
+            ReloadAction::Hyprctl(request) => write!(f, "hyprctl {}", request),
+        }
+    }
 }
 
-#[derive(Deserialize, Debug)]
+impl ReloadAction {
+    /// Run the reload. Returns the exit code for actions that run a command
+    /// to completion. `background` doesn't wait: shell commands are spawned
+    /// and left, systemd restarts are queued with `--no-block`. Signals and
+    /// Hyprland requests never block long enough to matter.
+    pub fn run(&self, background: bool, timeout: Duration) -> (Option<i32>, Result<(), String>) {
+        match self {
+            ReloadAction::Shell(cmd) if background => (None, reload_app_background(cmd)),
+            ReloadAction::Shell(cmd) => reload_app(cmd, timeout),
+            ReloadAction::Signal { process, sig } => (
+                None,
+                parse_signal(sig).and_then(|(_, signo)| signal_processes(process, signo)),
+            ),
+            ReloadAction::SystemdUnit(unit) => restart_unit(unit, background, timeout),
+            ReloadAction::Hyprctl(request) => (
+                None,
+                hyprland_socket().and_then(|socket| hyprctl_request(&socket, request, timeout)),
+            ),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
 pub struct TemplatesFile {
     pub templates: HashMap<String, TemplateConfig>,
+    /// Named groups for `cfg update @group`: template names or `@tag`s
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>,
 }
 
-/// A coalesced reload group: command, template names, and whether to background.
+/// A coalesced reload group: action, template names, and whether to background.
 pub struct ReloadGroup<'a> {
-    pub cmd: &'a str,
+    pub action: ReloadAction,
     pub names: Vec<&'a str>,
     pub background: bool,
 }
@@ -50,14 +254,185 @@ impl TemplatesFile {
         self.templates.get(name)
     }
 
-    /// Get all template names
+    /// Get all template names, sorted
     pub fn names(&self) -> Vec<&String> {
-        self.templates.keys().collect()
+        let mut names: Vec<&String> = self.templates.keys().collect();
+        names.sort();
+        names
+    }
+
+    /// Every tag and group name, with the sorted templates `@name` expands to
+    pub fn tags(&self) -> BTreeMap<String, Vec<String>> {
+        let mut names: BTreeSet<&str> = self.groups.keys().map(String::as_str).collect();
+        for config in self.templates.values() {
+            names.extend(config.tags.iter().map(String::as_str));
+        }
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let members = self.expand(&[format!("@{}", name)]).ok()?;
+                Some((name.to_string(), members))
+            })
+            .collect()
+    }
+
+    /// Replace every `@name` in `names` with the templates tagged `name` and
+    /// the members of group `name`, keeping order and dropping duplicates.
+    /// Other names pass through unchecked.
+    pub fn expand(&self, names: &[String]) -> Result<Vec<String>, String> {
+        let mut out = Vec::new();
+        for name in names {
+            let expanded = match name.strip_prefix('@') {
+                Some(tag) => self.expand_tag(tag, &mut Vec::new())?,
+                None => vec![name.clone()],
+            };
+            for name in expanded {
+                if !out.contains(&name) {
+                    out.push(name);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn expand_tag(&self, tag: &str, stack: &mut Vec<String>) -> Result<Vec<String>, String> {
+        if stack.iter().any(|t| t == tag) {
+            stack.push(tag.to_string());
+            return Err(format!("Group cycle: @{}", stack.join(" -> @")));
+        }
+        let mut out: BTreeSet<String> = self
+            .templates
+            .iter()
+            .filter(|(_, config)| config.tags.iter().any(|t| t == tag))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let group = self.groups.get(tag);
+        if out.is_empty() && group.is_none() {
+            return Err(format!("Unknown tag or group: @{}", tag));
+        }
+        stack.push(tag.to_string());
+        for member in group.into_iter().flatten() {
+            match member.strip_prefix('@') {
+                Some(inner) => out.extend(self.expand_tag(inner, stack)?),
+                None if self.templates.contains_key(member)
+                    || member == leds::LEDS_UPDATE_TARGET =>
+                {
+                    out.insert(member.clone());
+                }
+                None => {
+                    return Err(format!("Unknown template in group '{}': {}", tag, member));
+                }
+            }
+        }
+        stack.pop();
+        if out.is_empty() {
+            // An empty expansion would otherwise mean "update everything"
+            return Err(format!("@{} has no templates", tag));
+        }
+        Ok(out.into_iter().collect())
+    }
+}
+
+/// Load every registered template plus `<cfg_dir>/partials` into one engine.
+/// Templates are named by their registry `path`, so they can include or
+/// extend each other by that path.
+pub fn load_engine(
+    cfg_dir: &str,
+    dotfiles_dir: &str,
+    templates: &TemplatesFile,
+) -> Result<Engine, String> {
+    let partials_dir = PathBuf::from(format!("{}/{}", cfg_dir, PARTIALS_DIR));
+    Engine::load(&partials_dir, &registry_sources(dotfiles_dir, templates))
+}
+
+/// `(engine name, file)` for every registered template.
+fn registry_sources(dotfiles_dir: &str, templates: &TemplatesFile) -> Vec<(String, PathBuf)> {
+    templates
+        .templates
+        .values()
+        .map(|tpl| {
+            (
+                tpl.path.clone(),
+                PathBuf::from(format!("{}/{}", dotfiles_dir, tpl.path)),
+            )
+        })
+        .collect()
+}
+
+/// Render one template file with the current config, whether or not it's in
+/// `templates.toml`. `flavor` swaps the palette; each `sets` entry
+/// (`key=value`) overrides a context value. Registered templates and
+/// partials are loaded alongside so includes and extends still resolve.
+pub fn render_file(
+    cfg_dir: &str,
+    dotfiles_dir: &str,
+    template: &Path,
+    flavor: Option<&str>,
+    sets: &[String],
+) -> Result<String, String> {
+    let path = template
+        .canonicalize()
+        .map_err(|_| format!("template not found: {}", template.display()))?;
+
+    let mut config = Config::load_or_default(&format!("{}/config.toml", cfg_dir))?;
+    if let Some(flavor) = flavor {
+        config.flavor = flavor.to_string();
+    }
+    let palette_path = format!("{}/palettes/{}.toml", cfg_dir, config.flavor);
+    let palette =
+        Palette::load(&palette_path).map_err(|e| format!("Error loading palette: {}", e))?;
+
+    let mut context = build_context(&config, &palette);
+    for assignment in sets {
+        set_context_value(&mut context, assignment)?;
     }
+
+    // Name it like the registry does when it lives in the dotfiles dir.
+    let name = Path::new(dotfiles_dir)
+        .canonicalize()
+        .ok()
+        .and_then(|dotfiles| path.strip_prefix(dotfiles).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.clone())
+        .display()
+        .to_string();
+
+    let mut sources = TemplatesFile::load(&format!("{}/templates.toml", cfg_dir))
+        .map(|templates| registry_sources(dotfiles_dir, &templates))
+        .unwrap_or_default();
+    sources.retain(|(source, _)| source != &name);
+    sources.push((name.clone(), path));
+
+    let partials_dir = PathBuf::from(format!("{}/{}", cfg_dir, PARTIALS_DIR));
+    Engine::load(&partials_dir, &sources)?.render(&name, &context)
+}
+
+/// Names of registered templates that read any of `keys` (context variables
+/// like `fonts.mono` or `primary`, matched with `deps::uses`), sorted.
+/// Templates that fail to load are left out.
+pub fn templates_using(
+    cfg_dir: &str,
+    dotfiles_dir: &str,
+    keys: &[&str],
+) -> Result<Vec<String>, String> {
+    let templates = TemplatesFile::load(&format!("{}/templates.toml", cfg_dir))?;
+    let engine = load_engine(cfg_dir, dotfiles_dir, &templates)?;
+
+    let mut names: Vec<String> = templates
+        .templates
+        .iter()
+        .filter(|(_, tpl)| {
+            engine
+                .variables(&tpl.path)
+                .is_ok_and(|vars| keys.iter().any(|key| deps::uses(&vars, key)))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    Ok(names)
 }
 
-/// Group rendered template names by their reload command, preserving first-seen order.
-/// Templates without a reload command or not found in the registry are skipped.
+/// Group rendered template names by their reload action, preserving first-seen order.
+/// Templates without a reload action or not found in the registry are skipped.
 /// If any template in a group has `background = true`, the whole group is backgrounded.
 pub fn coalesce_reloads<'a>(
     rendered: &'a [String],
@@ -69,18 +444,18 @@ pub fn coalesce_reloads<'a>(
         let Some(tpl) = templates.get(name) else {
             continue;
         };
-        let Some(cmd) = tpl.reload.as_deref() else {
+        let Ok(Some(action)) = tpl.reload_action() else {
             continue;
         };
 
-        if let Some(group) = groups.iter_mut().find(|g| g.cmd == cmd) {
+        if let Some(group) = groups.iter_mut().find(|g| g.action == action) {
             group.names.push(name.as_str());
             if tpl.background {
                 group.background = true;
             }
         } else {
             groups.push(ReloadGroup {
-                cmd,
+                action,
                 names: vec![name.as_str()],
                 background: tpl.background,
             });
@@ -91,37 +466,133 @@ pub fn coalesce_reloads<'a>(
 }
 
 /// Reload an application using its reload command, with a timeout.
-/// On timeout, the child process is killed.
-pub fn reload_app(cmd: &str, timeout: Duration) -> Result<(), String> {
-    let mut child = Command::new("sh")
-        .arg("-c")
-        .arg(cmd)
-        .spawn()
-        .map_err(|e| format!("Failed to spawn: {}", e))?;
+/// On timeout, the child process is killed. Also returns the command's exit
+/// code when it exited on its own (not on spawn failure, timeout or a signal).
+pub fn reload_app(cmd: &str, timeout: Duration) -> (Option<i32>, Result<(), String>) {
+    match Command::new("sh").arg("-c").arg(cmd).spawn() {
+        Ok(child) => wait_reload(child, timeout),
+        Err(e) => (None, Err(format!("Failed to spawn: {}", e))),
+    }
+}
 
+/// Wait for a reload process, killing it after `timeout`. A failure's
+/// message includes whatever it wrote to stderr, if that was piped.
+fn wait_reload(mut child: Child, timeout: Duration) -> (Option<i32>, Result<(), String>) {
     let start = Instant::now();
     loop {
         match child.try_wait() {
+            Ok(Some(status)) if status.success() => return (status.code(), Ok(())),
             Ok(Some(status)) => {
-                return if status.success() {
-                    Ok(())
-                } else {
-                    Err(format!("exit code: {:?}", status.code()))
-                };
+                let mut stderr = String::new();
+                if let Some(pipe) = child.stderr.as_mut() {
+                    let _ = pipe.read_to_string(&mut stderr);
+                }
+                let mut message = format!("exit code: {:?}", status.code());
+                if !stderr.trim().is_empty() {
+                    message.push_str(&format!(": {}", stderr.trim()));
+                }
+                return (status.code(), Err(message));
             }
             Ok(None) => {
                 if start.elapsed() >= timeout {
                     let _ = child.kill();
                     let _ = child.wait();
-                    return Err(format!("timed out after {}s", timeout.as_secs()));
+                    return (None, Err(format!("timed out after {}s", timeout.as_secs())));
                 }
                 std::thread::sleep(Duration::from_millis(50));
             }
-            Err(e) => return Err(format!("wait error: {}", e)),
+            Err(e) => return (None, Err(format!("wait error: {}", e))),
         }
     }
 }
 
+/// Send `signo` to every process whose name is `process`, like `pkill -x`.
+/// Errors if none is running.
+fn signal_processes(process: &str, signo: libc::c_int) -> Result<(), String> {
+    // /proc/<pid>/comm holds at most 15 bytes of the name.
+    let comm: String = process.chars().take(15).collect();
+    let own = std::process::id();
+    let entries =
+        std::fs::read_dir("/proc").map_err(|e| format!("Failed to list processes: {}", e))?;
+
+    let mut signalled = 0;
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if pid == own {
+            continue;
+        }
+        let Ok(name) = std::fs::read_to_string(entry.path().join("comm")) else {
+            continue;
+        };
+        if name.trim_end() == comm && unsafe { libc::kill(pid as libc::pid_t, signo) } == 0 {
+            signalled += 1;
+        }
+    }
+    if signalled == 0 {
+        return Err(format!("no running process named '{}'", process));
+    }
+    Ok(())
+}
+
+/// `systemctl --user restart <unit>`, reporting systemctl's own error.
+fn restart_unit(
+    unit: &str,
+    background: bool,
+    timeout: Duration,
+) -> (Option<i32>, Result<(), String>) {
+    let mut cmd = Command::new("systemctl");
+    cmd.args(["--user", "restart"]);
+    if background {
+        cmd.arg("--no-block");
+    }
+    match cmd.arg(unit).stderr(Stdio::piped()).spawn() {
+        Ok(child) => wait_reload(child, timeout),
+        Err(e) => (None, Err(format!("Failed to run systemctl: {}", e))),
+    }
+}
+
+/// Hyprland's request socket for the running instance.
+fn hyprland_socket() -> Result<PathBuf, String> {
+    let instance = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+        .map_err(|_| "HYPRLAND_INSTANCE_SIGNATURE not set (is Hyprland running?)".to_string())?;
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_default();
+    [
+        format!("{}/hypr/{}/.socket.sock", runtime_dir, instance),
+        format!("/tmp/hypr/{}/.socket.sock", instance),
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .find(|path| path.exists())
+    .ok_or_else(|| format!("no Hyprland socket for instance {}", instance))
+}
+
+/// Send one request over Hyprland's socket, the way `hyprctl <request>`
+/// does. Anything other than an "ok" reply is an error.
+fn hyprctl_request(socket: &Path, request: &str, timeout: Duration) -> Result<(), String> {
+    let mut stream = UnixStream::connect(socket)
+        .map_err(|e| format!("Failed to connect to {}: {}", socket.display(), e))?;
+    let _ = stream.set_read_timeout(Some(timeout));
+    let _ = stream.set_write_timeout(Some(timeout));
+    // hyprctl prefixes requests with its flags and a '/'; none here.
+    stream
+        .write_all(format!("/{}", request).as_bytes())
+        .map_err(|e| format!("Failed to send to Hyprland: {}", e))?;
+    let mut reply = String::new();
+    stream
+        .read_to_string(&mut reply)
+        .map_err(|e| format!("Failed to read Hyprland reply: {}", e))?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        other => Err(format!("hyprctl {}: {}", request, other)),
+    }
+}
+
 /// Spawn a reload command in the background (fire-and-forget).
 /// Returns immediately without waiting for completion.
 pub fn reload_app_background(cmd: &str) -> Result<(), String> {
@@ -159,12 +630,22 @@ pub fn rotz_link(module: &str) -> Result<(), String> {
 pub struct RenderResult {
     pub name: String,
     pub output: Result<PathBuf, String>,
+    /// Rendered bytes matched the existing file, so it was neither written
+    /// nor reloaded.
+    pub unchanged: bool,
+    /// Time spent rendering and validating
+    pub duration: Duration,
 }
 
 /// Result of running a reload group.
 pub struct ReloadResult {
     pub names: Vec<String>,
+    pub cmd: String,
+    pub background: bool,
     pub result: Result<(), String>,
+    /// Exit code, when the command ran to completion in the foreground
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
 }
 
 /// Full result of an update operation (render + link + reload).
@@ -174,6 +655,89 @@ pub struct UpdateResult {
     pub leds: Option<Result<(), String>>,
 }
 
+/// A rendered template waiting to be written.
+struct StagedOutput {
+    path: PathBuf,
+    content: String,
+    /// Permissions to set after writing, from `TemplateConfig.mode`.
+    mode: Option<u32>,
+    /// Output already holds exactly `content` (and `mode`, if set).
+    unchanged: bool,
+}
+
+/// Write every staged output as one unit. The previous contents are captured
+/// into the snapshot dir first; if any write fails, everything already
+/// written is restored from that snapshot.
+fn commit_outputs(outputs: &[(&str, &StagedOutput)], snapshot_dir: &Path) -> Result<(), String> {
+    let targets: Vec<(String, PathBuf)> = outputs
+        .iter()
+        .map(|(name, out)| (name.to_string(), out.path.clone()))
+        .collect();
+    let snapshot = Snapshot::capture(&targets, snapshot_dir)
+        .map_err(|e| format!("not written: snapshot failed: {}", e))?;
+
+    for (_, out) in outputs {
+        if let Err(e) = write_output(out) {
+            let write_err = format!("Failed to write '{}': {}", out.path.display(), e);
+            return Err(match snapshot.restore(snapshot_dir) {
+                Ok(()) => format!("rolled back: {}", write_err),
+                Err(restore_err) => format!("{} ({})", write_err, restore_err),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Write one output, creating its parent dir and applying its mode.
+fn write_output(out: &StagedOutput) -> std::io::Result<()> {
+    if let Some(parent) = out.path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&out.path, &out.content)?;
+    if let Some(mode) = out.mode {
+        std::fs::set_permissions(&out.path, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+/// Run the coalesced reload commands for `names`, up to
+/// [`MAX_PARALLEL_RELOADS`] groups at a time. Results keep group order.
+fn run_reloads(names: &[String], templates: &TemplatesFile) -> Vec<ReloadResult> {
+    let groups = coalesce_reloads(names, templates);
+    let run = |group: &ReloadGroup| {
+        let start = Instant::now();
+        let (exit_code, result) = group.action.run(group.background, DEFAULT_RELOAD_TIMEOUT);
+        ReloadResult {
+            names: group.names.iter().map(|s| s.to_string()).collect(),
+            cmd: group.action.to_string(),
+            background: group.background,
+            result,
+            exit_code,
+            duration: start.elapsed(),
+        }
+    };
+
+    match reload_pool() {
+        Some(pool) => pool.install(|| groups.par_iter().map(run).collect()),
+        None => groups.iter().map(run).collect(),
+    }
+}
+
+/// The pool reloads run on, built on first use and kept for the process
+/// (watch mode reloads on every change). `None` if it couldn't be built,
+/// in which case reloads run one at a time.
+fn reload_pool() -> Option<&'static ThreadPool> {
+    static POOL: OnceLock<Option<ThreadPool>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        ThreadPoolBuilder::new()
+            .num_threads(MAX_PARALLEL_RELOADS)
+            .build()
+            .ok()
+    })
+    .as_ref()
+}
+
 /// Run a full update: render templates, symlink, and reload apps.
 /// Returns structured results instead of printing to stdout.
 pub fn run_update(
@@ -181,7 +745,7 @@ pub fn run_update(
     dotfiles_dir: &str,
     names: &[String],
 ) -> Result<UpdateResult, String> {
-    let config = Config::load(&format!("{}/config.toml", cfg_dir)).unwrap_or_default();
+    let config = Config::load_or_default(&format!("{}/config.toml", cfg_dir))?;
     let palette_path = format!("{}/palettes/{}.toml", cfg_dir, config.flavor);
     let palette =
         Palette::load(&palette_path).map_err(|e| format!("Error loading palette: {}", e))?;
@@ -191,6 +755,7 @@ pub fn run_update(
     let templates_path = format!("{}/templates.toml", cfg_dir);
     let templates = TemplatesFile::load(&templates_path)?;
 
+    let names = &templates.expand(names)?;
     let include_leds =
         names.is_empty() || names.iter().any(|name| name == leds::LEDS_UPDATE_TARGET);
     let explicit_leds =
@@ -210,61 +775,89 @@ pub fn run_update(
             .collect()
     };
 
-    let mut rendered_names: Vec<String> = Vec::new();
-    let mut render_results: Vec<RenderResult> = Vec::new();
-    let mut linked: HashSet<String> = HashSet::new();
+    let engine = load_engine(cfg_dir, dotfiles_dir, &templates)?;
 
-    for name in &targets {
-        let tpl_config = templates.get(name).unwrap();
-        let template_path = PathBuf::from(format!("{}/{}", dotfiles_dir, tpl_config.path));
+    // Render everything up front, in parallel; nothing touches disk until
+    // every template has rendered, so a broken template can't leave a
+    // half-themed desktop. Collecting keeps `targets` order.
+    let staged: Vec<(String, Result<StagedOutput, String>, Duration)> = targets
+        .par_iter()
+        .map(|name| {
+            let tpl_config = templates.get(name).unwrap();
+            let start = Instant::now();
 
-        if !template_path.exists() {
-            render_results.push(RenderResult {
-                name: (*name).clone(),
-                output: Err(format!("template not found: {}", template_path.display())),
-            });
-            continue;
+            let result = tpl_config
+                .reload_action()
+                .and_then(|_| tpl_config.file_mode())
+                .and_then(|mode| {
+                    let context = tpl_config.context(&context, &palette)?;
+                    let content = engine.render(&tpl_config.path, &context)?;
+                    let path = tpl_config.output_path(dotfiles_dir);
+                    tpl_config.validate_output(&path, &content)?;
+                    let unchanged = std::fs::read(&path)
+                        .is_ok_and(|current| current == content.as_bytes())
+                        && mode.is_none_or(|mode| {
+                            std::fs::metadata(&path)
+                                .is_ok_and(|meta| meta.permissions().mode() & 0o7777 == mode)
+                        });
+                    Ok(StagedOutput {
+                        path,
+                        content,
+                        mode,
+                        unchanged,
+                    })
+                });
+            ((*name).clone(), result, start.elapsed())
+        })
+        .collect();
+
+    // Only outputs whose bytes changed are written (and later reloaded). When
+    // nothing changed the previous snapshot is left alone for --rollback.
+    let commit = if staged.iter().any(|(_, result, _)| result.is_err()) {
+        Err("not written: another template failed to render".to_string())
+    } else {
+        let outputs: Vec<(&str, &StagedOutput)> = staged
+            .iter()
+            .filter_map(|(name, result, _)| result.as_ref().ok().map(|out| (name.as_str(), out)))
+            .filter(|(_, out)| !out.unchanged)
+            .collect();
+        if outputs.is_empty() {
+            Ok(())
+        } else {
+            commit_outputs(&outputs, &snapshot_dir(cfg_dir))
         }
+    };
 
-        match render_to_file(&template_path, &context, false) {
-            Ok(output) => {
-                rendered_names.push((*name).clone());
+    let mut rendered_names: Vec<String> = Vec::new();
+    let mut render_results: Vec<RenderResult> = Vec::new();
+    let mut linked: HashSet<String> = HashSet::new();
 
-                if let Some(link_module) = &tpl_config.link {
+    for (name, result, duration) in staged {
+        let unchanged = result.as_ref().is_ok_and(|out| out.unchanged);
+        let output = match (result, &commit) {
+            (Err(e), _) => Err(e),
+            (Ok(out), _) if out.unchanged => Ok(out.path),
+            (Ok(_), Err(e)) => Err(e.clone()),
+            (Ok(out), Ok(())) => {
+                if let Some(link_module) = &templates.get(&name).unwrap().link {
                     if !linked.contains(link_module) {
                         let _ = rotz_link(link_module);
                         linked.insert(link_module.clone());
                     }
                 }
-
-                render_results.push(RenderResult {
-                    name: (*name).clone(),
-                    output: Ok(output),
-                });
+                rendered_names.push(name.clone());
+                Ok(out.path)
             }
-            Err(e) => {
-                render_results.push(RenderResult {
-                    name: (*name).clone(),
-                    output: Err(e),
-                });
-            }
-        }
+        };
+        render_results.push(RenderResult {
+            name,
+            output,
+            unchanged,
+            duration,
+        });
     }
 
-    let mut reload_results: Vec<ReloadResult> = Vec::new();
-    if !rendered_names.is_empty() {
-        for group in coalesce_reloads(&rendered_names, &templates) {
-            let result = if group.background {
-                reload_app_background(group.cmd)
-            } else {
-                reload_app(group.cmd, DEFAULT_RELOAD_TIMEOUT)
-            };
-            reload_results.push(ReloadResult {
-                names: group.names.iter().map(|s| s.to_string()).collect(),
-                result,
-            });
-        }
-    }
+    let reload_results = run_reloads(&rendered_names, &templates);
 
     let led_result = if include_leds {
         let result = leds::apply_theme(&config, &palette, false, None, None).map(|_| ());
@@ -283,6 +876,35 @@ pub fn run_update(
     })
 }
 
+/// Restore the outputs captured before the last update and re-run their
+/// reloads so running apps pick the old files back up.
+pub fn run_rollback(cfg_dir: &str) -> Result<UpdateResult, String> {
+    let dir = snapshot_dir(cfg_dir);
+    let snapshot = Snapshot::load(&dir)?;
+    snapshot.restore(&dir)?;
+
+    let templates_path = format!("{}/templates.toml", cfg_dir);
+    let templates = TemplatesFile::load(&templates_path)?;
+
+    let names: Vec<String> = snapshot.entries.iter().map(|e| e.name.clone()).collect();
+    let reloaded = run_reloads(&names, &templates);
+
+    Ok(UpdateResult {
+        rendered: snapshot
+            .entries
+            .into_iter()
+            .map(|entry| RenderResult {
+                name: entry.name,
+                output: Ok(entry.path),
+                unchanged: false,
+                duration: Duration::ZERO,
+            })
+            .collect(),
+        reloaded,
+        leds: None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -295,12 +917,315 @@ mod tests {
                 TemplateConfig {
                     path: format!("test/{}.tera", name),
                     reload: reload.map(|s| s.to_string()),
+                    signal: None,
+                    systemd_unit: None,
+                    hyprctl: None,
                     link: None,
                     background,
+                    output: None,
+                    mode: None,
+                    validate: None,
+                    tags: Vec::new(),
+                    vars: toml::Table::new(),
+                    overrides: toml::Table::new(),
                 },
             );
         }
-        TemplatesFile { templates }
+        TemplatesFile {
+            templates,
+            groups: BTreeMap::new(),
+        }
+    }
+
+    fn template_with(output: Option<&str>, mode: Option<&str>) -> TemplateConfig {
+        TemplateConfig {
+            path: "tools/papirus/set-folders.sh.tera".to_string(),
+            reload: None,
+            signal: None,
+            systemd_unit: None,
+            hyprctl: None,
+            link: None,
+            background: false,
+            output: output.map(String::from),
+            mode: mode.map(String::from),
+            validate: None,
+            tags: Vec::new(),
+            vars: toml::Table::new(),
+            overrides: toml::Table::new(),
+        }
+    }
+
+    // =========================================================================
+    // TemplateConfig output/mode
+    // =========================================================================
+
+    #[test]
+    fn output_path_defaults_to_template_without_tera() {
+        assert_eq!(
+            template_with(None, None).output_path("/dots"),
+            PathBuf::from("/dots/tools/papirus/set-folders.sh")
+        );
+    }
+
+    #[test]
+    fn output_path_override_absolute_and_relative() {
+        assert_eq!(
+            template_with(Some("/opt/bin/set-folders"), None).output_path("/dots"),
+            PathBuf::from("/opt/bin/set-folders")
+        );
+        assert_eq!(
+            template_with(Some("generated/set-folders.sh"), None).output_path("/dots"),
+            PathBuf::from("/dots/generated/set-folders.sh")
+        );
+    }
+
+    #[test]
+    fn file_mode_parses_octal() {
+        assert_eq!(template_with(None, None).file_mode(), Ok(None));
+        assert_eq!(
+            template_with(None, Some("0755")).file_mode(),
+            Ok(Some(0o755))
+        );
+        assert_eq!(
+            template_with(None, Some("644")).file_mode(),
+            Ok(Some(0o644))
+        );
+        assert!(template_with(None, Some("0999")).file_mode().is_err());
+        assert!(template_with(None, Some("rwx")).file_mode().is_err());
+    }
+
+    // =========================================================================
+    // reload actions
+    // =========================================================================
+
+    fn parse_registry(toml: &str) -> TemplatesFile {
+        toml::from_str(toml).expect("registry should parse")
+    }
+
+    #[test]
+    fn reload_action_reads_typed_entries() {
+        let registry = parse_registry(
+            r#"[templates]
+kitty = { path = "k.tera", signal = { process = "kitty", sig = "SIGusr1" } }
+waybar = { path = "w.tera", systemd_unit = "waybar.service" }
+hyprland = { path = "h.tera", hyprctl = "reload" }
+mako = { path = "m.tera", reload = "makoctl reload" }
+btop = { path = "b.tera" }
+"#,
+        );
+        let action = |name: &str| registry.get(name).unwrap().reload_action().unwrap();
+
+        assert_eq!(
+            action("kitty"),
+            Some(ReloadAction::Signal {
+                process: "kitty".to_string(),
+                sig: "USR1".to_string()
+            })
+        );
+        assert_eq!(
+            action("waybar"),
+            Some(ReloadAction::SystemdUnit("waybar.service".to_string()))
+        );
+        assert_eq!(
+            action("hyprland"),
+            Some(ReloadAction::Hyprctl("reload".to_string()))
+        );
+        assert_eq!(
+            action("mako"),
+            Some(ReloadAction::Shell("makoctl reload".to_string()))
+        );
+        assert_eq!(action("btop"), None);
+        assert_eq!(
+            action("waybar").unwrap().to_string(),
+            "systemctl --user restart waybar.service"
+        );
+    }
+
+    #[test]
+    fn reload_action_rejects_conflicts_and_unknown_signals() {
+        let registry = parse_registry(
+            r#"[templates]
+both = { path = "b.tera", reload = "true", hyprctl = "reload" }
+bad = { path = "x.tera", signal = { process = "kitty", sig = "USR9" } }
+"#,
+        );
+        assert!(registry
+            .get("both")
+            .unwrap()
+            .reload_action()
+            .unwrap_err()
+            .contains("only one of"));
+        assert!(registry
+            .get("bad")
+            .unwrap()
+            .reload_action()
+            .unwrap_err()
+            .contains("unknown signal 'USR9'"));
+    }
+
+    #[test]
+    fn expand_resolves_tags_and_groups() {
+        let registry = parse_registry(
+            r#"[templates]
+gtk3 = { path = "g3.tera", tags = ["gtk"] }
+gtk4 = { path = "g4.tera", tags = ["gtk", "adwaita"] }
+qt6ct = { path = "q.tera", tags = ["qt"] }
+kitty = { path = "k.tera" }
+
+[groups]
+desktop = ["@gtk", "@qt", "kitty"]
+"#,
+        );
+        let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            registry.expand(&names(&["@gtk"])).unwrap(),
+            names(&["gtk3", "gtk4"])
+        );
+        assert_eq!(
+            registry
+                .expand(&names(&["kitty", "@desktop", "gtk4"]))
+                .unwrap(),
+            names(&["kitty", "gtk3", "gtk4", "qt6ct"])
+        );
+        // Plain names are left for the caller to check
+        assert_eq!(
+            registry.expand(&names(&["nope"])).unwrap(),
+            names(&["nope"])
+        );
+        assert_eq!(registry.tags()["adwaita"], names(&["gtk4"]));
+        assert_eq!(registry.tags()["desktop"].len(), 4);
+    }
+
+    #[test]
+    fn expand_rejects_unknown_empty_and_cyclic_groups() {
+        let registry = parse_registry(
+            r#"[templates]
+kitty = { path = "k.tera" }
+
+[groups]
+empty = []
+typo = ["kity"]
+a = ["@b"]
+b = ["@a"]
+"#,
+        );
+        let expand = |name: &str| registry.expand(&[name.to_string()]).unwrap_err();
+
+        assert_eq!(expand("@nope"), "Unknown tag or group: @nope");
+        assert_eq!(expand("@empty"), "@empty has no templates");
+        assert_eq!(expand("@typo"), "Unknown template in group 'typo': kity");
+        assert_eq!(expand("@a"), "Group cycle: @a -> @b -> @a");
+    }
+
+    #[test]
+    fn coalesce_groups_identical_typed_actions() {
+        let registry = parse_registry(
+            r#"[templates]
+style = { path = "s.tera", systemd_unit = "waybar.service" }
+config = { path = "c.tera", systemd_unit = "waybar.service" }
+kitty = { path = "k.tera", signal = { process = "kitty", sig = "USR1" } }
+diff = { path = "d.tera", signal = { process = "kitty", sig = "SIGUSR1" } }
+bars = { path = "b.tera", hyprctl = "reload" }
+"#,
+        );
+        let rendered: Vec<String> = ["style", "kitty", "bars", "config", "diff"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let groups = coalesce_reloads(&rendered, &registry);
+        let names: Vec<Vec<&str>> = groups.iter().map(|g| g.names.clone()).collect();
+        assert_eq!(
+            names,
+            vec![vec!["style", "config"], vec!["kitty", "diff"], vec!["bars"]]
+        );
+    }
+
+    #[test]
+    fn signal_reaches_processes_by_name() {
+        let dir = std::env::temp_dir().join(format!("cfg-signal-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let shell = dir.join("cfgsigtest");
+        let ready = dir.join("ready");
+        let _ = std::fs::remove_file(&ready);
+        std::fs::copy("/bin/sh", &shell).unwrap();
+        let mut child = Command::new(&shell)
+            .arg("-c")
+            .arg(format!(
+                "trap 'exit 7' USR1; touch '{}'; while :; do sleep 0.05; done",
+                ready.display()
+            ))
+            .spawn()
+            .unwrap();
+
+        // Wait until the process is findable by name and has its trap set
+        let comm = format!("/proc/{}/comm", child.id());
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !(ready.exists()
+            && std::fs::read_to_string(&comm).is_ok_and(|name| name.trim() == "cfgsigtest"))
+        {
+            assert!(Instant::now() < deadline, "test process never started");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let (code, result) = ReloadAction::Signal {
+            process: "cfgsigtest".to_string(),
+            sig: "USR1".to_string(),
+        }
+        .run(false, DEFAULT_RELOAD_TIMEOUT);
+        assert_eq!((code, result), (None, Ok(())));
+        assert_eq!(child.wait().unwrap().code(), Some(7));
+
+        assert_eq!(
+            signal_processes("cfgsigtest", libc::SIGUSR1),
+            Err("no running process named 'cfgsigtest'".to_string())
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn hyprctl_request_sends_command_and_checks_reply() {
+        use std::os::unix::net::UnixListener;
+
+        let socket = std::env::temp_dir().join(format!("cfg-hypr-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for reply in ["ok", "Invalid dispatcher"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 256];
+                let len = stream.read(&mut buf).unwrap();
+                requests.push(String::from_utf8_lossy(&buf[..len]).to_string());
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+            requests
+        });
+
+        let timeout = Duration::from_secs(2);
+        assert_eq!(hyprctl_request(&socket, "reload", timeout), Ok(()));
+        assert_eq!(
+            hyprctl_request(&socket, "dispatch nope", timeout),
+            Err("hyprctl dispatch nope: Invalid dispatcher".to_string())
+        );
+        assert_eq!(server.join().unwrap(), vec!["/reload", "/dispatch nope"]);
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[test]
+    fn reload_failure_includes_piped_stderr() {
+        let child = Command::new("sh")
+            .args(["-c", "echo 'Unit waybar.service not found.' >&2; exit 5"])
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let (code, result) = wait_reload(child, DEFAULT_RELOAD_TIMEOUT);
+        assert_eq!(code, Some(5));
+        assert_eq!(
+            result,
+            Err("exit code: Some(5): Unit waybar.service not found.".to_string())
+        );
     }
 
     // =========================================================================
@@ -318,7 +1243,7 @@ mod tests {
 
         let groups = coalesce_reloads(&rendered, &tpl);
         assert_eq!(groups.len(), 1);
-        assert_eq!(groups[0].cmd, "hyprctl reload");
+        assert_eq!(groups[0].action.to_string(), "hyprctl reload");
         assert_eq!(groups[0].names, vec!["a", "b", "c"]);
         assert!(!groups[0].background);
     }
@@ -334,9 +1259,9 @@ mod tests {
 
         let groups = coalesce_reloads(&rendered, &tpl);
         assert_eq!(groups.len(), 2);
-        assert_eq!(groups[0].cmd, "cmd-x");
+        assert_eq!(groups[0].action.to_string(), "cmd-x");
         assert_eq!(groups[0].names, vec!["x", "z"]);
-        assert_eq!(groups[1].cmd, "cmd-y");
+        assert_eq!(groups[1].action.to_string(), "cmd-y");
         assert_eq!(groups[1].names, vec!["y"]);
     }
 
@@ -380,10 +1305,10 @@ mod tests {
 
         let groups = coalesce_reloads(&rendered, &tpl);
         assert_eq!(groups.len(), 3);
-        assert_eq!(groups[0].cmd, "hyprctl reload");
+        assert_eq!(groups[0].action.to_string(), "hyprctl reload");
         assert_eq!(groups[0].names, vec!["hyprland", "hyprbars"]);
-        assert_eq!(groups[1].cmd, "makoctl reload");
-        assert_eq!(groups[2].cmd, "pkill -USR1 kitty");
+        assert_eq!(groups[1].action.to_string(), "makoctl reload");
+        assert_eq!(groups[2].action.to_string(), "pkill -USR1 kitty");
     }
 
     #[test]
@@ -413,28 +1338,167 @@ mod tests {
 
     #[test]
     fn reload_success() {
-        assert!(reload_app("true", Duration::from_secs(5)).is_ok());
+        assert_eq!(
+            reload_app("true", Duration::from_secs(5)),
+            (Some(0), Ok(()))
+        );
     }
 
     #[test]
     fn reload_failure() {
-        let err = reload_app("false", Duration::from_secs(5)).unwrap_err();
-        assert!(err.contains("exit code"));
+        let (code, result) = reload_app("exit 3", Duration::from_secs(5));
+        assert_eq!(code, Some(3));
+        assert!(result.unwrap_err().contains("exit code"));
     }
 
     #[test]
     fn reload_timeout_kills() {
         let start = Instant::now();
-        let err = reload_app("sleep 60", Duration::from_millis(200)).unwrap_err();
+        let (code, result) = reload_app("sleep 60", Duration::from_millis(200));
+        let err = result.unwrap_err();
         let elapsed = start.elapsed();
 
         assert!(err.contains("timed out"));
+        assert_eq!(code, None);
         assert!(
             elapsed < Duration::from_secs(1),
             "should return quickly after timeout"
         );
     }
 
+    // =========================================================================
+    // run_reloads
+    // =========================================================================
+
+    #[test]
+    fn reload_groups_run_in_parallel_and_keep_order() {
+        // Each group checks in, then waits until all of them have: that only
+        // finishes if MAX_PARALLEL_RELOADS groups run at the same time.
+        let dir = std::env::temp_dir().join(format!("cfg-reload-barrier-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let barrier = |name: &str, status: &str| {
+            format!(
+                "touch '{dir}/{name}'; for _ in $(seq 40); do \
+                 [ $(ls '{dir}' | wc -l) -ge {n} ] && exit {status}; sleep 0.1; done; \
+                 echo barrier not reached >&2; exit 9",
+                dir = dir.display(),
+                n = MAX_PARALLEL_RELOADS,
+            )
+        };
+        let commands: Vec<(&str, String)> = vec![
+            ("a", barrier("a", "0")),
+            ("b", barrier("b", "1")),
+            ("c", barrier("c", "0")),
+            ("d", barrier("d", "0")),
+        ];
+        assert_eq!(commands.len(), MAX_PARALLEL_RELOADS);
+        let tpl = make_templates(
+            commands
+                .iter()
+                .map(|(name, cmd)| (*name, Some(cmd.as_str()), false))
+                .collect(),
+        );
+        let names: Vec<String> = commands.iter().map(|(n, _)| n.to_string()).collect();
+
+        let results = run_reloads(&names, &tpl);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let order: Vec<&str> = results.iter().map(|r| r.names[0].as_str()).collect();
+        assert_eq!(order, vec!["a", "b", "c", "d"]);
+        let codes: Vec<Option<i32>> = results.iter().map(|r| r.exit_code).collect();
+        assert_eq!(codes, vec![Some(0), Some(1), Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn reload_pool_is_built_once() {
+        let first = reload_pool().expect("pool should build");
+        assert!(std::ptr::eq(first, reload_pool().unwrap()));
+        assert_eq!(first.current_num_threads(), MAX_PARALLEL_RELOADS);
+    }
+
+    // =========================================================================
+    // run_update
+    // =========================================================================
+
+    /// A cfg and dotfiles dir with `count` templates `t00`, `t01`, ... that
+    /// each render their own name, plus a broken `t{broken}` if given.
+    fn update_fixture(test: &str, count: usize, broken: Option<usize>) -> (PathBuf, Vec<String>) {
+        let root = std::env::temp_dir().join(format!("cfg-update-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("cfg/palettes")).unwrap();
+        std::fs::create_dir_all(root.join("dotfiles")).unwrap();
+        std::fs::write(
+            root.join("cfg/palettes/mocha.toml"),
+            "[colors]\nblue = \"89b4fa\"\n",
+        )
+        .unwrap();
+
+        let mut registry = String::from("[templates]\n");
+        let names: Vec<String> = (0..count).map(|i| format!("t{:02}", i)).collect();
+        for (i, name) in names.iter().enumerate() {
+            registry.push_str(&format!("{} = {{ path = \"{}.conf.tera\" }}\n", name, name));
+            let body = if Some(i) == broken {
+                "{{ missing }}".to_string()
+            } else {
+                format!("{} {{{{ blue | hex }}}}\n", name)
+            };
+            std::fs::write(root.join(format!("dotfiles/{}.conf.tera", name)), body).unwrap();
+        }
+        std::fs::write(root.join("cfg/templates.toml"), registry).unwrap();
+        (root, names)
+    }
+
+    fn update(root: &Path, names: &[String]) -> UpdateResult {
+        run_update(
+            &root.join("cfg").to_string_lossy(),
+            &root.join("dotfiles").to_string_lossy(),
+            names,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn parallel_staging_keeps_order_and_writes_everything() {
+        let (root, names) = update_fixture("staging-ok", 24, None);
+        // Reverse order, to see results follow the request, not the registry
+        let requested: Vec<String> = names.iter().rev().cloned().collect();
+
+        let result = update(&root, &requested);
+
+        let order: Vec<&String> = result.rendered.iter().map(|r| &r.name).collect();
+        assert_eq!(order, requested.iter().collect::<Vec<_>>());
+        for name in &names {
+            let output = root.join(format!("dotfiles/{}.conf", name));
+            assert_eq!(
+                std::fs::read_to_string(output).unwrap(),
+                format!("{} 89b4fa\n", name)
+            );
+        }
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn parallel_staging_writes_nothing_when_one_template_fails() {
+        let (root, names) = update_fixture("staging-fail", 24, Some(17));
+
+        let result = update(&root, &names);
+
+        let order: Vec<&String> = result.rendered.iter().map(|r| &r.name).collect();
+        assert_eq!(order, names.iter().collect::<Vec<_>>());
+        for (i, r) in result.rendered.iter().enumerate() {
+            let err = r.output.as_ref().unwrap_err();
+            if i == 17 {
+                assert!(err.contains("Failed to render"), "{}", err);
+            } else {
+                assert!(err.contains("another template failed"), "{}", err);
+            }
+            assert!(!root.join(format!("dotfiles/{}.conf", r.name)).exists());
+        }
+        assert!(result.reloaded.is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
     // =========================================================================
     // reload_app_background
     // =========================================================================
@@ -0,0 +1,113 @@
+//! Everything cfg uses from Tera's `#[doc(hidden)]` API, in one place.
+//!
+//! Tera doesn't publicly expose parsed templates or its context lookup, but
+//! loading templates with their partials (`render::Engine`), dependency
+//! tracking (`deps`) and `cfg lint` all need them. None of this is covered by
+//! Tera's semver. It is written against tera 1.20.1, so the crate manifest
+//! (kept outside this tree) must pin `tera = "=1.20.1"`. When bumping it,
+//! this module is the only code to check, and its tests fail if any of these
+//! pieces change behavior.
+
+use std::collections::HashMap;
+
+use tera::{Tera, Value};
+
+pub use tera::ast::{Expr, ExprVal, FunctionCall, LogicOperator, Node};
+pub use tera::Template;
+
+/// Parse a template on its own, without adding it to a `Tera` instance.
+pub fn parse(name: &str, content: &str) -> tera::Result<Template> {
+    Template::new(name, None, content)
+}
+
+/// The parsed body of a template.
+pub fn ast(template: &Template) -> &[Node] {
+    &template.ast
+}
+
+/// Templates that `template` needs loaded alongside it: its
+/// `{% extends %}` parent, then every `{% import %}`ed macro file.
+pub fn dependencies(template: &Template) -> impl Iterator<Item = &str> {
+    template.parent.as_deref().into_iter().chain(
+        template
+            .imported_macro_files
+            .iter()
+            .map(|(file, _)| file.as_str()),
+    )
+}
+
+/// Every template loaded in `tera`, by name.
+pub fn templates(tera: &Tera) -> &HashMap<String, Template> {
+    &tera.templates
+}
+
+/// Whether `name` is registered as a filter in `tera` (custom or built-in).
+pub fn has_filter(tera: &Tera, name: &str) -> bool {
+    filter_names(tera).any(|filter| filter == name)
+}
+
+/// Names of every filter registered in `tera` (custom and built-in).
+pub fn filter_names(tera: &Tera) -> impl Iterator<Item = &str> {
+    tera.filters.keys().map(String::as_str)
+}
+
+/// Look up a dotted context path (`fonts.mono`, `vars.gaps`) the way Tera
+/// resolves a variable at render time.
+pub fn lookup<'a>(context: &'a Value, path: &str) -> Option<&'a Value> {
+    tera::dotted_pointer(context, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsed_templates_expose_body_and_dependencies() {
+        let template = parse(
+            "kitty",
+            "{% extends \"partials/base\" %}{% import \"partials/macros\" as m %}\
+             {% block body %}{{ blue | hex }}{% endblock body %}",
+        )
+        .unwrap();
+        assert_eq!(
+            dependencies(&template).collect::<Vec<_>>(),
+            vec!["partials/base", "partials/macros"]
+        );
+        assert!(ast(&template)
+            .iter()
+            .any(|node| matches!(node, Node::Block(..))));
+
+        let plain = parse("bar", "{{ blue }}").unwrap();
+        assert_eq!(dependencies(&plain).count(), 0);
+        assert!(matches!(ast(&plain), [Node::VariableBlock(..)]));
+        assert!(parse("broken", "{{ blue").is_err());
+    }
+
+    #[test]
+    fn loaded_templates_and_filters() {
+        let mut tera = Tera::default();
+        tera.register_filter(
+            "hex",
+            |v: &tera::Value, _: &HashMap<String, tera::Value>| Ok(v.clone()),
+        );
+        tera.add_raw_templates(vec![("kitty", "{{ blue | hex }}")])
+            .unwrap();
+        assert!(templates(&tera).contains_key("kitty"));
+        assert!(has_filter(&tera, "hex"));
+        assert!(has_filter(&tera, "upper"));
+        assert!(!has_filter(&tera, "nope"));
+        let names: Vec<&str> = filter_names(&tera).collect();
+        assert!(names.contains(&"hex") && names.contains(&"upper"));
+    }
+
+    #[test]
+    fn lookup_follows_dotted_paths() {
+        let context = serde_json::json!({ "fonts": { "mono": "Iosevka" }, "gaps": 8 });
+        assert_eq!(
+            lookup(&context, "fonts.mono"),
+            Some(&Value::from("Iosevka"))
+        );
+        assert_eq!(lookup(&context, "gaps"), Some(&Value::from(8)));
+        assert_eq!(lookup(&context, "fonts.sans"), None);
+    }
+}
@@ -1,4 +1,5 @@
 use std::io;
+use std::time::Duration;
 
 use crossterm::event::{self, Event, KeyCode, KeyEventKind, MouseEventKind};
 use ratatui::{
@@ -12,12 +13,13 @@ use ratatui::{
 use crate::config::Config;
 use crate::leds;
 use crate::palette::Palette;
-use crate::templates::{run_update, ReloadResult, RenderResult};
+use crate::templates::{run_update, templates_using, ReloadResult, RenderResult};
 use crate::wallpaper;
 
 use super::colors::ColorPicker;
 use super::fonts::FontPicker;
 use super::keyboard::KeyboardPicker;
+use super::profiles::ProfilePicker;
 use super::update::UpdatePicker;
 use super::wallpapers::WallpaperPicker;
 use super::{init, restore};
@@ -31,15 +33,30 @@ pub struct ApplyScope {
     pub fonts: bool,
     pub wallpaper: bool,
     pub keyboard: bool,
+    /// A profile was switched to: everything may have changed
+    pub profile: bool,
 }
 
 impl ApplyScope {
     pub fn any(&self) -> bool {
-        self.colors || self.fonts || self.wallpaper || self.keyboard
+        self.colors || self.fonts || self.wallpaper || self.keyboard || self.profile
     }
 
-    pub fn with_color_side_effects(self) -> Self {
+    /// Context variables the committed pickers can change. Only templates
+    /// reading one of these are re-rendered.
+    pub fn template_vars(&self) -> Vec<&'static str> {
+        let mut vars = Vec::new();
         if self.colors {
+            vars.extend(["primary", "secondary"]);
+        }
+        if self.fonts {
+            vars.push("fonts");
+        }
+        vars
+    }
+
+    pub fn with_color_side_effects(self) -> Self {
+        if self.colors || self.profile {
             Self {
                 wallpaper: true,
                 keyboard: true,
@@ -70,6 +87,7 @@ pub enum Tab {
     Wallpapers,
     Keyboard,
     Update,
+    Profiles,
 }
 
 impl Tab {
@@ -80,6 +98,7 @@ impl Tab {
             Tab::Wallpapers => 2,
             Tab::Keyboard => 3,
             Tab::Update => 4,
+            Tab::Profiles => 5,
         }
     }
 
@@ -89,7 +108,8 @@ impl Tab {
             Tab::Fonts => Tab::Wallpapers,
             Tab::Wallpapers => Tab::Keyboard,
             Tab::Keyboard => Tab::Update,
-            Tab::Update => Tab::Colors,
+            Tab::Update => Tab::Profiles,
+            Tab::Profiles => Tab::Colors,
         }
     }
 }
@@ -128,6 +148,7 @@ pub struct App {
     wallpaper_init_error: Option<String>,
     keyboard_picker: KeyboardPicker,
     update_picker: UpdatePicker,
+    profile_picker: ProfilePicker,
     theme: TabTheme,
     should_apply: bool,
     config_path: String,
@@ -166,6 +187,13 @@ impl App {
             dotfiles_dir.clone(),
         );
 
+        let profile_picker = ProfilePicker::new(
+            palette,
+            &config.primary,
+            cfg_dir.clone(),
+            config_path.clone(),
+        );
+
         let (wallpaper_picker, wallpaper_init_error) = match WallpaperPicker::new(
             config.clone(),
             palette,
@@ -184,6 +212,7 @@ impl App {
             wallpaper_init_error,
             keyboard_picker,
             update_picker,
+            profile_picker,
             theme,
             should_apply: false,
             config_path,
@@ -232,6 +261,7 @@ impl App {
             }
             Tab::Keyboard => self.keyboard_picker.render_in_area(frame, chunks[1]),
             Tab::Update => self.update_picker.render_in_area(frame, chunks[1]),
+            Tab::Profiles => self.profile_picker.render_in_area(frame, chunks[1]),
         }
 
         if let Some(results) = &self.apply_results {
@@ -307,12 +337,13 @@ impl App {
                     .add_modifier(Modifier::BOLD),
             )));
             for result in &results.rendered {
-                let (mark, style) = if result.output.is_ok() {
-                    ("ok", Style::default().fg(Color::Green))
-                } else {
-                    ("fail", Style::default().fg(Color::Red))
+                let (mark, style) = match &result.output {
+                    Ok(_) if result.unchanged => ("same", Style::default().fg(self.theme.subtext0)),
+                    Ok(_) => ("ok", Style::default().fg(Color::Green)),
+                    Err(_) => ("fail", Style::default().fg(Color::Red)),
                 };
                 let detail = match &result.output {
+                    Ok(_) if result.unchanged => "unchanged".to_string(),
                     Ok(path) => path.display().to_string(),
                     Err(e) => e.clone(),
                 };
@@ -429,6 +460,15 @@ impl App {
                 ),
                 Span::raw(" \u{f0e4e} Update"), // 󰑓 reload icon
             ]),
+            Line::from(vec![
+                Span::styled(
+                    "6",
+                    Style::default()
+                        .fg(self.theme.subtext0)
+                        .add_modifier(Modifier::DIM),
+                ),
+                Span::raw(" \u{f007} Profiles"), //  user icon
+            ]),
         ];
 
         let tabs = Tabs::new(titles)
@@ -466,8 +506,10 @@ impl App {
                         self.active_tab = Tab::Wallpapers;
                     } else if mouse.column < 52 {
                         self.active_tab = Tab::Keyboard;
-                    } else {
+                    } else if mouse.column < 64 {
                         self.active_tab = Tab::Update;
+                    } else {
+                        self.active_tab = Tab::Profiles;
                     }
                     return Ok(true);
                 }
@@ -487,6 +529,7 @@ impl App {
                         .unwrap_or(false),
                     Tab::Keyboard => self.keyboard_picker.captures_input(),
                     Tab::Update => self.update_picker.is_in_search(),
+                    Tab::Profiles => self.profile_picker.is_in_search(),
                 };
 
                 if !captures_input {
@@ -511,6 +554,10 @@ impl App {
                             self.active_tab = Tab::Update;
                             return Ok(true);
                         }
+                        KeyCode::Char('6') => {
+                            self.active_tab = Tab::Profiles;
+                            return Ok(true);
+                        }
                         KeyCode::BackTab => {
                             // Shift+Tab switches between tabs
                             self.active_tab = self.active_tab.next();
@@ -545,6 +592,7 @@ impl App {
             },
             Tab::Keyboard => self.keyboard_picker.handle_event(event)?,
             Tab::Update => self.update_picker.handle_event(event)?,
+            Tab::Profiles => self.profile_picker.handle_event(event)?,
         };
 
         // Check if picker wants to apply
@@ -559,6 +607,7 @@ impl App {
                     .unwrap_or(false),
                 Tab::Keyboard => self.keyboard_picker.wants_apply(),
                 Tab::Update => false, // Update tab handles its own updates
+                Tab::Profiles => self.profile_picker.wants_apply(),
             };
         }
 
@@ -575,8 +624,29 @@ impl App {
             keyboard: None,
         };
 
-        if scope.colors || scope.fonts {
-            match run_update(&self.cfg_dir, &self.dotfiles_dir, &[]) {
+        // A profile switch can change anything, so it updates every template
+        let affected = if scope.profile {
+            Ok(Vec::new())
+        } else if scope.colors || scope.fonts {
+            templates_using(&self.cfg_dir, &self.dotfiles_dir, &scope.template_vars())
+        } else {
+            Ok(Vec::new())
+        };
+        let affected = match affected {
+            Ok(names) => names,
+            Err(e) => {
+                results.rendered.push(RenderResult {
+                    name: "update".to_string(),
+                    output: Err(e),
+                    unchanged: false,
+                    duration: Duration::ZERO,
+                });
+                Vec::new()
+            }
+        };
+
+        if scope.profile || !affected.is_empty() {
+            match run_update(&self.cfg_dir, &self.dotfiles_dir, &affected) {
                 Ok(result) => {
                     results.rendered = result.rendered;
                     results.reloaded = result.reloaded;
@@ -588,14 +658,18 @@ impl App {
                     results.rendered.push(RenderResult {
                         name: "update".to_string(),
                         output: Err(e),
+                        unchanged: false,
+                        duration: Duration::ZERO,
                     });
                 }
             }
         }
 
         if scope.wallpaper {
-            let config = Config::load(&self.config_path).unwrap_or_default();
-            results.wallpaper = Some(wallpaper::apply(&config, &self.cfg_dir));
+            results.wallpaper = Some(
+                Config::load_or_default(&self.config_path)
+                    .and_then(|config| wallpaper::apply(&config, &self.cfg_dir)),
+            );
         }
 
         if scope.keyboard && results.keyboard.is_none() {
@@ -650,6 +724,7 @@ impl App {
             fonts: fonts_apply,
             wallpaper: wallpaper_apply,
             keyboard: self.keyboard_picker.wants_apply(),
+            profile: self.profile_picker.wants_apply(),
         };
         if scope.any() {
             let results = self.run_apply(scope);
@@ -694,6 +769,9 @@ fn format_scope(scope: ApplyScope) -> String {
     if scope.keyboard {
         parts.push("keyboard");
     }
+    if scope.profile {
+        parts.push("profile");
+    }
     parts.join(", ")
 }
 
@@ -717,7 +795,7 @@ fn result_line<'a>(
 }
 
 fn load_config_and_palette(cfg_dir: &str) -> Result<(Config, Palette), String> {
-    let config = Config::load(&format!("{}/config.toml", cfg_dir)).unwrap_or_default();
+    let config = Config::load_or_default(&format!("{}/config.toml", cfg_dir))?;
     let palette_path = format!("{}/palettes/{}.toml", cfg_dir, config.flavor);
     let palette =
         Palette::load(&palette_path).map_err(|e| format!("Error loading palette: {}", e))?;
@@ -769,6 +847,22 @@ mod tests {
         assert!(scope.keyboard);
     }
 
+    #[test]
+    fn template_vars_follow_committed_pickers() {
+        let colors = ApplyScope {
+            colors: true,
+            ..ApplyScope::default()
+        };
+        assert_eq!(colors.template_vars(), vec!["primary", "secondary"]);
+
+        let fonts = ApplyScope {
+            fonts: true,
+            wallpaper: true,
+            ..ApplyScope::default()
+        };
+        assert_eq!(fonts.template_vars(), vec!["fonts"]);
+    }
+
     #[test]
     fn tab_order_places_keyboard_before_update() {
         assert_eq!(Tab::Keyboard.index(), 3);
@@ -776,4 +870,17 @@ mod tests {
         assert_eq!(Tab::Wallpapers.next(), Tab::Keyboard);
         assert_eq!(Tab::Keyboard.next(), Tab::Update);
     }
+
+    #[test]
+    fn profile_apply_refreshes_everything() {
+        let scope = ApplyScope {
+            profile: true,
+            ..ApplyScope::default()
+        }
+        .with_color_side_effects();
+
+        assert!(scope.any());
+        assert!(scope.wallpaper);
+        assert!(scope.keyboard);
+    }
 }
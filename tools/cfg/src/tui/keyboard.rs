@@ -746,6 +746,7 @@ mod tests {
                         r: 30,
                         g: 30,
                         b: 46,
+                        a: None,
                     },
                 ),
                 (
@@ -754,6 +755,7 @@ mod tests {
                         r: 205,
                         g: 214,
                         b: 244,
+                        a: None,
                     },
                 ),
                 (
@@ -762,6 +764,7 @@ mod tests {
                         r: 166,
                         g: 173,
                         b: 200,
+                        a: None,
                     },
                 ),
                 (
@@ -770,6 +773,7 @@ mod tests {
                         r: 49,
                         g: 50,
                         b: 68,
+                        a: None,
                     },
                 ),
                 (
@@ -778,6 +782,7 @@ mod tests {
                         r: 69,
                         g: 71,
                         b: 90,
+                        a: None,
                     },
                 ),
                 (
@@ -786,6 +791,7 @@ mod tests {
                         r: 127,
                         g: 132,
                         b: 156,
+                        a: None,
                     },
                 ),
                 (
@@ -794,6 +800,7 @@ mod tests {
                         r: 166,
                         g: 227,
                         b: 161,
+                        a: None,
                     },
                 ),
                 (
@@ -802,6 +809,7 @@ mod tests {
                         r: 249,
                         g: 226,
                         b: 175,
+                        a: None,
                     },
                 ),
                 (
@@ -810,6 +818,7 @@ mod tests {
                         r: 243,
                         g: 139,
                         b: 168,
+                        a: None,
                     },
                 ),
                 (
@@ -818,6 +827,7 @@ mod tests {
                         r: 137,
                         g: 180,
                         b: 250,
+                        a: None,
                     },
                 ),
             ]),
@@ -3,6 +3,7 @@ pub mod clipboard;
 pub mod colors;
 pub mod fonts;
 pub mod keyboard;
+pub mod profiles;
 pub mod update;
 pub mod wallpapers;
 mod widgets;
@@ -0,0 +1,479 @@
+use std::io;
+
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::config::Config;
+use crate::palette::Palette;
+use crate::profile;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Mode {
+    Normal,
+    /// Typing a name for `s` (save current config as a profile)
+    Naming(String),
+}
+
+struct Theme {
+    base: Color,
+    text: Color,
+    subtext0: Color,
+    surface0: Color,
+    surface1: Color,
+    overlay1: Color,
+    green: Color,
+    red: Color,
+    accent: Color,
+}
+
+impl Theme {
+    fn from_palette(palette: &Palette, primary: &str) -> Self {
+        let get = |name: &str| -> Color {
+            palette
+                .get(name)
+                .map(|c| Color::Rgb(c.r, c.g, c.b))
+                .unwrap_or(Color::White)
+        };
+        Self {
+            base: get("base"),
+            text: get("text"),
+            subtext0: get("subtext0"),
+            surface0: get("surface0"),
+            surface1: get("surface1"),
+            overlay1: get("overlay1"),
+            green: get("green"),
+            red: get("red"),
+            accent: get(primary),
+        }
+    }
+}
+
+/// Profiles tab: switch to a saved profile (and apply it), or save the
+/// current config as a new one.
+pub struct ProfilePicker {
+    names: Vec<String>,
+    /// Profiles matching the current config
+    current: Vec<String>,
+    selected: usize,
+    list_state: ListState,
+    mode: Mode,
+    /// Result of the last save or failed switch, with whether it failed
+    message: Option<(String, bool)>,
+    theme: Theme,
+    cfg_dir: String,
+    config_path: String,
+    should_apply: bool,
+}
+
+impl ProfilePicker {
+    pub fn new(palette: &Palette, primary: &str, cfg_dir: String, config_path: String) -> Self {
+        let mut picker = Self {
+            names: Vec::new(),
+            current: Vec::new(),
+            selected: 0,
+            list_state: ListState::default(),
+            mode: Mode::Normal,
+            message: None,
+            theme: Theme::from_palette(palette, primary),
+            cfg_dir,
+            config_path,
+            should_apply: false,
+        };
+        picker.reload();
+        picker
+    }
+
+    fn config(&self) -> Result<Config, String> {
+        Config::load_or_default(&self.config_path)
+    }
+
+    fn reload(&mut self) {
+        match profile::list(&self.cfg_dir) {
+            Ok(names) => self.names = names,
+            Err(e) => self.message = Some((e, true)),
+        }
+        self.current = match self.config() {
+            Ok(config) => profile::matching(&self.cfg_dir, &config),
+            Err(e) => {
+                self.message = Some((e, true));
+                Vec::new()
+            }
+        };
+        self.selected = self.selected.min(self.names.len().saturating_sub(1));
+        self.list_state.select(if self.names.is_empty() {
+            None
+        } else {
+            Some(self.selected)
+        });
+    }
+
+    fn selected_name(&self) -> Option<&String> {
+        self.names.get(self.selected)
+    }
+
+    fn save(&mut self, name: &str) {
+        let saved = self
+            .config()
+            .and_then(|config| profile::save(&self.cfg_dir, name, &config));
+        self.message = Some(match saved {
+            Ok(_) => (format!("Saved profile '{}'", name), false),
+            Err(e) => (e, true),
+        });
+        self.reload();
+        if let Some(idx) = self.names.iter().position(|n| n == name) {
+            self.selected = idx;
+            self.list_state.select(Some(idx));
+        }
+    }
+
+    /// Switch to the selected profile. Returns whether it worked, in which
+    /// case the App applies it.
+    fn switch(&mut self) -> bool {
+        let Some(name) = self.selected_name().cloned() else {
+            return false;
+        };
+        match profile::switch(&self.cfg_dir, &name) {
+            Ok(_) => {
+                self.should_apply = true;
+                true
+            }
+            Err(e) => {
+                self.message = Some((e, true));
+                false
+            }
+        }
+    }
+
+    // ── Rendering ──────────────────────────────────────────────────────
+
+    pub fn render_in_area(&mut self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(
+            Block::default().style(Style::default().bg(self.theme.base)),
+            area,
+        );
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(10),
+                Constraint::Length(1),
+            ])
+            .split(area);
+
+        self.render_header(frame, chunks[0]);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+            .split(chunks[1]);
+        self.render_list(frame, columns[0]);
+        self.render_changes(frame, columns[1]);
+
+        self.render_footer(frame, chunks[2]);
+    }
+
+    fn render_header(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.surface1))
+            .title(Span::styled(
+                " cfg profile ",
+                Style::default()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let line = match (&self.mode, &self.message) {
+            (Mode::Naming(name), _) => Line::from(vec![
+                Span::styled("Save as: ", Style::default().fg(self.theme.overlay1)),
+                Span::styled(name.clone(), Style::default().fg(self.theme.text)),
+                Span::styled(" ", Style::default().bg(self.theme.text)),
+            ]),
+            (Mode::Normal, Some((message, failed))) => Line::from(Span::styled(
+                message.clone(),
+                Style::default().fg(if *failed {
+                    self.theme.red
+                } else {
+                    self.theme.green
+                }),
+            )),
+            (Mode::Normal, None) => Line::from(Span::styled(
+                format!("{} saved", self.names.len()),
+                Style::default().fg(self.theme.overlay1),
+            )),
+        };
+        frame.render_widget(Paragraph::new(line), inner);
+    }
+
+    fn render_list(&mut self, frame: &mut Frame, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.surface1))
+            .title(Span::styled(
+                " Profiles ",
+                Style::default().fg(self.theme.overlay1),
+            ))
+            .title_bottom(Line::from(vec![
+                Span::styled(" *", Style::default().fg(self.theme.green)),
+                Span::styled(" current ", Style::default().fg(self.theme.surface1)),
+            ]));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        if self.names.is_empty() {
+            frame.render_widget(
+                Paragraph::new("  No profiles yet. Press s to save the current config.")
+                    .style(Style::default().fg(self.theme.subtext0))
+                    .wrap(Wrap { trim: false }),
+                inner,
+            );
+            return;
+        }
+
+        let items: Vec<ListItem> = self
+            .names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let marker = if self.current.contains(name) {
+                    Span::styled("* ", Style::default().fg(self.theme.green))
+                } else {
+                    Span::raw("  ")
+                };
+                let style = if idx == self.selected {
+                    Style::default().bg(self.theme.surface0)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw("  "),
+                    marker,
+                    Span::styled(name.clone(), Style::default().fg(self.theme.text)),
+                ]))
+                .style(style)
+            })
+            .collect();
+
+        frame.render_stateful_widget(List::new(items), inner, &mut self.list_state);
+    }
+
+    /// What switching to the selected profile would change
+    fn render_changes(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.surface1))
+            .title(Span::styled(
+                " Changes ",
+                Style::default().fg(self.theme.overlay1),
+            ));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let Some(name) = self.selected_name() else {
+            return;
+        };
+        let changes = self.config().and_then(|current| {
+            profile::load(&self.cfg_dir, name).map(|target| profile::diff(&current, &target))
+        });
+        let lines: Vec<Line> = match changes {
+            Ok(changes) => {
+                if changes.is_empty() {
+                    vec![Line::from(Span::styled(
+                        "  Same as the current config",
+                        Style::default().fg(self.theme.subtext0),
+                    ))]
+                } else {
+                    changes
+                        .into_iter()
+                        .map(|change| {
+                            Line::from(vec![
+                                Span::styled(
+                                    format!("  {}  ", change.key),
+                                    Style::default().fg(self.theme.text),
+                                ),
+                                Span::styled(
+                                    change.old.unwrap_or_else(|| "(unset)".to_string()),
+                                    Style::default().fg(self.theme.subtext0),
+                                ),
+                                Span::styled(" → ", Style::default().fg(self.theme.overlay1)),
+                                Span::styled(
+                                    change.new.unwrap_or_else(|| "(unset)".to_string()),
+                                    Style::default().fg(self.theme.accent),
+                                ),
+                            ])
+                        })
+                        .collect()
+                }
+            }
+            Err(e) => vec![Line::from(Span::styled(
+                format!("  {}", e),
+                Style::default().fg(self.theme.red),
+            ))],
+        };
+        frame.render_widget(Paragraph::new(lines), inner);
+    }
+
+    fn render_footer(&self, frame: &mut Frame, area: Rect) {
+        let hints: Vec<(&str, &str)> = match self.mode {
+            Mode::Naming(_) => vec![("Enter", "save"), ("Esc", "cancel")],
+            Mode::Normal => vec![
+                ("j/k", "navigate"),
+                ("Enter", "use + apply"),
+                ("s", "save current"),
+                ("q/Esc", "quit"),
+            ],
+        };
+
+        let spans: Vec<Span> = hints
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (key, desc))| {
+                let mut s = vec![
+                    Span::styled(*key, Style::default().fg(self.theme.accent)),
+                    Span::styled(
+                        format!(" {} ", desc),
+                        Style::default().fg(self.theme.overlay1),
+                    ),
+                ];
+                if i < hints.len() - 1 {
+                    s.push(Span::raw(" "));
+                }
+                s
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
+    }
+
+    // ── Event handling ─────────────────────────────────────────────────
+
+    pub fn handle_event(&mut self, event: Event) -> io::Result<bool> {
+        let Event::Key(key) = event else {
+            return Ok(true);
+        };
+        if key.kind != KeyEventKind::Press {
+            return Ok(true);
+        }
+
+        if let Mode::Naming(name) = &mut self.mode {
+            match key.code {
+                KeyCode::Esc => self.mode = Mode::Normal,
+                KeyCode::Enter => {
+                    let name = name.trim().to_string();
+                    self.mode = Mode::Normal;
+                    if !name.is_empty() {
+                        self.save(&name);
+                    }
+                }
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Char(c) => name.push(c),
+                _ => {}
+            }
+            return Ok(true);
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Char('s') => {
+                let name = self.selected_name().cloned().unwrap_or_default();
+                self.message = None;
+                self.mode = Mode::Naming(name);
+            }
+            KeyCode::Enter if self.switch() => return Ok(false),
+            KeyCode::Char('j') | KeyCode::Down => self.move_down(),
+            KeyCode::Char('k') | KeyCode::Up => self.move_up(),
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn move_down(&mut self) {
+        if self.selected + 1 < self.names.len() {
+            self.selected += 1;
+            self.list_state.select(Some(self.selected));
+        }
+    }
+
+    fn move_up(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+            self.list_state.select(Some(self.selected));
+        }
+    }
+
+    /// Whether the profile picker is capturing text input
+    pub fn is_in_search(&self) -> bool {
+        matches!(self.mode, Mode::Naming(_))
+    }
+
+    pub fn wants_apply(&self) -> bool {
+        self.should_apply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyEvent, KeyModifiers};
+    use std::collections::HashMap;
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn picker(name: &str) -> (ProfilePicker, String) {
+        let dir =
+            std::env::temp_dir().join(format!("cfg-tui-profiles-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let cfg_dir = dir.to_string_lossy().into_owned();
+        let palette = Palette {
+            colors: HashMap::new(),
+        };
+        let picker = ProfilePicker::new(
+            &palette,
+            "blue",
+            cfg_dir.clone(),
+            format!("{}/config.toml", cfg_dir),
+        );
+        (picker, cfg_dir)
+    }
+
+    #[test]
+    fn save_then_switch_requests_apply() {
+        let (mut picker, cfg_dir) = picker("switch");
+
+        picker.handle_event(key(KeyCode::Char('s'))).unwrap();
+        assert!(picker.is_in_search());
+        for c in "work".chars() {
+            picker.handle_event(key(KeyCode::Char(c))).unwrap();
+        }
+        picker.handle_event(key(KeyCode::Enter)).unwrap();
+        assert_eq!(picker.names, vec!["work"]);
+        assert_eq!(picker.current, vec!["work"]);
+
+        let mut latte = Config::default();
+        latte.set("flavor", "latte").unwrap();
+        profile::save(&cfg_dir, "latte", &latte).unwrap();
+        std::fs::create_dir_all(format!("{}/palettes", cfg_dir)).unwrap();
+        std::fs::write(format!("{}/palettes/latte.toml", cfg_dir), "[colors]\n").unwrap();
+        picker.reload();
+        assert_eq!(picker.selected_name().unwrap(), "latte");
+
+        assert!(!picker.handle_event(key(KeyCode::Enter)).unwrap());
+        assert!(picker.wants_apply());
+        let config = Config::load(&format!("{}/config.toml", cfg_dir)).unwrap();
+        assert_eq!(config.flavor, "latte");
+    }
+}
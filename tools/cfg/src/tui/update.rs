@@ -1,4 +1,5 @@
 use std::io;
+use std::time::Duration;
 
 use crossterm::event::{Event, KeyCode, KeyEventKind};
 use ratatui::{
@@ -22,6 +23,8 @@ struct UnitEntry {
     name: String,
     has_reload: bool,
     selected: bool,
+    /// For a `@tag`/`@group` row: indices of its member templates
+    members: Option<Vec<usize>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -87,6 +90,7 @@ impl UpdatePicker {
         let templates_path = format!("{}/templates.toml", cfg_dir);
         let templates = TemplatesFile::load(&templates_path).unwrap_or_else(|_| TemplatesFile {
             templates: std::collections::HashMap::new(),
+            groups: Default::default(),
         });
 
         let mut units: Vec<UnitEntry> = templates
@@ -94,12 +98,33 @@ impl UpdatePicker {
             .iter()
             .map(|(name, config)| UnitEntry {
                 name: name.clone(),
-                has_reload: config.reload.is_some(),
+                has_reload: config.reload_action().is_ok_and(|a| a.is_some()),
                 selected: false,
+                members: None,
             })
             .collect();
         units.sort_by(|a, b| a.name.cmp(&b.name));
 
+        // Tags and groups go first, so a whole set can be toggled at once
+        let tags = templates.tags();
+        let offset = tags.len();
+        let groups: Vec<UnitEntry> = tags
+            .into_iter()
+            .map(|(tag, names)| UnitEntry {
+                name: format!("@{}", tag),
+                has_reload: false,
+                selected: false,
+                members: Some(
+                    names
+                        .iter()
+                        .filter_map(|n| units.iter().position(|u| &u.name == n))
+                        .map(|i| i + offset)
+                        .collect(),
+                ),
+            })
+            .collect();
+        let units: Vec<UnitEntry> = groups.into_iter().chain(units).collect();
+
         let filtered: Vec<usize> = (0..units.len()).collect();
 
         Self {
@@ -138,15 +163,45 @@ impl UpdatePicker {
         self.filtered.get(self.selected).copied()
     }
 
+    /// How many of a unit's templates are selected, out of how many
+    fn selection(&self, idx: usize) -> (usize, usize) {
+        match &self.units[idx].members {
+            Some(members) => (
+                members.iter().filter(|&&i| self.units[i].selected).count(),
+                members.len(),
+            ),
+            None => (self.units[idx].selected as usize, 1),
+        }
+    }
+
+    fn selected_count(&self) -> usize {
+        self.units.iter().filter(|u| u.selected).count()
+    }
+
     fn toggle_selected(&mut self) {
         if let Some(idx) = self.selected_unit_index() {
-            self.units[idx].selected = !self.units[idx].selected;
+            let (selected, total) = self.selection(idx);
+            let select = selected < total;
+            match self.units[idx].members.clone() {
+                Some(members) => {
+                    for i in members {
+                        self.units[i].selected = select;
+                    }
+                }
+                None => self.units[idx].selected = select,
+            }
         }
     }
 
     fn toggle_all(&mut self) {
-        let all_selected = self.filtered.iter().all(|&i| self.units[i].selected);
-        for &i in &self.filtered {
+        let templates: Vec<usize> = self
+            .filtered
+            .iter()
+            .copied()
+            .filter(|&i| self.units[i].members.is_none())
+            .collect();
+        let all_selected = templates.iter().all(|&i| self.units[i].selected);
+        for i in templates {
             self.units[i].selected = !all_selected;
         }
     }
@@ -174,6 +229,8 @@ impl UpdatePicker {
                 self.render_results = vec![RenderResult {
                     name: "error".to_string(),
                     output: Err(e),
+                    unchanged: false,
+                    duration: Duration::ZERO,
                 }];
                 self.reload_results = Vec::new();
             }
@@ -211,7 +268,7 @@ impl UpdatePicker {
     }
 
     fn render_header(&mut self, frame: &mut Frame, area: Rect) {
-        let selected_count = self.units.iter().filter(|u| u.selected).count();
+        let selected_count = self.selected_count();
         let title = if selected_count > 0 {
             format!(" cfg update  {} selected ", selected_count)
         } else {
@@ -255,6 +312,8 @@ impl UpdatePicker {
         let inner = block.inner(area);
         frame.render_widget(block, area);
 
+        let selections: Vec<(usize, usize)> =
+            self.filtered.iter().map(|&i| self.selection(i)).collect();
         let items: Vec<ListItem> = self
             .filtered
             .iter()
@@ -265,15 +324,19 @@ impl UpdatePicker {
 
                 let mut spans = vec![Span::raw("  ")];
 
-                // Checkbox
-                if unit.selected {
+                // Checkbox; a partly selected group gets a dash
+                let (selected, total) = selections[vis_idx];
+                let all_selected = total > 0 && selected == total;
+                if all_selected {
                     spans.push(Span::styled("✓ ", Style::default().fg(self.theme.green)));
+                } else if selected > 0 {
+                    spans.push(Span::styled("- ", Style::default().fg(self.theme.green)));
                 } else {
                     spans.push(Span::styled("  ", Style::default()));
                 }
 
                 // Name with fuzzy highlight
-                let name_style = if unit.selected {
+                let name_style = if all_selected {
                     Style::default().fg(self.theme.text)
                 } else {
                     Style::default().fg(self.theme.subtext0)
@@ -295,8 +358,13 @@ impl UpdatePicker {
                     spans.push(Span::raw(" ".repeat(padding)));
                 }
 
-                // Reload indicator
-                if unit.has_reload {
+                // Member count for groups, reload indicator for templates
+                if let Some(members) = &unit.members {
+                    spans.push(Span::styled(
+                        format!("{} templates", members.len()),
+                        Style::default().fg(self.theme.overlay1),
+                    ));
+                } else if unit.has_reload {
                     spans.push(Span::styled("󰑓", Style::default().fg(self.theme.overlay1)));
                 }
 
@@ -331,7 +399,7 @@ impl UpdatePicker {
     }
 
     fn render_footer(&self, frame: &mut Frame, area: Rect) {
-        let selected_count = self.units.iter().filter(|u| u.selected).count();
+        let selected_count = self.selected_count();
         let enter_label = if selected_count > 0 {
             format!("update ({})", selected_count)
         } else {
@@ -382,18 +450,22 @@ impl UpdatePicker {
         let ok_count = self
             .render_results
             .iter()
-            .filter(|r| r.output.is_ok())
+            .filter(|r| r.output.is_ok() && !r.unchanged)
             .count();
+        let unchanged_count = self.render_results.iter().filter(|r| r.unchanged).count();
         let fail_count = self
             .render_results
             .iter()
             .filter(|r| r.output.is_err())
             .count();
-        let title = if fail_count > 0 {
-            format!(" cfg update  {} ok, {} failed ", ok_count, fail_count)
-        } else {
-            format!(" cfg update  {} ok ", ok_count)
-        };
+        let mut title = format!(" cfg update  {} ok", ok_count);
+        if unchanged_count > 0 {
+            title.push_str(&format!(", {} unchanged", unchanged_count));
+        }
+        if fail_count > 0 {
+            title.push_str(&format!(", {} failed", fail_count));
+        }
+        title.push(' ');
 
         let header_block = Block::default()
             .borders(Borders::ALL)
@@ -421,10 +493,12 @@ impl UpdatePicker {
 
         for r in &self.render_results {
             let (icon, style) = match &r.output {
+                Ok(_) if r.unchanged => ("=", Style::default().fg(self.theme.overlay1)),
                 Ok(_) => ("✓", Style::default().fg(self.theme.green)),
                 Err(_) => ("✗", Style::default().fg(self.theme.red)),
             };
             let detail = match &r.output {
+                Ok(_) if r.unchanged => "  unchanged".to_string(),
                 Ok(path) => format!("  →  {}", path.display()),
                 Err(e) => format!("  {}", e),
             };
@@ -0,0 +1,448 @@
+//! Checks run on rendered output before it is written, so a template typo
+//! fails the update instead of breaking the app at reload.
+//!
+//! Built-in checks are picked by output extension: JSON/JSONC and TOML are
+//! parsed; YAML and CSS get a structural syntax check. A template can add its
+//! own command via `validate` in `templates.toml`.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// `validate` value that turns off the built-in check for a template.
+pub const DISABLE_BUILTIN: &str = "none";
+
+/// How long a custom validator may run before it's killed.
+pub const VALIDATOR_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Numbers each validator's temp file. Templates are staged in parallel and
+/// several can share an output file name (`gtk.css` for GTK 3 and 4).
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Run the built-in check for `output`'s extension, if there is one.
+pub fn builtin(output: &Path, content: &str) -> Result<(), String> {
+    let ext = output
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let result = match ext.as_str() {
+        "json" => check_json(content),
+        "jsonc" => check_json(&strip_jsonc(content)),
+        "toml" => toml::from_str::<toml::Value>(content)
+            .map(|_| ())
+            .map_err(|e| e.message().to_string()),
+        "yaml" | "yml" => check_yaml(content),
+        "css" => check_css(content),
+        _ => Ok(()),
+    };
+    result.map_err(|e| format!("invalid {}: {}", ext, e))
+}
+
+/// Run a user command against the rendered content. The content is written
+/// to a temp file with the output's extension; `{}` in `cmd` is replaced by
+/// its path (or the path is appended when there's no `{}`).
+pub fn command(cmd: &str, output: &Path, content: &str) -> Result<(), String> {
+    let mut name = format!(
+        "cfg-validate-{}-{}",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    if let Some(file) = output.file_name().and_then(|f| f.to_str()) {
+        name.push('-');
+        name.push_str(file);
+    }
+    let temp = std::env::temp_dir().join(name);
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp)
+        .and_then(|mut f| f.write_all(content.as_bytes()))
+        .map_err(|e| format!("failed to write temp file for validator: {}", e))?;
+
+    let quoted = format!("'{}'", temp.display().to_string().replace('\'', r"'\''"));
+    let full = if cmd.contains("{}") {
+        cmd.replace("{}", &quoted)
+    } else {
+        format!("{} {}", cmd, quoted)
+    };
+    let result = run(&full, VALIDATOR_TIMEOUT);
+    let _ = std::fs::remove_file(&temp);
+    result.map_err(|e| format!("validate '{}' failed: {}", cmd, e))
+}
+
+fn run(cmd: &str, timeout: Duration) -> Result<(), String> {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn: {}", e))?;
+    let pid = child.id();
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(child.wait_with_output());
+    });
+
+    match rx.recv_timeout(timeout) {
+        Ok(Ok(out)) if out.status.success() => Ok(()),
+        Ok(Ok(out)) => {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            let stdout = String::from_utf8_lossy(&out.stdout);
+            let message = [stderr.trim(), stdout.trim()]
+                .into_iter()
+                .find(|s| !s.is_empty())
+                .map(|s| s.lines().take(3).collect::<Vec<_>>().join(" / "));
+            Err(message.unwrap_or_else(|| format!("exit code: {:?}", out.status.code())))
+        }
+        Ok(Err(e)) => Err(format!("wait error: {}", e)),
+        Err(_) => {
+            unsafe {
+                libc::kill(pid as libc::pid_t, libc::SIGKILL);
+            }
+            Err(format!("timed out after {}s", timeout.as_secs()))
+        }
+    }
+}
+
+fn check_json(content: &str) -> Result<(), String> {
+    serde_json::from_str::<serde_json::Value>(content)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Drop `//` and `/* */` comments and trailing commas so JSONC (waybar
+/// config) parses as JSON. Newlines are kept so error lines still match.
+/// Comments go first, so a comma followed by a comment still counts as
+/// trailing.
+fn strip_jsonc(content: &str) -> String {
+    drop_trailing_commas(&strip_comments(content))
+}
+
+fn strip_comments(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                    }
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn drop_trailing_commas(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            ',' => {
+                let next = chars.clone().find(|n| !n.is_whitespace());
+                if !matches!(next, Some('}' | ']')) {
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Balanced braces, parens and brackets, closed strings and comments.
+fn check_css(content: &str) -> Result<(), String> {
+    let mut open: Vec<(char, usize)> = Vec::new();
+    let mut line = 1;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let start = line;
+                let mut prev = ' ';
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if prev == '*' && c == '/' {
+                        closed = true;
+                        break;
+                    }
+                    prev = c;
+                }
+                if !closed {
+                    return Err(format!("unterminated comment starting on line {}", start));
+                }
+            }
+            '"' | '\'' => {
+                let mut closed = false;
+                while let Some(s) = chars.next() {
+                    match s {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '\n' => break,
+                        _ if s == c => {
+                            closed = true;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                if !closed {
+                    return Err(format!("line {}: unterminated string", line));
+                }
+            }
+            '{' | '(' | '[' => open.push((c, line)),
+            '}' | ')' | ']' => {
+                let expected = match c {
+                    '}' => '{',
+                    ')' => '(',
+                    _ => '[',
+                };
+                match open.pop() {
+                    Some((o, _)) if o == expected => {}
+                    Some((o, l)) => {
+                        return Err(format!(
+                            "line {}: '{}' doesn't match '{}' from line {}",
+                            line, c, o, l
+                        ))
+                    }
+                    None => return Err(format!("line {}: unexpected '{}'", line, c)),
+                }
+            }
+            _ => {}
+        }
+    }
+    match open.pop() {
+        Some((o, l)) => Err(format!("'{}' opened on line {} is never closed", o, l)),
+        None => Ok(()),
+    }
+}
+
+/// No tabs in indentation, quoted scalars closed on their line, and flow
+/// collections (`[..]`, `{..}`) balanced. Block scalars (`|`, `>`) are skipped.
+fn check_yaml(content: &str) -> Result<(), String> {
+    let mut block_scalar_indent: Option<usize> = None;
+    let mut flow_depth: i32 = 0;
+    for (index, raw) in content.lines().enumerate() {
+        let line = index + 1;
+        let trimmed = raw.trim_start_matches([' ', '\t']);
+        let indent = raw.len() - trimmed.len();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(parent) = block_scalar_indent {
+            if indent > parent {
+                continue;
+            }
+            block_scalar_indent = None;
+        }
+        if raw[..indent].contains('\t') {
+            return Err(format!("line {}: tab in indentation", line));
+        }
+        if trimmed.starts_with('#') {
+            continue;
+        }
+
+        // Quotes and flow collections only count where a scalar can start:
+        // line start, after `- `, `: `, or inside a flow collection.
+        let mut quote: Option<char> = None;
+        let mut scalar_start = true;
+        let mut prev = ' ';
+        let mut value = String::new();
+        let mut chars = trimmed.chars().peekable();
+        while let Some(c) = chars.next() {
+            match quote {
+                Some('"') if c == '\\' => {
+                    chars.next();
+                }
+                Some(q) if c == q => {
+                    if q == '\'' && chars.peek() == Some(&'\'') {
+                        chars.next();
+                    } else {
+                        quote = None;
+                    }
+                }
+                Some(_) => {}
+                None => match c {
+                    ' ' => scalar_start |= matches!(prev, ':' | '-'),
+                    '#' if prev == ' ' => break,
+                    '"' | '\'' if scalar_start => {
+                        quote = Some(c);
+                        scalar_start = false;
+                    }
+                    '[' | '{' if scalar_start => flow_depth += 1,
+                    ',' if flow_depth > 0 => scalar_start = true,
+                    ']' | '}' if flow_depth > 0 => flow_depth -= 1,
+                    _ => scalar_start = false,
+                },
+            }
+            value.push(c);
+            prev = c;
+        }
+        if quote.is_some() && flow_depth == 0 {
+            return Err(format!("line {}: unterminated quoted string", line));
+        }
+        if quote.is_none() && is_block_scalar_header(value.trim_end()) {
+            block_scalar_indent = Some(indent);
+        }
+    }
+    if flow_depth != 0 {
+        return Err("unclosed '[' or '{'".to_string());
+    }
+    Ok(())
+}
+
+/// `key: |`, `- >-`, a bare `|2`, ...
+fn is_block_scalar_header(value: &str) -> bool {
+    let (before, last) = match value.rsplit_once(' ') {
+        Some((before, last)) => (before.trim_end(), last),
+        None => ("", value),
+    };
+    let mut indicator = last.chars();
+    matches!(indicator.next(), Some('|' | '>'))
+        && indicator.all(|c| c == '-' || c == '+' || c.is_ascii_digit())
+        && (before.is_empty() || before.ends_with(':') || before == "-" || before.ends_with(" -"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(name: &str, content: &str) -> Result<(), String> {
+        builtin(Path::new(name), content)
+    }
+
+    #[test]
+    fn json_and_jsonc() {
+        assert!(check("a.json", "{\"a\": 1}").is_ok());
+        assert!(check("a.json", "{\"a\": 1,}").is_err());
+        let jsonc = "// top\n{\n  \"a\": \"http://x\", /* note */\n  \"b\": [1, 2,],\n}\n";
+        assert!(check("config.jsonc", jsonc).is_ok());
+        assert!(check("config.jsonc", "{\n  \"a\": 1\n  \"b\": 2\n}").is_err());
+        // A comment between the last entry's comma and the closing bracket
+        assert!(check("config.jsonc", "{\"a\": 1, // note\n}").is_ok());
+        assert!(check("config.jsonc", "[1, /* x */ ]").is_ok());
+        assert!(check("config.jsonc", "{\"a\": \"//,\", \"b\": \"/*\"}").is_ok());
+    }
+
+    #[test]
+    fn toml_syntax() {
+        assert!(check("a.toml", "[section]\nkey = \"v\"\n").is_ok());
+        let err = check("a.toml", "key = \n").unwrap_err();
+        assert!(err.starts_with("invalid toml"), "{}", err);
+    }
+
+    #[test]
+    fn css_balance() {
+        let css = "@define-color blue #89b4fa;\n/* { */\nwindow { color: alpha(@blue, 0.5); content: \"}\"; }\n";
+        assert!(check("gtk.css", css).is_ok());
+        let err = check("gtk.css", "window {\n  color: red;\n").unwrap_err();
+        assert!(err.contains("opened on line 1"), "{}", err);
+        assert!(check("gtk.css", "a { b: c; }}").is_err());
+        assert!(check("gtk.css", "a { b: rgba(0, 0, 0; }").is_err());
+    }
+
+    #[test]
+    fn yaml_structure() {
+        let yaml = "# theme\ncolors:\n  blue: \"#89b4fa\"\n  list: [a, 'b', {c: d}]\n  note: don't panic\nscript: |\n  if [ \"$x\n\tstill block\nafter: 1\n";
+        assert!(check("theme.yml", yaml).is_ok());
+        let err = check("theme.yaml", "a:\n\tb: 1\n").unwrap_err();
+        assert!(err.contains("tab"), "{}", err);
+        assert!(check("theme.yaml", "a: \"open\n").is_err());
+        assert!(check("theme.yaml", "a: [1, 2\n").is_err());
+    }
+
+    #[test]
+    fn unknown_extension_passes() {
+        assert!(check("hyprland.conf", "{{{").is_ok());
+    }
+
+    #[test]
+    fn command_gets_rendered_file() {
+        assert!(command("grep -q needle {}", Path::new("x.conf"), "hay needle\n").is_ok());
+        let err = command("grep -q needle", Path::new("x.conf"), "hay\n").unwrap_err();
+        assert!(err.contains("exit code"), "{}", err);
+        let err = command("echo bad line >&2; false", Path::new("x.conf"), "").unwrap_err();
+        assert!(err.contains("bad line"), "{}", err);
+    }
+
+    #[test]
+    fn same_named_outputs_validate_separately() {
+        // Each validator is still running when the other writes its file
+        std::thread::scope(|scope| {
+            let gtk3 = scope.spawn(|| {
+                command(
+                    "sleep 0.2; grep -qx gtk3",
+                    Path::new("gtk-3.0/gtk.css"),
+                    "gtk3\n",
+                )
+            });
+            let gtk4 = scope.spawn(|| {
+                command(
+                    "sleep 0.2; grep -qx gtk4",
+                    Path::new("gtk-4.0/gtk.css"),
+                    "gtk4\n",
+                )
+            });
+            assert_eq!(gtk3.join().unwrap(), Ok(()));
+            assert_eq!(gtk4.join().unwrap(), Ok(()));
+        });
+    }
+}
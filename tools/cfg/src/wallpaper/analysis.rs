@@ -29,13 +29,20 @@ pub struct DominantColor {
 /// Returns an empty `Vec` (not an error) if the grey filter removes every
 /// color — the caller decides what fallback, if any, makes sense.
 pub fn analyze(path: &str) -> Result<Vec<DominantColor>, String> {
+    Ok(select_dominants(&histogram(path, 8)?))
+}
+
+/// The image at `path` reduced to `colors` colors, as `(color, pixel count)`
+/// pairs, greys included.
+pub fn histogram(path: &str, colors: u32) -> Result<Vec<(Color, u32)>, String> {
+    let colors = colors.to_string();
     let output = Command::new("magick")
         .args([
             path,
             "-resize",
             "100x100",
             "-colors",
-            "8",
+            &colors,
             "-format",
             "%c",
             "histogram:info:",
@@ -62,7 +69,7 @@ pub fn analyze(path: &str) -> Result<Vec<DominantColor>, String> {
         return Err("no histogram output from magick".to_string());
     }
 
-    Ok(select_dominants(&parse_histogram(&stdout)))
+    Ok(parse_histogram(&stdout))
 }
 
 /// Parse `magick ... histogram:info:` stdout into `(color, count)` pairs.
@@ -104,7 +111,7 @@ fn parse_histogram(stdout: &str) -> Vec<(Color, u32)> {
 
 /// Apply the grey filter, normalize weights, sort descending, truncate to
 /// `TOP_N`.
-fn select_dominants(entries: &[(Color, u32)]) -> Vec<DominantColor> {
+pub fn select_dominants(entries: &[(Color, u32)]) -> Vec<DominantColor> {
     let filtered: Vec<(Color, u32)> = entries
         .iter()
         .copied()
@@ -0,0 +1,201 @@
+//! `cfg palette from-wallpaper`: build a complete palette from an image,
+//! pywal-style, using the Catppuccin color names so every template works
+//! with it unchanged.
+//!
+//! The background ramp (`base`, `mantle`, `crust`, the surfaces and
+//! overlays up to `text`) takes its hue from the image's most common color,
+//! kept close to grey. Each accent starts from its Catppuccin counterpart
+//! and has its hue pulled toward the nearest dominant color of the image.
+//! Text and accents are then nudged in lightness until they're readable on
+//! the generated `base`.
+
+use std::collections::BTreeMap;
+
+use super::analysis::{self, DominantColor};
+use crate::color::{Color, Oklch, WCAG_AA};
+use crate::palette_import;
+
+/// Colors the image is reduced to. More than the picker's 8, so smaller
+/// patches of color get a say in the accents.
+const HISTOGRAM_COLORS: u32 = 16;
+
+/// Accents and the Catppuccin colors they start from: mocha for dark
+/// palettes, latte for light ones.
+const ACCENTS: &[(&str, &str, &str)] = &[
+    ("rosewater", "f5e0dc", "dc8a78"),
+    ("flamingo", "f2cdcd", "dd7878"),
+    ("pink", "f5c2e7", "ea76cb"),
+    ("mauve", "cba6f7", "8839ef"),
+    ("red", "f38ba8", "d20f39"),
+    ("maroon", "eba0ac", "e64553"),
+    ("peach", "fab387", "fe640b"),
+    ("yellow", "f9e2af", "df8e1d"),
+    ("green", "a6e3a1", "40a02b"),
+    ("teal", "94e2d5", "179299"),
+    ("sky", "89dceb", "04a5e5"),
+    ("sapphire", "74c7ec", "209fb5"),
+    ("blue", "89b4fa", "1e66f5"),
+    ("lavender", "b4befe", "7287fd"),
+];
+
+/// An accent takes on an image hue at most this many degrees from its own.
+const HUE_REACH: f64 = 45.0;
+
+/// How far (0-1) an accent's hue moves toward the image hue. Less than all
+/// the way, so neighbouring accents (red, maroon, flamingo) stay apart.
+const HUE_PULL: f64 = 0.75;
+
+/// Contrast `text` keeps on `base` (WCAG AAA).
+const TEXT_CONTRAST: f64 = 7.0;
+
+/// Build a palette from the image at `path`: every name in
+/// `palette::NAMES`.
+pub fn palette(path: &str, light: bool) -> Result<BTreeMap<String, Color>, String> {
+    let histogram = analysis::histogram(path, HISTOGRAM_COLORS)?;
+    from_histogram(&histogram, light)
+}
+
+/// `palette` for an image already reduced to `(color, pixel count)` pairs.
+pub fn from_histogram(
+    histogram: &[(Color, u32)],
+    light: bool,
+) -> Result<BTreeMap<String, Color>, String> {
+    let (background, _) = histogram
+        .iter()
+        .max_by_key(|(_, count)| *count)
+        .ok_or_else(|| "the image has no colors".to_string())?;
+    let tint = background.to_oklch();
+
+    // Light backgrounds show a tint much more, so they get less of it
+    let base = Color::from_oklch(Oklch {
+        l: if light { 0.96 } else { 0.23 },
+        c: tint.c.min(if light { 0.012 } else { 0.03 }),
+        h: tint.h,
+    });
+    let text = Color::from_oklch(Oklch {
+        l: if light { 0.36 } else { 0.9 },
+        c: tint.c.min(0.02),
+        h: tint.h,
+    });
+    let mut colors = BTreeMap::from([
+        ("base".to_string(), base),
+        ("text".to_string(), readable(text, base, TEXT_CONTRAST)),
+    ]);
+
+    let dominants = analysis::select_dominants(histogram);
+    for (name, dark_hex, light_hex) in ACCENTS {
+        let reference = Color::from_hex(if light { light_hex } else { dark_hex })?;
+        let accent = pull_hue(reference.to_oklch(), &dominants);
+        colors.insert(
+            name.to_string(),
+            readable(Color::from_oklch(accent), base, WCAG_AA),
+        );
+    }
+
+    palette_import::derive_missing(&mut colors);
+    for name in ["subtext0", "subtext1"] {
+        let color = readable(colors[name], base, WCAG_AA);
+        colors.insert(name.to_string(), color);
+    }
+    Ok(colors)
+}
+
+/// `accent` with its hue moved toward the nearest dominant color's within
+/// `HUE_REACH`, if any.
+fn pull_hue(accent: Oklch, dominants: &[DominantColor]) -> Oklch {
+    let nearest = dominants
+        .iter()
+        .map(|d| hue_difference(accent.h, d.color.to_oklch().h))
+        .filter(|diff| diff.abs() <= HUE_REACH)
+        .min_by(|a, b| a.abs().total_cmp(&b.abs()));
+    match nearest {
+        Some(diff) => Oklch {
+            h: (accent.h + diff * HUE_PULL).rem_euclid(360.0),
+            ..accent
+        },
+        None => accent,
+    }
+}
+
+/// Signed shortest turn from hue `from` to hue `to`, -180 to 180 degrees.
+fn hue_difference(from: f64, to: f64) -> f64 {
+    (to - from + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// `color`, lightened (on a dark background) or darkened (on a light one)
+/// until it has `min_ratio` contrast on `background`.
+fn readable(color: Color, background: Color, min_ratio: f64) -> Color {
+    let step = if background.to_oklch().l < 0.5 {
+        0.01
+    } else {
+        -0.01
+    };
+    let mut lch = color.to_oklch();
+    let mut color = color;
+    while color.contrast_ratio(background) < min_ratio && (0.0..=1.0).contains(&lch.l) {
+        lch.l += step;
+        color = Color::from_oklch(Oklch {
+            l: lch.l.clamp(0.0, 1.0),
+            ..lch
+        });
+    }
+    color
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette;
+
+    fn histogram(entries: &[(&str, u32)]) -> Vec<(Color, u32)> {
+        entries
+            .iter()
+            .map(|(hex, count)| (Color::from_hex(hex).unwrap(), *count))
+            .collect()
+    }
+
+    #[test]
+    fn palettes_are_complete_and_readable() {
+        // Mostly dark teal water, an orange sunset and some grey rocks
+        let image = histogram(&[
+            ("0f3b3a", 500),
+            ("e8742a", 120),
+            ("6b6b6b", 200),
+            ("2f7f7a", 150),
+        ]);
+        for light in [false, true] {
+            let colors = from_histogram(&image, light).unwrap();
+            assert_eq!(colors.len(), palette::NAMES.len());
+            let base = colors["base"];
+            assert_eq!(base.to_oklch().l > 0.5, light);
+            assert!(colors["text"].contrast_ratio(base) >= TEXT_CONTRAST);
+            for (name, _, _) in ACCENTS {
+                assert!(colors[*name].contrast_ratio(base) >= WCAG_AA, "{}", name);
+            }
+            for name in ["subtext0", "subtext1"] {
+                assert!(colors[name].contrast_ratio(base) >= WCAG_AA, "{}", name);
+            }
+            // The background ramp is tinted toward the water, not neutral
+            let tint = base.to_oklch();
+            assert!(tint.c > 0.005 && hue_difference(tint.h, 190.0).abs() < 30.0);
+        }
+
+        // The orange pulls peach toward it; blue has nothing near and stays
+        let colors = from_histogram(&image, false).unwrap();
+        let orange = Color::from_hex("e8742a").unwrap().to_oklch().h;
+        let peach = Color::from_hex("fab387").unwrap().to_oklch().h;
+        let pulled = colors["peach"].to_oklch().h;
+        assert!(hue_difference(pulled, orange).abs() < hue_difference(peach, orange).abs());
+        let blue = Color::from_hex("89b4fa").unwrap().to_oklch().h;
+        assert!(hue_difference(colors["blue"].to_oklch().h, blue).abs() < 2.0);
+
+        assert!(from_histogram(&[], false).is_err());
+    }
+
+    #[test]
+    fn hue_difference_takes_the_short_way_round() {
+        assert_eq!(hue_difference(350.0, 10.0), 20.0);
+        assert_eq!(hue_difference(10.0, 350.0), -20.0);
+        assert_eq!(hue_difference(90.0, 90.0), 0.0);
+    }
+}
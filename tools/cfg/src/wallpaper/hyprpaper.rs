@@ -21,6 +21,16 @@ pub fn generate_config(entries: &[WallpaperEntry]) -> String {
     out
 }
 
+/// Image paths in hyprpaper.conf content written by `generate_config`, in
+/// monitor order.
+pub fn config_paths(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("path = "))
+        .map(|path| path.trim().to_string())
+        .collect()
+}
+
 /// Write hyprpaper.conf and restart the hyprpaper service.
 ///
 /// Uses atomic write (temp file + rename) to avoid truncated configs if
@@ -74,6 +84,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn config_paths_roundtrip() {
+        let entries = vec![
+            WallpaperEntry {
+                monitor: "DP-1".to_string(),
+                path: "/cache/left.jpg".to_string(),
+            },
+            WallpaperEntry {
+                monitor: "DP-2".to_string(),
+                path: "/cache/right.jpg".to_string(),
+            },
+        ];
+        assert_eq!(
+            config_paths(&generate_config(&entries)),
+            vec!["/cache/left.jpg", "/cache/right.jpg"]
+        );
+    }
+
     #[test]
     fn generate_config_multi_monitor() {
         let entries = vec![
@@ -1,4 +1,5 @@
 pub mod analysis;
+pub mod generate;
 pub mod hyprpaper;
 pub mod monitors;
 pub mod picker;
@@ -38,6 +39,25 @@ pub(crate) fn resolve_cache_dir(cfg: &WallpaperConfig) -> String {
     }
 }
 
+/// The image currently on screen: the pinned wallpaper, or else what cfg
+/// last handed hyprpaper (the processed copy for the first monitor).
+pub fn current(config: &Config) -> Result<String, String> {
+    let cfg = &config.wallpaper;
+    if cfg.mode == "pinned" && !cfg.path.trim().is_empty() {
+        return Ok(expand_tilde(&cfg.path));
+    }
+    let home = std::env::var("HOME").map_err(|_| "HOME not set".to_string())?;
+    let content = std::fs::read_to_string(format!("{}/.config/hypr/hyprpaper.conf", home))
+        .unwrap_or_default();
+    hyprpaper::config_paths(&content)
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+            "no current wallpaper (pass an image path, or apply one with cfg wallpaper --apply)"
+                .to_string()
+        })
+}
+
 /// Apply the wallpaper described by `cfg` across all detected monitors.
 ///
 /// Pipeline:
@@ -367,7 +367,7 @@ mod tests {
     }
 
     fn c(r: u8, g: u8, b: u8) -> Color {
-        Color { r, g, b }
+        Color { r, g, b, a: None }
     }
 
     fn dom(r: u8, g: u8, b: u8, weight: f32) -> DominantColor {
@@ -0,0 +1,474 @@
+//! `cfg update --watch`: re-render templates when their sources, partials,
+//! the registry, config or the active palette change on disk.
+
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::{CString, OsStr};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tera::Value;
+
+use crate::config::Config;
+use crate::deps;
+use crate::palette::Palette;
+use crate::render::build_context;
+use crate::templates::{load_engine, run_update, TemplatesFile, UpdateResult};
+
+/// Quiet period after the last event before an update runs, so an editor's
+/// write-rename-chmod burst or a `git checkout` triggers one update.
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Events that mean a file in a watched directory has new content (or is
+/// gone). Editors that save by renaming a temp file show up as `MOVED_TO`.
+const WATCH_MASK: u32 = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_DELETE;
+
+/// Directory watches on one inotify instance. Directories are watched
+/// rather than files so replaced files stay watched.
+pub struct Watcher {
+    fd: libc::c_int,
+    dirs: HashMap<libc::c_int, PathBuf>,
+}
+
+impl Watcher {
+    pub fn new() -> Result<Self, String> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(format!(
+                "Failed to start inotify: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(Watcher {
+            fd,
+            dirs: HashMap::new(),
+        })
+    }
+
+    /// Watch `dir`. Watching a directory again is a no-op.
+    pub fn watch_dir(&mut self, dir: &Path) -> Result<(), String> {
+        let path = CString::new(dir.as_os_str().as_bytes())
+            .map_err(|_| format!("Invalid path: {}", dir.display()))?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), WATCH_MASK) };
+        if wd < 0 {
+            return Err(format!(
+                "Failed to watch '{}': {}",
+                dir.display(),
+                std::io::Error::last_os_error()
+            ));
+        }
+        self.dirs.insert(wd, dir.to_path_buf());
+        Ok(())
+    }
+
+    /// Block until a file changes, then keep collecting until `quiet` passes
+    /// with no further events. Returns every changed path.
+    pub fn wait(&mut self, quiet: Duration) -> Result<BTreeSet<PathBuf>, String> {
+        let mut changed = BTreeSet::new();
+        let mut timeout = None;
+        while self.poll(timeout)? {
+            self.read_events(&mut changed)?;
+            if !changed.is_empty() {
+                timeout = Some(quiet);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Wait for the fd to become readable; `false` on timeout.
+    fn poll(&self, timeout: Option<Duration>) -> Result<bool, String> {
+        let mut pollfd = libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = timeout.map_or(-1, |t| t.as_millis() as libc::c_int);
+        loop {
+            let ready = unsafe { libc::poll(&mut pollfd, 1, timeout_ms) };
+            if ready >= 0 {
+                return Ok(ready > 0);
+            }
+            let err = std::io::Error::last_os_error();
+            if err.kind() != std::io::ErrorKind::Interrupted {
+                return Err(format!("Failed to wait for file changes: {}", err));
+            }
+        }
+    }
+
+    fn read_events(&self, changed: &mut BTreeSet<PathBuf>) -> Result<(), String> {
+        let mut buf = [0u8; 4096];
+        let len = unsafe { libc::read(self.fd, buf.as_mut_ptr().cast(), buf.len()) };
+        if len < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                return Ok(());
+            }
+            return Err(format!("Failed to read file changes: {}", err));
+        }
+
+        let header = std::mem::size_of::<libc::inotify_event>();
+        let mut offset = 0;
+        while offset + header <= len as usize {
+            let event: libc::inotify_event =
+                unsafe { std::ptr::read_unaligned(buf[offset..].as_ptr().cast()) };
+            let name = &buf[offset + header..offset + header + event.len as usize];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            if let (Some(dir), false) = (self.dirs.get(&event.wd), name.is_empty()) {
+                changed.insert(dir.join(OsStr::from_bytes(name)));
+            }
+            offset += header + event.len as usize;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// What a round of the watch loop compares against: the registry, the
+/// rendering context and which files and variables each template reads.
+struct State {
+    templates: TemplatesFile,
+    context: Value,
+    /// Files each registered template reads: its own source plus the
+    /// partials and templates it includes, extends or imports
+    sources: HashMap<String, BTreeSet<PathBuf>>,
+    vars: HashMap<String, BTreeSet<String>>,
+}
+
+impl State {
+    fn load(cfg_dir: &str, dotfiles_dir: &str) -> Result<Self, String> {
+        let config = Config::load_or_default(&format!("{}/config.toml", cfg_dir))?;
+        let palette_path = format!("{}/palettes/{}.toml", cfg_dir, config.flavor);
+        let palette =
+            Palette::load(&palette_path).map_err(|e| format!("Error loading palette: {}", e))?;
+        let templates = TemplatesFile::load(&format!("{}/templates.toml", cfg_dir))?;
+        let engine = load_engine(cfg_dir, dotfiles_dir, &templates)?;
+
+        let mut sources = HashMap::new();
+        let mut vars = HashMap::new();
+        for (name, tpl) in &templates.templates {
+            let (files, read) = match engine.references(&tpl.path) {
+                Ok(refs) => (
+                    refs.templates
+                        .iter()
+                        .map(|t| engine_file(cfg_dir, dotfiles_dir, t))
+                        .collect(),
+                    refs.variables
+                        .iter()
+                        .map(|r| deps::normalize(&r.ident))
+                        .collect(),
+                ),
+                // Not loadable yet; fixing the file is what triggers it.
+                Err(_) => (
+                    BTreeSet::from([engine_file(cfg_dir, dotfiles_dir, &tpl.path)]),
+                    BTreeSet::new(),
+                ),
+            };
+            sources.insert(name.clone(), files);
+            vars.insert(name.clone(), read);
+        }
+
+        Ok(State {
+            templates,
+            context: build_context(&config, &palette).into_json(),
+            sources,
+            vars,
+        })
+    }
+
+    /// Directories to watch: `cfg_dir` (config and registry), its palettes
+    /// and the parent of every template source.
+    fn dirs(&self, cfg_dir: &str) -> BTreeSet<PathBuf> {
+        let mut dirs = BTreeSet::from([
+            PathBuf::from(cfg_dir),
+            PathBuf::from(format!("{}/palettes", cfg_dir)),
+        ]);
+        for files in self.sources.values() {
+            dirs.extend(
+                files
+                    .iter()
+                    .filter_map(|f| f.parent().map(Path::to_path_buf)),
+            );
+        }
+        dirs
+    }
+}
+
+/// File behind an engine template name: partials live in the cfg dir,
+/// registered templates in the dotfiles dir.
+fn engine_file(cfg_dir: &str, dotfiles_dir: &str, name: &str) -> PathBuf {
+    let root = if name.starts_with(&format!("{}/", crate::render::PARTIALS_DIR)) {
+        cfg_dir
+    } else {
+        dotfiles_dir
+    };
+    PathBuf::from(format!("{}/{}", root, name))
+}
+
+/// Templates to re-render going from `prev` to `next` after `changed` files:
+/// new or edited registry entries, templates reading a context value that
+/// changed (config or palette edits), and templates whose source files changed.
+fn affected(prev: &State, next: &State, changed: &BTreeSet<PathBuf>) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+
+    for (name, tpl) in &next.templates.templates {
+        if prev.templates.get(name) != Some(tpl) {
+            names.insert(name.clone());
+        }
+    }
+
+    let empty = serde_json::Map::new();
+    let prev_context = prev.context.as_object().unwrap_or(&empty);
+    let next_context = next.context.as_object().unwrap_or(&empty);
+    let changed_keys: Vec<&String> = prev_context
+        .keys()
+        .chain(next_context.keys())
+        .filter(|key| prev_context.get(*key) != next_context.get(*key))
+        .collect();
+    for (name, vars) in &next.vars {
+        if changed_keys.iter().any(|key| deps::uses(vars, key)) {
+            names.insert(name.clone());
+        }
+    }
+
+    for (name, files) in &next.sources {
+        let before = prev.sources.get(name);
+        if files
+            .iter()
+            .chain(before.into_iter().flatten())
+            .any(|file| changed.contains(file))
+        {
+            names.insert(name.clone());
+        }
+    }
+    names
+}
+
+/// A running watch: the inotify watches plus the state the next round of
+/// changes is compared against.
+pub struct Watch {
+    cfg_dir: String,
+    dotfiles_dir: String,
+    state: State,
+    watcher: Watcher,
+}
+
+/// Load the registry and start watching everything it reads. Changes made
+/// after this returns are picked up by `Watch::run`.
+pub fn start(cfg_dir: &str, dotfiles_dir: &str) -> Result<Watch, String> {
+    let state = State::load(cfg_dir, dotfiles_dir)?;
+    let mut watcher = Watcher::new()?;
+    for dir in state.dirs(cfg_dir) {
+        // A template's directory may not exist yet; lint reports that.
+        let _ = watcher.watch_dir(&dir);
+    }
+    Ok(Watch {
+        cfg_dir: cfg_dir.to_string(),
+        dotfiles_dir: dotfiles_dir.to_string(),
+        state,
+        watcher,
+    })
+}
+
+impl Watch {
+    /// Number of registered templates being watched
+    pub fn len(&self) -> usize {
+        self.state.templates.templates.len()
+    }
+
+    /// Watch until interrupted, running `cfg update` for the affected
+    /// templates after each burst of changes. Each update's result (or a
+    /// failure to reload the config, palette or registry) goes to `report`;
+    /// the loop keeps going.
+    pub fn run(
+        mut self,
+        mut report: impl FnMut(Result<UpdateResult, String>),
+    ) -> Result<(), String> {
+        // Changes seen while the tree didn't load carry over to the next round.
+        let mut pending = BTreeSet::new();
+        loop {
+            pending.extend(self.watcher.wait(DEBOUNCE)?);
+            let next = match State::load(&self.cfg_dir, &self.dotfiles_dir) {
+                Ok(next) => next,
+                Err(e) => {
+                    report(Err(e));
+                    continue;
+                }
+            };
+            let names: Vec<String> = affected(&self.state, &next, &pending).into_iter().collect();
+            pending.clear();
+            for dir in next.dirs(&self.cfg_dir) {
+                let _ = self.watcher.watch_dir(&dir);
+            }
+            self.state = next;
+
+            if !names.is_empty() {
+                report(run_update(&self.cfg_dir, &self.dotfiles_dir, &names));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cfg-watch-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(root: &Path, rel: &str, content: &str) {
+        let path = root.join(rel);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    /// cfg dir and dotfiles dir in one root: `kitty` reads blue and a
+    /// partial, `waybar` reads mauve and the mono font.
+    fn fixture(name: &str) -> (PathBuf, String, String) {
+        let root = temp_dir(name);
+        write(
+            &root,
+            "cfg/config.toml",
+            "flavor = \"mocha\"\nprimary = \"blue\"\n",
+        );
+        write(
+            &root,
+            "cfg/palettes/mocha.toml",
+            "[colors]\nblue = \"89b4fa\"\nmauve = \"cba6f7\"\n",
+        );
+        write(&root, "cfg/partials/accent.tera", "{{ blue | hex }}");
+        write(
+            &root,
+            "cfg/templates.toml",
+            "[templates]\n\
+             kitty = { path = \"kitty/kitty.conf.tera\" }\n\
+             waybar = { path = \"waybar/style.css.tera\" }\n",
+        );
+        write(
+            &root,
+            "kitty/kitty.conf.tera",
+            "{% include \"partials/accent.tera\" %}",
+        );
+        write(
+            &root,
+            "waybar/style.css.tera",
+            "{{ mauve | hex }} {{ fonts.mono }}",
+        );
+        let cfg_dir = root.join("cfg").display().to_string();
+        let dotfiles_dir = root.display().to_string();
+        (root, cfg_dir, dotfiles_dir)
+    }
+
+    fn affected_by(
+        cfg_dir: &str,
+        dotfiles_dir: &str,
+        edit: impl FnOnce(),
+        changed: &[String],
+    ) -> Vec<String> {
+        let prev = State::load(cfg_dir, dotfiles_dir).unwrap();
+        edit();
+        let next = State::load(cfg_dir, dotfiles_dir).unwrap();
+        let changed = changed.iter().map(PathBuf::from).collect();
+        affected(&prev, &next, &changed).into_iter().collect()
+    }
+
+    #[test]
+    fn watcher_reports_written_files() {
+        let dir = temp_dir("inotify");
+        let mut watcher = Watcher::new().unwrap();
+        watcher.watch_dir(&dir).unwrap();
+
+        let file = dir.join("kitty.conf.tera");
+        let writer = {
+            let file = file.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                std::fs::write(&file, "a").unwrap();
+                std::fs::write(&file, "b").unwrap();
+            })
+        };
+        let changed = watcher.wait(Duration::from_millis(200)).unwrap();
+        writer.join().unwrap();
+
+        assert_eq!(changed, BTreeSet::from([file]));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn source_and_partial_edits_affect_their_templates() {
+        let (root, cfg_dir, dotfiles_dir) = fixture("sources");
+
+        let partial = format!("{}/partials/accent.tera", cfg_dir);
+        assert_eq!(
+            affected_by(&cfg_dir, &dotfiles_dir, || {}, &[partial]),
+            vec!["kitty"]
+        );
+        let source = format!("{}/waybar/style.css.tera", dotfiles_dir);
+        assert_eq!(
+            affected_by(&cfg_dir, &dotfiles_dir, || {}, &[source]),
+            vec!["waybar"]
+        );
+        let output = format!("{}/waybar/style.css", dotfiles_dir);
+        assert!(affected_by(&cfg_dir, &dotfiles_dir, || {}, &[output]).is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn config_and_palette_edits_affect_readers_of_changed_values() {
+        let (root, cfg_dir, dotfiles_dir) = fixture("context");
+
+        let palette = || {
+            write(
+                &root,
+                "cfg/palettes/mocha.toml",
+                "[colors]\nblue = \"89b4fa\"\nmauve = \"ff0000\"\n",
+            )
+        };
+        assert_eq!(
+            affected_by(&cfg_dir, &dotfiles_dir, palette, &[]),
+            vec!["waybar"]
+        );
+
+        let font = || {
+            write(
+                &root,
+                "cfg/config.toml",
+                "flavor = \"mocha\"\nprimary = \"blue\"\n[fonts]\nmono = \"Iosevka\"\n",
+            )
+        };
+        assert_eq!(
+            affected_by(&cfg_dir, &dotfiles_dir, font, &[]),
+            vec!["waybar"]
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn registry_edits_affect_new_and_changed_entries() {
+        let (root, cfg_dir, dotfiles_dir) = fixture("registry");
+        let edit = || {
+            write(
+                &root,
+                "cfg/templates.toml",
+                "[templates]\n\
+                 kitty = { path = \"kitty/kitty.conf.tera\", reload = \"true\" }\n\
+                 waybar = { path = \"waybar/style.css.tera\" }\n",
+            )
+        };
+        assert_eq!(
+            affected_by(&cfg_dir, &dotfiles_dir, edit, &[]),
+            vec!["kitty"]
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -205,6 +205,1094 @@ fn update_dry_run_leds_virtual_target_accepted() {
         .stdout(predicate::str::contains("[dry-run] leds"));
 }
 
+// =============================================================================
+// UPDATE command - transactional writes and rollback
+// =============================================================================
+
+/// Build an isolated CFG_DIR + DOTFILES_DIR pair with a minimal palette and
+/// one `templates.toml` entry per `(name, template body)`. Template `name`
+/// lives at `<dotfiles>/<name>.conf.tera` and renders to `<name>.conf`.
+fn template_fixture(test: &str, templates: &[(&str, &str)]) -> (String, String) {
+    let cfg_dir = isolated_cfg_dir(&format!("{}-cfg", test));
+    let dotfiles_dir = isolated_cfg_dir(&format!("{}-dotfiles", test));
+
+    std::fs::create_dir_all(format!("{}/palettes", cfg_dir)).unwrap();
+    std::fs::write(
+        format!("{}/palettes/mocha.toml", cfg_dir),
+        "[colors]\nblue = \"89b4fa\"\nmauve = \"cba6f7\"\nbase = \"1e1e2e\"\n",
+    )
+    .unwrap();
+
+    let mut registry = String::from("[templates]\n");
+    for (name, body) in templates {
+        registry.push_str(&format!("{} = {{ path = \"{}.conf.tera\" }}\n", name, name));
+        std::fs::write(format!("{}/{}.conf.tera", dotfiles_dir, name), body).unwrap();
+    }
+    std::fs::write(format!("{}/templates.toml", cfg_dir), registry).unwrap();
+
+    (cfg_dir, dotfiles_dir)
+}
+
+#[test]
+fn update_render_failure_writes_nothing() {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        "update-render-failure",
+        &[
+            ("good", "color={{ blue | hex }}"),
+            ("bad", "{{ missing_var }}"),
+        ],
+    );
+    let good_output = format!("{}/good.conf", dotfiles_dir);
+    std::fs::write(&good_output, "old\n").unwrap();
+
+    let output = cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "good", "bad"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(stderr.contains("bad - error"), "stderr was: {}", stderr);
+    assert!(
+        stderr.contains("good - error: not written"),
+        "stderr was: {}",
+        stderr
+    );
+    assert_eq!(std::fs::read_to_string(&good_output).unwrap(), "old\n");
+}
+
+#[test]
+fn update_rollback_restores_previous_outputs() {
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("update-rollback", &[("kitty", "color={{ blue | hex }}")]);
+    let kitty_output = format!("{}/kitty.conf", dotfiles_dir);
+    std::fs::write(&kitty_output, "old\n").unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "kitty"])
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(&kitty_output).unwrap(),
+        "color=89b4fa\n"
+    );
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--rollback"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("kitty"));
+    assert_eq!(std::fs::read_to_string(&kitty_output).unwrap(), "old\n");
+}
+
+#[test]
+fn update_rollback_without_snapshot_errors() {
+    let dir = isolated_cfg_dir("rollback-no-snapshot");
+    cfg()
+        .env("CFG_DIR", &dir)
+        .args(["update", "--rollback"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no snapshot"));
+}
+
+#[test]
+fn update_rollback_rejects_names_and_dry_run() {
+    cfg()
+        .args(["update", "--rollback", "mako"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    cfg()
+        .args(["update", "--rollback", "--dry-run"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn update_watch_rejects_names_and_dry_run() {
+    cfg()
+        .args(["update", "--watch", "mako"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    cfg()
+        .args(["update", "--watch", "--dry-run"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn update_watch_rerenders_on_palette_change() {
+    use std::io::BufRead;
+
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("update-watch", &[("app", "color={{ blue | hex }}\n")]);
+    let output = format!("{}/app.conf", dotfiles_dir);
+
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_cfg"))
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--watch"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = std::io::BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert!(line.starts_with("Watching"), "got {:?}", line);
+
+    std::fs::write(
+        format!("{}/palettes/mocha.toml", cfg_dir),
+        "[colors]\nblue = \"000000\"\nmauve = \"cba6f7\"\nbase = \"1e1e2e\"\n",
+    )
+    .unwrap();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while std::fs::read_to_string(&output).ok().as_deref() != Some("color=000000\n")
+        && std::time::Instant::now() < deadline
+    {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "color=000000\n");
+}
+
+#[test]
+fn update_json_reports_templates_and_reloads() {
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("update-json", &[("app", "color={{ blue | hex }}\n")]);
+    std::fs::write(
+        format!("{}/templates.toml", cfg_dir),
+        "[templates]\napp = { path = \"app.conf.tera\", reload = \"true\" }\n",
+    )
+    .unwrap();
+
+    let output = cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "app", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(report["status"], "ok");
+    assert_eq!(report["templates"][0]["name"], "app");
+    assert_eq!(report["templates"][0]["status"], "written");
+    assert_eq!(
+        report["templates"][0]["output"],
+        format!("{}/app.conf", dotfiles_dir)
+    );
+    assert!(report["templates"][0]["duration_ms"].is_number());
+    assert_eq!(report["reloads"][0]["command"], "true");
+    assert_eq!(report["reloads"][0]["templates"][0], "app");
+    assert_eq!(report["reloads"][0]["exit_code"], 0);
+    assert!(report["leds"].is_null());
+}
+
+#[test]
+fn update_exit_codes_distinguish_render_and_reload_errors() {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        "update-exit-codes",
+        &[("good", "color={{ blue | hex }}\n"), ("bad", "{{ nope }}")],
+    );
+    std::fs::write(
+        format!("{}/templates.toml", cfg_dir),
+        "[templates]\n\
+         good = { path = \"good.conf.tera\", reload = \"exit 7\" }\n\
+         bad = { path = \"bad.conf.tera\" }\n",
+    )
+    .unwrap();
+
+    let output = cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "good", "bad", "--json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["status"], "render_error");
+    assert_eq!(report["templates"][0]["status"], "error");
+    assert!(report["templates"][0]["error"].is_string());
+
+    let output = cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "good", "--json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["status"], "reload_error");
+    assert_eq!(report["reloads"][0]["status"], "error");
+    assert_eq!(report["reloads"][0]["exit_code"], 7);
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "bad"])
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn update_json_reports_fatal_errors() {
+    let (cfg_dir, dotfiles_dir) = template_fixture("update-json-fatal", &[]);
+
+    let output = cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "missing", "--json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["status"], "error");
+    assert_eq!(report["error"], "Unknown template: missing");
+}
+
+#[test]
+fn update_applies_per_template_overrides_and_vars() {
+    let body = "{{ primary | hex }} {{ primary_name }}{% if gaps %} gaps={{ gaps }}{% endif %}\n";
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("update-overrides", &[("plain", body), ("lock", body)]);
+    std::fs::write(
+        format!("{}/templates.toml", cfg_dir),
+        r#"[templates]
+plain = { path = "plain.conf.tera" }
+lock = { path = "lock.conf.tera", overrides = { primary = "mauve" }, vars = { gaps = 8 } }
+"#,
+    )
+    .unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update"])
+        .assert()
+        .success();
+
+    let read =
+        |name: &str| std::fs::read_to_string(format!("{}/{}.conf", dotfiles_dir, name)).unwrap();
+    assert_eq!(read("plain"), "89b4fa blue\n");
+    assert_eq!(read("lock"), "cba6f7 mauve gaps=8\n");
+}
+
+#[test]
+fn update_rejects_override_of_unknown_variable() {
+    let (cfg_dir, dotfiles_dir) = template_fixture("update-override-typo", &[("a", "x")]);
+    std::fs::write(
+        format!("{}/templates.toml", cfg_dir),
+        "[templates]\na = { path = \"a.conf.tera\", overrides = { primray = \"mauve\" } }\n",
+    )
+    .unwrap();
+
+    let output = cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(
+        stderr.contains("override 'primray' is not in the context"),
+        "stderr was: {}",
+        stderr
+    );
+}
+
+fn tagged_fixture(test: &str) -> (String, String) {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        test,
+        &[("gtk3", "a"), ("gtk4", "b"), ("qt6ct", "c"), ("kitty", "d")],
+    );
+    std::fs::write(
+        format!("{}/templates.toml", cfg_dir),
+        r#"[templates]
+gtk3 = { path = "gtk3.conf.tera", tags = ["gtk"] }
+gtk4 = { path = "gtk4.conf.tera", tags = ["gtk"] }
+qt6ct = { path = "qt6ct.conf.tera", tags = ["qt"] }
+kitty = { path = "kitty.conf.tera" }
+
+[groups]
+toolkits = ["@gtk", "@qt"]
+"#,
+    )
+    .unwrap();
+    (cfg_dir, dotfiles_dir)
+}
+
+#[test]
+fn update_expands_tags_and_groups() {
+    let (cfg_dir, dotfiles_dir) = tagged_fixture("update-tags");
+    let written = |names: &[&str]| {
+        names
+            .iter()
+            .map(|n| std::path::Path::new(&format!("{}/{}.conf", dotfiles_dir, n)).exists())
+            .collect::<Vec<_>>()
+    };
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "@gtk"])
+        .assert()
+        .success();
+    assert_eq!(
+        written(&["gtk3", "gtk4", "qt6ct", "kitty"]),
+        [true, true, false, false]
+    );
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "kitty", "--tag", "qt"])
+        .assert()
+        .success();
+    assert_eq!(written(&["qt6ct", "kitty"]), [true, true]);
+}
+
+#[test]
+fn update_dry_run_expands_groups_and_rejects_unknown_tags() {
+    let (cfg_dir, dotfiles_dir) = tagged_fixture("update-tags-dry-run");
+
+    let output = cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--dry-run", "@toolkits"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    for name in ["gtk3", "gtk4", "qt6ct"] {
+        assert!(stdout.contains(name), "stdout was: {}", stdout);
+    }
+    assert!(!stdout.contains("kitty"), "stdout was: {}", stdout);
+
+    let output = cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--tag", "kde"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown tag or group: @kde"));
+}
+
+#[test]
+fn update_list_prints_tags_and_groups() {
+    let (cfg_dir, dotfiles_dir) = tagged_fixture("update-list-tags");
+
+    let output = cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--list"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        stdout.lines().any(|l| l == "gtk3   @gtk"),
+        "stdout was: {}",
+        stdout
+    );
+    assert!(
+        stdout.lines().any(|l| l == "kitty"),
+        "stdout was: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Groups:\n  @toolkits  gtk3, gtk4, qt6ct\n"),
+        "stdout was: {}",
+        stdout
+    );
+}
+
+#[test]
+fn update_skips_unchanged_outputs_and_their_reloads() {
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("update-unchanged", &[("app", "color={{ blue | hex }}\n")]);
+    let marker = format!("{}/reloads", dotfiles_dir);
+    std::fs::write(
+        format!("{}/templates.toml", cfg_dir),
+        format!(
+            "[templates]\napp = {{ path = \"app.conf.tera\", reload = \"echo reload >> {}\" }}\n",
+            marker
+        ),
+    )
+    .unwrap();
+    let output = format!("{}/app.conf", dotfiles_dir);
+    std::fs::write(&output, "old\n").unwrap();
+
+    let update = || {
+        cfg()
+            .env("CFG_DIR", &cfg_dir)
+            .env("DOTFILES_DIR", &dotfiles_dir)
+            .args(["update", "app"])
+            .assert()
+            .success()
+    };
+    update().stdout(predicate::str::contains("app  →"));
+    update().stdout(predicate::str::contains("app  unchanged"));
+
+    assert_eq!(std::fs::read_to_string(&marker).unwrap(), "reload\n");
+
+    // The no-op update keeps the first update's snapshot for --rollback.
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--rollback"])
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "old\n");
+}
+
+#[test]
+fn update_writes_output_override_with_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("update-output-override", &[("script", "#!/bin/sh\n")]);
+    let target = format!("{}/bin/set-folders", cfg_dir);
+    std::fs::write(
+        format!("{}/templates.toml", cfg_dir),
+        format!(
+            "[templates]\nscript = {{ path = \"script.conf.tera\", output = \"{}\", mode = \"0755\" }}\n",
+            target
+        ),
+    )
+    .unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--dry-run", "script"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "[dry-run] script  →  {}",
+            target
+        )));
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "script"])
+        .assert()
+        .success();
+
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "#!/bin/sh\n");
+    let mode = std::fs::metadata(&target).unwrap().permissions().mode() & 0o7777;
+    assert_eq!(mode, 0o755);
+    assert!(!std::path::Path::new(&format!("{}/script.conf", dotfiles_dir)).exists());
+}
+
+#[test]
+fn update_validation_failure_leaves_old_file() {
+    let (cfg_dir, dotfiles_dir) = template_fixture("update-validate", &[]);
+    std::fs::write(
+        format!("{}/bar.json.tera", dotfiles_dir),
+        "{\"color\": \"{{ blue | hex }}\",}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        format!("{}/app.conf.tera", dotfiles_dir),
+        "color={{ blue | hex }}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        format!("{}/templates.toml", cfg_dir),
+        "[templates]\n\
+         bar = { path = \"bar.json.tera\" }\n\
+         app = { path = \"app.conf.tera\", validate = \"grep -q '^color=' {}\" }\n",
+    )
+    .unwrap();
+    let bar_output = format!("{}/bar.json", dotfiles_dir);
+    std::fs::write(&bar_output, "{}\n").unwrap();
+
+    let output = cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "bar", "app"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("bar - error: invalid json"), "{}", stderr);
+    assert_eq!(std::fs::read_to_string(&bar_output).unwrap(), "{}\n");
+    assert!(!std::path::Path::new(&format!("{}/app.conf", dotfiles_dir)).exists());
+
+    // A passing custom validator lets the template through.
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "app"])
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(format!("{}/app.conf", dotfiles_dir)).unwrap(),
+        "color=89b4fa\n"
+    );
+}
+
+#[test]
+fn update_list_uses_filters_by_context_variable() {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        "update-list-uses",
+        &[
+            (
+                "kitty",
+                "font={{ fonts.mono }}\ncolor={{ primary | hex }}\n",
+            ),
+            ("foot", "font={{ font_mono }}\n"),
+            ("mako", "bg={{ base | hex }}\n"),
+        ],
+    );
+
+    let output = cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--list", "--uses", "fonts.mono"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "foot\nkitty\n");
+}
+
+#[test]
+fn update_affected_by_renders_only_dependent_templates() {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        "update-affected-by",
+        &[
+            ("kitty", "font={{ fonts.mono }}\n"),
+            ("mako", "bg={{ base | hex }}\n"),
+        ],
+    );
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--affected-by", "fonts.mono", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[dry-run] kitty"))
+        .stdout(predicate::str::contains("mako").not())
+        .stdout(predicate::str::contains("[dry-run] leds").not());
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--affected-by", "wallpaper.path"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No templates use wallpaper.path"));
+}
+
+#[test]
+fn update_uses_requires_list() {
+    cfg()
+        .args(["update", "--uses", "primary"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--list"));
+    cfg()
+        .args(["update", "--affected-by", "primary", "mako"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn update_dry_run_diff_shows_changed_lines() {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        "update-dry-run-diff",
+        &[
+            ("changed", "# header\ncolor={{ blue | hex }}\n"),
+            ("same", "accent={{ mauve | hex }}\n"),
+        ],
+    );
+    let changed_output = format!("{}/changed.conf", dotfiles_dir);
+    std::fs::write(&changed_output, "# header\ncolor=#000000\n").unwrap();
+    std::fs::write(format!("{}/same.conf", dotfiles_dir), "accent=cba6f7\n").unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--dry-run", "--diff", "changed", "same"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "--- {}\n",
+            changed_output
+        )))
+        .stdout(predicate::str::contains(
+            " # header\n-color=#000000\n+color=89b4fa\n",
+        ))
+        .stdout(predicate::str::contains("same.conf").not());
+
+    assert_eq!(
+        std::fs::read_to_string(&changed_output).unwrap(),
+        "# header\ncolor=#000000\n"
+    );
+}
+
+#[test]
+fn update_dry_run_stat_lists_only_changed_outputs() {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        "update-dry-run-stat",
+        &[
+            ("changed", "color={{ blue | hex }}\n"),
+            ("same", "accent={{ mauve | hex }}\n"),
+            ("fresh", "base={{ base | hex }}\nblue={{ blue | hex }}\n"),
+        ],
+    );
+    std::fs::write(format!("{}/changed.conf", dotfiles_dir), "color=#000000\n").unwrap();
+    std::fs::write(format!("{}/same.conf", dotfiles_dir), "accent=cba6f7\n").unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["update", "--dry-run", "--stat", "changed", "same", "fresh"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("changed  →").and(predicate::str::contains("(+1 -1)")))
+        .stdout(predicate::str::contains("fresh  →").and(predicate::str::contains("(+2 -0)")))
+        .stdout(predicate::str::contains("same  →").not())
+        .stdout(predicate::str::contains("2 of 3 templates would change"));
+    assert!(!std::path::Path::new(&format!("{}/fresh.conf", dotfiles_dir)).exists());
+}
+
+#[test]
+fn update_diff_requires_dry_run() {
+    cfg()
+        .args(["update", "--diff"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--dry-run"));
+    cfg()
+        .args(["update", "--dry-run", "--diff", "--stat"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+// =============================================================================
+// RENDER command tests
+// =============================================================================
+
+#[test]
+fn render_prints_unregistered_template_to_stdout() {
+    let (cfg_dir, dotfiles_dir) = template_fixture("render-stdout", &[]);
+    std::fs::create_dir_all(format!("{}/partials", cfg_dir)).unwrap();
+    std::fs::write(
+        format!("{}/partials/accent.tera", cfg_dir),
+        "accent={{ blue | hex }}",
+    )
+    .unwrap();
+    let template = format!("{}/proto.conf.tera", cfg_dir);
+    std::fs::write(
+        &template,
+        "{{ flavor }} {% include \"partials/accent.tera\" %} gaps={{ gaps }}\n",
+    )
+    .unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["render", &template, "--set", "gaps=8"])
+        .assert()
+        .success()
+        .stdout("mocha accent=89b4fa gaps=8\n");
+    assert!(!std::path::Path::new(&format!("{}/proto.conf", cfg_dir)).exists());
+}
+
+#[test]
+fn render_perceptual_color_filters() {
+    let (cfg_dir, dotfiles_dir) = template_fixture("render-perceptual", &[]);
+    let template = format!("{}/states.tera", dotfiles_dir);
+    std::fs::write(
+        &template,
+        "{{ blue | lighten_l(amount=8) | hex }} \
+         {{ blue | desaturate(amount=100) | hex }} \
+         {{ blue | with_chroma(chroma=0) | hex }} \
+         {{ blue | rotate_hue(degrees=360) | saturate(amount=0) | hex }} \
+         {{ blue | mix_oklab(base=base, amount=100) | hex }}",
+    )
+    .unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["render", &template])
+        .assert()
+        .success()
+        .stdout("b0ceff c2c2c2 b3b3b3 89b4fa 89b4fa\n");
+
+    std::fs::write(&template, "{{ blue | rotate_hue }}").unwrap();
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["render", &template])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "rotate_hue: missing 'degrees' argument",
+        ));
+}
+
+#[test]
+fn render_alpha_composes_with_formatters() {
+    let (cfg_dir, dotfiles_dir) = template_fixture("render-alpha", &[]);
+    // Old-style bare hex keeps working next to #rrggbbaa and CSS notations
+    std::fs::write(
+        format!("{}/palettes/mocha.toml", cfg_dir),
+        "[colors]\nblue = \"89b4fa\"\nbase = \"#1e1e2e\"\n\
+         overlay = \"#6c708680\"\nsurface = \"hsl(237 16% 23%)\"\n",
+    )
+    .unwrap();
+    let template = format!("{}/alpha.tera", dotfiles_dir);
+    std::fs::write(
+        &template,
+        "{{ blue | hex }} {{ overlay | hex }} {{ surface | hex }}\n\
+         #{{ base | alpha(value=0.8) | hex }} \
+         {{ blue | alpha(value=0.5) | rgb_css }} \
+         {{ blue | alpha(value=0.5) | hyprlang }} \
+         {{ blue | alpha(value=0.5) | hex_argb }} \
+         {{ overlay | rgba_css }} {{ blue | rgba_css(alpha=0.9) }} \
+         {{ blue | alpha(value=0.5) | lighten(amount=0) | upper }} \
+         {{ tint | hyprlang }}",
+    )
+    .unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["render", &template, "--set", "tint=rgb(0 0 0 / 25%)"])
+        .assert()
+        .success()
+        .stdout(
+            "89b4fa 6c708680 313244\n\
+             #1e1e2ecc rgba(137, 180, 250, 0.5) rgba(89b4fa80) #8089b4fa \
+             rgba(108, 112, 134, 0.502) rgba(137, 180, 250, 0.9) 89B4FA80 \
+             rgba(00000040)\n",
+        );
+
+    std::fs::write(&template, "{{ blue | alpha(value=2) | hex }}").unwrap();
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["render", &template])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "alpha: value must be between 0 and 1",
+        ));
+}
+
+#[test]
+fn render_contrast_and_readable_on() {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        "render-contrast",
+        &[("bar", "{{ blue | contrast(with=base) | hex }}")],
+    );
+    std::fs::write(
+        format!("{}/palettes/mocha.toml", cfg_dir),
+        "[colors]\nblue = \"89b4fa\"\nbase = \"1e1e2e\"\ntext = \"cdd6f4\"\n\
+         crust = \"11111b\"\nyellow = \"f9e2af\"\n",
+    )
+    .unwrap();
+    let template = format!("{}/contrast.tera", dotfiles_dir);
+    std::fs::write(
+        &template,
+        "{{ blue | contrast(with=base) }} {{ base | contrast(with=blue) }} \
+         {{ text | contrast(with=base, method=\"apca\") }} \
+         {{ blue | readable_on(candidates=[text, base, crust]) | hex }} \
+         {{ base | readable_on(candidates=[crust, yellow, text]) | hex }} \
+         {{ blue | readable_on(candidates=[text], min=21) | hex }} \
+         {% if blue | contrast(with=base) >= 4.5 %}aa{% endif %}",
+    )
+    .unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["render", &template])
+        .assert()
+        .success()
+        .stdout("7.79 7.79 -80 1e1e2e f9e2af cdd6f4 aa\n");
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .arg("lint")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "error: bar: filter 'hex' expects a color but 'blue' is a number here",
+        ));
+}
+
+#[test]
+fn render_flavor_and_color_overrides() {
+    let (cfg_dir, dotfiles_dir) = template_fixture("render-flavor", &[]);
+    std::fs::write(
+        format!("{}/palettes/latte.toml", cfg_dir),
+        "[colors]\nblue = \"1e66f5\"\nbase = \"eff1f5\"\n",
+    )
+    .unwrap();
+    let template = format!("{}/colors.tera", dotfiles_dir);
+    std::fs::write(&template, "{{ blue | hex }} {{ base | hex }}").unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args([
+            "render",
+            &template,
+            "--flavor",
+            "latte",
+            "--set",
+            "base=#000000",
+        ])
+        .assert()
+        .success()
+        .stdout("1e66f5 000000\n");
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["render", &template, "--flavor", "nope"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("palette"));
+}
+
+#[test]
+fn render_missing_template_errors() {
+    // No palettes either: the missing template is reported first
+    let dir = isolated_cfg_dir("render-missing");
+    cfg()
+        .env("CFG_DIR", &dir)
+        .env("DOTFILES_DIR", &dir)
+        .args(["render", "/nonexistent/x.tera"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("template not found"));
+}
+
+// =============================================================================
+// LINT command tests
+// =============================================================================
+
+#[test]
+fn lint_clean_registry_succeeds() {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        "lint-clean",
+        &[(
+            "kitty",
+            "{{ blue | hex }} {{ mauve_name | ansi }} {{ base | rgb_css }}",
+        )],
+    );
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .arg("lint")
+        .assert()
+        .success()
+        .stdout("No problems found\n");
+}
+
+#[test]
+fn lint_reports_errors_and_fails() {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        "lint-errors",
+        &[(
+            "kitty",
+            "{{ overlay3 | hex }} {{ blue | ansi }} {{ mauve | shout }} {{ base | hex }}",
+        )],
+    );
+    std::fs::write(format!("{}/stray.conf.tera", dotfiles_dir), "").unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .arg("lint")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "error: kitty: undefined variable 'overlay3'",
+        ))
+        .stdout(predicate::str::contains(
+            "error: kitty: filter 'ansi' expects a color name but 'blue' is a color (use 'blue_name')",
+        ))
+        .stdout(predicate::str::contains("error: kitty: unknown filter 'shout'"))
+        .stdout(predicate::str::contains(
+            "warning: stray.conf.tera: not registered in templates.toml",
+        ))
+        .stdout(predicate::str::contains("3 error(s), 1 warning(s)"));
+}
+
+#[test]
+fn lint_warnings_alone_succeed() {
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("lint-warnings", &[("kitty", "{{ primary | hex }}")]);
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .arg("lint")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "warning: palettes/mocha.toml: colors not used by any template: base, mauve",
+        ));
+}
+
+// =============================================================================
+// DOCTOR command tests
+// =============================================================================
+
+/// A PATH dir with a stub for every external tool `cfg doctor` looks for
+fn fake_tools_path(test: &str) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = isolated_cfg_dir(&format!("{}-bin", test));
+    for tool in [
+        "magick",
+        "fc-list",
+        "fc-match",
+        "hyprctl",
+        "kitty",
+        "systemctl",
+    ] {
+        let path = format!("{}/{}", dir, tool);
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn doctor_clean_setup_succeeds() {
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("doctor-clean", &[("kitty", "{{ blue | hex }}")]);
+    std::fs::write(
+        format!("{}/config.toml", cfg_dir),
+        "flavor = \"mocha\"\nprimary = \"blue\"\n\n[vars]\ngaps = 8\n",
+    )
+    .unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .env("PATH", fake_tools_path("doctor-clean"))
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout("No problems found\n");
+}
+
+#[test]
+fn doctor_reports_config_registry_and_tool_problems() {
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("doctor-problems", &[("kitty", "{{ blue | hex }}")]);
+    std::fs::write(
+        format!("{}/config.toml", cfg_dir),
+        "flavor = \"mocha\"\nprimary = \"peach\"\nprimry = \"blue\"\n",
+    )
+    .unwrap();
+    let registry = format!("{}/templates.toml", cfg_dir);
+    let mut templates = std::fs::read_to_string(&registry).unwrap();
+    templates.push_str("ghost = { path = \"ghost.conf.tera\", reloda = \"true\" }\n");
+    std::fs::write(&registry, templates).unwrap();
+    let empty_path = isolated_cfg_dir("doctor-problems-bin");
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .env("PATH", &empty_path)
+        .arg("doctor")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "error: config.toml: primary 'peach' is not a color in the mocha palette",
+        ))
+        .stdout(predicate::str::contains(
+            "warning: config.toml: unknown key 'primry'",
+        ))
+        .stdout(predicate::str::contains(
+            "error: ghost: template file not found: ghost.conf.tera",
+        ))
+        .stdout(predicate::str::contains(
+            "warning: templates.toml: unknown key 'templates.ghost.reloda'",
+        ))
+        .stdout(predicate::str::contains(
+            "warning: magick: not found in PATH",
+        ))
+        .stdout(predicate::str::contains("2 error(s), 8 warning(s)"));
+
+    std::fs::write(
+        format!("{}/config.toml", cfg_dir),
+        "flavor = \"frappe\"\nprimary = \"blue\"\n",
+    )
+    .unwrap();
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .env("PATH", fake_tools_path("doctor-problems"))
+        .arg("doctor")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "error: config.toml: flavor 'frappe' has no palette (available: mocha)",
+        ));
+}
+
+#[test]
+fn broken_config_is_reported_not_replaced() {
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("broken-config", &[("kitty", "{{ blue | hex }}")]);
+    let config_path = format!("{}/config.toml", cfg_dir);
+    let broken = "flavor = \"mocha\"\nprimary = \"blue\n";
+    std::fs::write(&config_path, broken).unwrap();
+
+    for args in [
+        vec!["theme", "--set", "flavor=latte"],
+        vec!["update"],
+        vec!["profile", "save", "snapshot"],
+    ] {
+        cfg()
+            .env("CFG_DIR", &cfg_dir)
+            .env("DOTFILES_DIR", &dotfiles_dir)
+            .args(&args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Failed to parse config file"));
+    }
+    assert_eq!(std::fs::read_to_string(&config_path).unwrap(), broken);
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .arg("doctor")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "error: config.toml: TOML parse error",
+        ));
+}
+
 // =============================================================================
 // LEDS command tests
 // =============================================================================
@@ -461,6 +1549,111 @@ fn font_list_json_outputs_valid_structure() {
         .stdout(predicate::str::contains("\"nerd_font\""));
 }
 
+// =============================================================================
+// CONFIG command tests
+// =============================================================================
+
+#[test]
+fn config_set_get_and_unset_array_paths() {
+    let dir = isolated_cfg_dir("config-paths");
+    let run = |args: &[&str]| {
+        let output = cfg().env("CFG_DIR", &dir).args(args).output().unwrap();
+        assert!(
+            output.status.success(),
+            "{:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    run(&[
+        "config",
+        "--set",
+        r#"wallpaper.sources[0]={ name = "APOD", path = "~/APOD" }"#,
+    ]);
+    assert_eq!(
+        run(&[
+            "config",
+            "--set",
+            "wallpaper.sources[0].path=~/Pictures/APOD"
+        ]),
+        "wallpaper.sources[0].path=~/Pictures/APOD\n"
+    );
+    assert_eq!(
+        run(&["config", "--get", "wallpaper.sources[0].path"]),
+        "~/Pictures/APOD\n"
+    );
+    // Same settings are reachable from `cfg theme`
+    assert_eq!(
+        run(&["theme", "--get", "wallpaper.sources[0].name"]),
+        "APOD\n"
+    );
+
+    run(&["config", "--set", "leds.speed=20"]);
+    assert_eq!(
+        run(&["config", "--unset", "leds.speed"]),
+        "leds.speed=175 (default)\n"
+    );
+
+    let dump: serde_json::Value =
+        serde_json::from_str(&run(&["config", "--dump", "--json"])).unwrap();
+    assert_eq!(dump["wallpaper"]["sources"][0]["name"], "APOD");
+    assert_eq!(dump["leds"]["speed"], 175);
+    assert!(run(&["config"]).contains("[[wallpaper.sources]]\nname = \"APOD\""));
+
+    run(&["config", "--unset", "wallpaper.sources[0]"]);
+    assert!(!run(&["config"]).contains("wallpaper.sources"));
+}
+
+#[test]
+fn config_rejects_unknown_and_mistyped_values() {
+    let dir = isolated_cfg_dir("config-errors");
+    for (args, message) in [
+        (
+            vec!["config", "--set", "fonts.bogus=1"],
+            "Unknown config key: fonts.bogus",
+        ),
+        (
+            vec!["config", "--set", "leds.brightness=bright"],
+            "Invalid number: bright",
+        ),
+        (
+            vec!["config", "--set", "wallpaper.gravity=middle"],
+            "Invalid gravity 'middle'",
+        ),
+        (vec!["config", "--get", "nope"], "Unknown config key: nope"),
+        (vec!["config", "--unset", "flavor"], "Can't unset 'flavor'"),
+    ] {
+        cfg()
+            .env("CFG_DIR", &dir)
+            .args(&args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(message));
+    }
+    cfg()
+        .env("CFG_DIR", &dir)
+        .args(["config", "--get", "flavor", "--unset", "primary"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    for mode in [
+        vec!["--get", "flavor"],
+        vec!["--set", "primary=blue"],
+        vec!["--unset", "primary"],
+    ] {
+        cfg()
+            .env("CFG_DIR", &dir)
+            .arg("config")
+            .args(&mode)
+            .arg("--json")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+}
+
 // =============================================================================
 // THEME command tests
 // =============================================================================
@@ -528,6 +1721,113 @@ fn theme_get_set_list_all_conflict() {
         .stderr(predicate::str::contains("cannot be used with"));
 }
 
+#[test]
+fn theme_set_vars_reach_templates() {
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("theme-set-vars", &[("bar", "gaps={{ vars.gaps }}\n")]);
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["theme", "--set", "vars.gaps=8", "--apply"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("vars.gaps=8"));
+
+    let config = std::fs::read_to_string(format!("{}/config.toml", cfg_dir)).unwrap();
+    assert!(
+        config.contains("[vars]\ngaps = 8"),
+        "config was: {}",
+        config
+    );
+    assert_eq!(
+        std::fs::read_to_string(format!("{}/bar.conf", dotfiles_dir)).unwrap(),
+        "gaps=8\n"
+    );
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .args(["theme", "--get", "vars.gaps"])
+        .assert()
+        .success()
+        .stdout("8\n");
+}
+
+#[test]
+fn theme_set_apply_repicks_wallpaper_after_failed_reload() {
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("theme-apply-reload", &[("bar", "{{ primary | hex }}\n")]);
+    std::fs::write(
+        format!("{}/templates.toml", cfg_dir),
+        "[templates]\nbar = { path = \"bar.conf.tera\", reload = \"exit 7\" }\n",
+    )
+    .unwrap();
+    std::fs::write(
+        format!("{}/config.toml", cfg_dir),
+        format!(
+            "flavor = \"mocha\"\nprimary = \"blue\"\n\n[wallpaper]\nmode = \"picker\"\nsource_dir = \"{}/missing\"\n",
+            cfg_dir
+        ),
+    )
+    .unwrap();
+
+    // The failed reload still sets the exit code, but only after the
+    // wallpaper step has run.
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["theme", "--set", "primary=mauve", "--apply"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("wallpaper re-pick failed"));
+    assert_eq!(
+        std::fs::read_to_string(format!("{}/bar.conf", dotfiles_dir)).unwrap(),
+        "cba6f7\n"
+    );
+}
+
+#[test]
+fn theme_check_contrast_flags_pairs_below_aa() {
+    let cfg_dir = isolated_cfg_dir("theme-contrast");
+    std::fs::create_dir_all(format!("{}/palettes", cfg_dir)).unwrap();
+    std::fs::write(
+        format!("{}/palettes/latte.toml", cfg_dir),
+        "[colors]\nbase = \"eff1f5\"\ntext = \"4c4f69\"\n\
+         yellow = \"df8e1d\"\nmauve = \"8839ef\"\n",
+    )
+    .unwrap();
+    let config = format!("{}/config.toml", cfg_dir);
+
+    std::fs::write(
+        &config,
+        "flavor = \"latte\"\nprimary = \"mauve\"\nsecondary = \"mauve\"\n",
+    )
+    .unwrap();
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .args(["theme", "--check-contrast"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("mauve on base"))
+        .stdout(predicate::str::contains("base on mauve"))
+        .stdout(predicate::str::contains("below AA").not());
+
+    std::fs::write(
+        &config,
+        "flavor = \"latte\"\nprimary = \"yellow\"\nsecondary = \"mauve\"\n",
+    )
+    .unwrap();
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .args(["theme", "--check-contrast"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "primary    yellow on base         2.31:1",
+        ))
+        .stdout(predicate::str::contains("text on yellow         3.05:1"))
+        .stderr(predicate::str::contains("2 pair(s) below WCAG AA"));
+}
+
 #[test]
 fn theme_interactive_rejects_get() {
     cfg()
@@ -555,6 +1855,391 @@ fn theme_interactive_rejects_list() {
         .stderr(predicate::str::contains("cannot be used with"));
 }
 
+// =============================================================================
+// PALETTE command tests
+// =============================================================================
+
+#[test]
+fn palette_import_creates_a_usable_flavor() {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        "palette-import",
+        &[(
+            "bar",
+            "{{ base | hex }} {{ peach | hex }} {{ primary | hex }}\n",
+        )],
+    );
+    let theme = format!("{}/Nord Theme.conf", dotfiles_dir);
+    std::fs::write(
+        &theme,
+        "## name: Nord\nbackground #2e3440\nforeground #d8dee9\n\
+         color0 #3b4252\ncolor1 #bf616a\ncolor2 #a3be8c\ncolor3 #ebcb8b\n\
+         color4 #81a1c1\ncolor5 #b48ead\ncolor6 #88c0d0\ncolor7 #e5e9f0\n",
+    )
+    .unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["theme", "--set", "flavor=nord-theme"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Invalid flavor 'nord-theme'. Valid: [\"mocha\"]",
+        ));
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .args(["palette", "import", &theme])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Imported Nord as palette 'nord-theme'",
+        ))
+        .stdout(predicate::str::contains("Derived: mantle, crust, surface0"))
+        .stdout(predicate::str::contains(
+            "cfg theme --set flavor=nord-theme",
+        ));
+
+    let palette = std::fs::read_to_string(format!("{}/palettes/nord-theme.toml", cfg_dir)).unwrap();
+    assert!(palette.starts_with("# Nord, imported from "), "{}", palette);
+    assert!(palette.contains("\nbase = \"2e3440\"\n"), "{}", palette);
+    assert!(palette.contains("\nmauve = \"b48ead\"\n"), "{}", palette);
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["theme", "--set", "flavor=nord-theme", "--apply"])
+        .assert()
+        .success();
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["config", "--set", "primary=mauve"])
+        .assert()
+        .success();
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .arg("update")
+        .assert()
+        .success();
+    let rendered = std::fs::read_to_string(format!("{}/bar.conf", dotfiles_dir)).unwrap();
+    assert!(rendered.starts_with("2e3440 "), "{}", rendered);
+    assert!(rendered.ends_with(" b48ead\n"), "{}", rendered);
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .args(["palette", "import", &theme])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Palette 'nord-theme' already exists (use --force to replace it)",
+        ));
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .args(["palette", "import", &theme, "--name", "../nord"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid palette name '../nord'"));
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .args([
+            "palette",
+            "import",
+            &theme,
+            "--format",
+            "alacritty",
+            "--name",
+            "x",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "(alacritty scheme): TOML parse error",
+        ));
+}
+
+#[test]
+fn palette_from_wallpaper_generates_and_sets_flavor() {
+    use std::os::unix::fs::PermissionsExt;
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("palette-wallpaper", &[("bar", "{{ base | hex }}\n")]);
+    std::fs::write(
+        format!("{}/config.toml", cfg_dir),
+        "flavor = \"mocha\"\nprimary = \"blue\"\n",
+    )
+    .unwrap();
+    // A stand-in magick printing the histogram of a dark teal image
+    let bin = isolated_cfg_dir("palette-wallpaper-bin");
+    std::fs::write(
+        format!("{}/magick", bin),
+        "#!/bin/sh\necho '  500: (15,59,58) #0F3B3A srgb(15,59,58)'\n\
+         echo '  120: (232,116,42) #E8742A srgb(232,116,42)'\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(
+        format!("{}/magick", bin),
+        std::fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+    let image = format!("{}/sunset.jpg", dotfiles_dir);
+    std::fs::write(&image, "").unwrap();
+    let path = format!("{}:{}", bin, std::env::var("PATH").unwrap());
+    let run = |args: &[&str]| {
+        cfg()
+            .env("CFG_DIR", &cfg_dir)
+            .env("DOTFILES_DIR", &dotfiles_dir)
+            .env("PATH", &path)
+            .env("HOME", &dotfiles_dir)
+            .args(args)
+            .assert()
+    };
+
+    run(&["palette", "from-wallpaper", &image])
+        .success()
+        .stdout(predicate::str::contains("Generated palette 'dynamic' from"))
+        .stdout(predicate::str::contains("cfg theme --set flavor=dynamic"));
+    let palette = std::fs::read_to_string(format!("{}/palettes/dynamic.toml", cfg_dir)).unwrap();
+    assert!(palette.contains("\ncrust = "), "{}", palette);
+    assert_eq!(palette.matches(" = \"").count(), 26, "{}", palette);
+
+    // Regenerating replaces it and can switch to it
+    run(&[
+        "palette",
+        "from-wallpaper",
+        &image,
+        "--light",
+        "--set-flavor",
+        "--apply",
+    ])
+    .success()
+    .stdout(predicate::str::contains("flavor=dynamic"));
+    let rendered = std::fs::read_to_string(format!("{}/bar.conf", dotfiles_dir)).unwrap();
+    let red = u8::from_str_radix(&rendered[..2], 16).unwrap();
+    assert!(red > 0xe0, "light base expected: {}", rendered);
+    run(&["theme", "--check-contrast"]).success();
+
+    // Shipped palettes aren't replaced without --force
+    run(&["palette", "from-wallpaper", &image, "--name", "mocha"])
+        .failure()
+        .stderr(predicate::str::contains(
+            "Palette 'mocha' already exists and wasn't generated from a wallpaper",
+        ));
+    // No path and no current wallpaper
+    run(&["palette", "from-wallpaper"])
+        .failure()
+        .stderr(predicate::str::contains("no current wallpaper"));
+}
+
+// =============================================================================
+// PROFILE command tests
+// =============================================================================
+
+#[test]
+fn profile_save_list_use_and_diff() {
+    let dir = isolated_cfg_dir("profile-roundtrip");
+    std::fs::create_dir_all(format!("{}/palettes", dir)).unwrap();
+    for flavor in ["mocha", "latte"] {
+        std::fs::write(
+            format!("{}/palettes/{}.toml", dir, flavor),
+            "[colors]\nblue = \"89b4fa\"\n",
+        )
+        .unwrap();
+    }
+    let run = |args: &[&str]| {
+        let output = cfg().env("CFG_DIR", &dir).args(args).output().unwrap();
+        assert!(
+            output.status.success(),
+            "{:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    assert!(run(&["profile", "list"]).contains("No profiles saved"));
+    run(&["profile", "save", "evening"]);
+    run(&["theme", "--set", "flavor=latte"]);
+    run(&["theme", "--set", "primary=peach"]);
+    run(&["profile", "save", "work"]);
+
+    assert_eq!(run(&["profile", "list"]), "  evening\n* work\n");
+    assert_eq!(
+        run(&["profile", "diff", "evening", "work"]),
+        "flavor: mocha → latte\nprimary: blue → peach\n"
+    );
+
+    assert!(run(&["profile", "use", "evening"]).contains("Switched to profile 'evening'"));
+    assert_eq!(run(&["theme", "--get", "flavor"]), "mocha\n");
+    assert_eq!(run(&["profile", "list"]), "* evening\n  work\n");
+    assert_eq!(run(&["profile", "diff", "evening"]), "No differences\n");
+}
+
+#[test]
+fn profile_use_apply_repicks_wallpaper_when_picker_active() {
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("profile-repick", &[("bar", "accent={{ primary | hex }}\n")]);
+    std::fs::write(
+        format!("{}/config.toml", cfg_dir),
+        format!(
+            "flavor = \"mocha\"\nprimary = \"blue\"\n\n[wallpaper]\nmode = \"picker\"\nsource_dir = \"{}/missing\"\n",
+            cfg_dir
+        ),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        cfg()
+            .env("CFG_DIR", &cfg_dir)
+            .env("DOTFILES_DIR", &dotfiles_dir)
+            .args(args)
+            .assert()
+    };
+
+    run(&["profile", "save", "day"]);
+    run(&["theme", "--set", "primary=mauve"]);
+    // Only the accent differs, but the picker chooses by accent
+    run(&["profile", "use", "day", "--apply"])
+        .success()
+        .stderr(predicate::str::contains("wallpaper re-pick failed"));
+    assert_eq!(
+        std::fs::read_to_string(format!("{}/bar.conf", dotfiles_dir)).unwrap(),
+        "accent=89b4fa\n"
+    );
+}
+
+#[test]
+fn profile_use_unknown_fails() {
+    let dir = isolated_cfg_dir("profile-unknown");
+    cfg()
+        .env("CFG_DIR", &dir)
+        .args(["profile", "use", "nope"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown profile: nope"));
+    cfg()
+        .env("CFG_DIR", &dir)
+        .args(["profile", "save", "../x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid profile name"));
+}
+
+// =============================================================================
+// SCHEDULE command tests
+// =============================================================================
+
+#[test]
+fn schedule_once_applies_active_rule() {
+    let (cfg_dir, dotfiles_dir) =
+        template_fixture("schedule-once", &[("bar", "accent={{ primary | hex }}\n")]);
+    std::fs::write(
+        format!("{}/config.toml", cfg_dir),
+        "flavor = \"mocha\"\nprimary = \"blue\"\n\n[[schedule.rules]]\nat = \"00:00\"\nset = { primary = \"mauve\" }\n",
+    )
+    .unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["schedule", "--once"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Schedule: 00:00 → primary=mauve"));
+
+    assert_eq!(
+        std::fs::read_to_string(format!("{}/bar.conf", dotfiles_dir)).unwrap(),
+        "accent=cba6f7\n"
+    );
+    let config = std::fs::read_to_string(format!("{}/config.toml", cfg_dir)).unwrap();
+    assert!(
+        config.contains("primary = \"mauve\""),
+        "config was: {}",
+        config
+    );
+    assert!(
+        config.contains("[[schedule.rules]]"),
+        "config was: {}",
+        config
+    );
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .args(["schedule", "--list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "* 00:00  00:00 → primary=mauve\n",
+        ))
+        .stdout(predicate::str::contains("Next: 00:00 at 00:00"));
+}
+
+#[test]
+fn schedule_once_repicks_wallpaper_like_theme_apply() {
+    let (cfg_dir, dotfiles_dir) = template_fixture(
+        "schedule-repick",
+        &[("bar", "accent={{ primary | hex }}\n")],
+    );
+    std::fs::write(
+        format!("{}/config.toml", cfg_dir),
+        format!(
+            "flavor = \"mocha\"\nprimary = \"blue\"\n\n[wallpaper]\nmode = \"picker\"\nsource_dir = \"{}/missing\"\n\n[[schedule.rules]]\nat = \"00:00\"\nset = {{ primary = \"mauve\" }}\n",
+            cfg_dir
+        ),
+    )
+    .unwrap();
+
+    cfg()
+        .env("CFG_DIR", &cfg_dir)
+        .env("DOTFILES_DIR", &dotfiles_dir)
+        .args(["schedule", "--once"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("wallpaper re-pick failed"));
+    assert_eq!(
+        std::fs::read_to_string(format!("{}/bar.conf", dotfiles_dir)).unwrap(),
+        "accent=cba6f7\n"
+    );
+}
+
+#[test]
+fn schedule_rejects_invalid_rules() {
+    let dir = isolated_cfg_dir("schedule-invalid");
+    let config_path = format!("{}/config.toml", dir);
+
+    cfg()
+        .env("CFG_DIR", &dir)
+        .args(["schedule", "--list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No schedule rules"));
+
+    std::fs::write(
+        &config_path,
+        "flavor = \"mocha\"\nprimary = \"blue\"\n\n[[schedule.rules]]\nat = \"sunset-30m\"\nprofile = \"evening\"\n",
+    )
+    .unwrap();
+    cfg()
+        .env("CFG_DIR", &dir)
+        .args(["schedule", "--once"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "needs schedule.latitude and schedule.longitude",
+        ));
+
+    std::fs::write(
+        &config_path,
+        "flavor = \"mocha\"\nprimary = \"blue\"\n\n[[schedule.rules]]\nat = \"noon\"\nprofile = \"evening\"\n",
+    )
+    .unwrap();
+    cfg()
+        .env("CFG_DIR", &dir)
+        .args(["schedule", "--list"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid schedule time 'noon'"));
+}
+
 // =============================================================================
 // WALLPAPER command tests
 // =============================================================================